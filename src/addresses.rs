@@ -1,18 +1,24 @@
 use super::types::Protocol;
+use crate::registry::registry;
 
 use ethers::types::Address;
 
 use once_cell::sync::Lazy;
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::Path;
 
 pub fn lookup(address: Address) -> String {
-    ADDRESSBOOK
-        .get(&address)
-        .unwrap_or(&format!("{:?}", &address))
-        .clone()
+    registry()
+        .label(&address)
+        .cloned()
+        .unwrap_or_else(|| format!("{:?}", &address))
+}
+
+/// Returns the address of a key contract, unless it was overridden in the
+/// registry under the same name
+fn contract(name: &str, default: &str) -> Address {
+    registry()
+        .contract(name)
+        .unwrap_or_else(|| parse_address(default))
 }
 
 fn insert_many<T: Clone>(
@@ -26,17 +32,16 @@ fn insert_many<T: Clone>(
     map
 }
 
-// reads line-separated addresses from a file path and returns them as a vector
-fn read_addrs<P>(path: P) -> Vec<Address>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(path).unwrap();
-    let lines = io::BufReader::new(file).lines();
-    lines.map(|line| parse_address(&line.unwrap())).collect()
+// reads line-separated addresses from an embedded file
+fn read_addrs(contents: &str) -> impl Iterator<Item = Address> + '_ {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| parse_address(line.trim()))
 }
 
-// Protocol Addrs
+// Protocol Addrs. These are the embedded defaults, use `registry()` to get
+// them along with any which were loaded at runtime.
 pub static PROTOCOLS: Lazy<HashMap<Address, Protocol>> = Lazy::new(|| {
     let map = HashMap::new();
     let map = insert_many(
@@ -65,15 +70,15 @@ pub static PROTOCOLS: Lazy<HashMap<Address, Protocol>> = Lazy::new(|| {
         Protocol::Sushiswap,
     );
 
-    for addr in read_addrs("./res/v1pairs.csv") {
+    for addr in read_addrs(include_str!("../res/v1pairs.csv")) {
         map.insert(addr, Protocol::UniswapV1);
     }
 
-    for addr in read_addrs("./res/v2pairs.csv") {
+    for addr in read_addrs(include_str!("../res/v2pairs.csv")) {
         map.insert(addr, Protocol::Uniswap);
     }
 
-    for addr in read_addrs("./res/sushipairs.csv") {
+    for addr in read_addrs(include_str!("../res/sushipairs.csv")) {
         map.insert(addr, Protocol::Sushiswap);
     }
 
@@ -95,6 +100,27 @@ pub static PROTOCOLS: Lazy<HashMap<Address, Protocol>> = Lazy::new(|| {
         Protocol::Sushiswap,
     );
 
+    let map = insert_many(
+        map,
+        &[
+            "0x11111254369792b2ca5d084ab5eea397ca8fa48b", // 1inch
+            "0x111111125434b319222cdbf8c261674adb56f3ae", // 1inch v2
+            "0x11111112542d85b3ef69ae05771c2dccff4faa26", // 1inch v3 router
+        ],
+        Protocol::OneInch,
+    );
+
+    let map = insert_many(
+        map,
+        &[
+            "0x9509665d015bfe3c77aa5ad6ca20c8afa1d98989", // paraswap
+            "0x86969d29F5fd327E1009bA66072BE22DB6017cC6", // paraswap v2
+            "0xf90e98f3d8dce44632e5020abf2e122e0f99dfab", // paraswap v3
+            "0x1bd435f3c054b6e901b7b108a0ab7617c808677b", // paraswap p4
+        ],
+        Protocol::Paraswap,
+    );
+
     insert_many(
         map,
         &["0xfe01821Ca163844203220cd08E4f2B2FB43aE4E4"], // 0x: BalancerBridge
@@ -102,7 +128,10 @@ pub static PROTOCOLS: Lazy<HashMap<Address, Protocol>> = Lazy::new(|| {
     )
 });
 
-// Addresses which should be ignored when used as the target of a transaction
+// Addresses which may be ignored when used as the target of a transaction.
+// This is opt-in, see `BatchInspector::with_filter`. These are the embedded
+// defaults, use `registry()` to get them along with any which were loaded
+// at runtime.
 pub static FILTER: Lazy<HashSet<Address>> = Lazy::new(|| {
     let mut set = HashSet::new();
     // 1inch
@@ -142,41 +171,223 @@ pub static FILTER: Lazy<HashSet<Address>> = Lazy::new(|| {
     set
 });
 
+// Implementations which many unrelated contracts delegate to, so sharing them
+// does not make two senders the same searcher. These are the embedded
+// defaults, use `registry()` to get them along with any which were loaded
+// at runtime.
+pub static SHARED: Lazy<HashSet<Address>> = Lazy::new(|| {
+    let mut set = HashSet::new();
+    // Gnosis Safe master copies
+    set.insert(parse_address("0xb6029ea3b2c51d09a50b53ca8012feeb05bda35a"));
+    set.insert(parse_address("0x34cfac646f301356faa8b21e94227e3583fe3f5f"));
+    set.insert(parse_address("0x6851d6fdfafd08c0295c392436245e5bc78b0185"));
+    set.insert(parse_address("0xd9db270c1b5e3bd161e8c8503c55ceabee709552"));
+    set.insert(parse_address("0x3e5c63644e683549055b9be8653de26e0b4cd36e"));
+    // Maker's DSProxy actions
+    set.insert(parse_address("0x82ecd135dce65fbc6dbdd0e4237e0af93ffd5038"));
+    set
+});
+
+pub static UNISWAP_FACTORY: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "UNISWAP_FACTORY",
+        "0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f",
+    )
+});
+
+pub static SUSHISWAP_FACTORY: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "SUSHISWAP_FACTORY",
+        "0xc0aee478e3658e2610c5f7a4a2e1777ce9e4f2ac",
+    )
+});
+
+pub static SAKESWAP_FACTORY: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "SAKESWAP_FACTORY",
+        "0x75e48c954594d64ef9613aeef97ad85370f13807",
+    )
+});
+
 pub static ZEROX: Lazy<Address> =
-    Lazy::new(|| parse_address("0x61935cbdd02287b511119ddb11aeb42f1593b7ef"));
+    Lazy::new(|| contract("ZEROX", "0x61935cbdd02287b511119ddb11aeb42f1593b7ef"));
+
+pub static ZEROX_EXCHANGE_PROXY: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "ZEROX_EXCHANGE_PROXY",
+        "0xdef1c0ded9bec7f1a1670819833240f027b25eff",
+    )
+});
+
+/// The 0x staking proxy, which collects the protocol fees of order fills
+pub static ZEROX_STAKING: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "ZEROX_STAKING",
+        "0xa26e80e7dea86279c6d778d702cc413e6cffa777",
+    )
+});
 
 pub static DYDX: Lazy<Address> =
-    Lazy::new(|| parse_address("0x1e0447b19bb6ecfdae1e4ae1694b0c3659614e4e"));
+    Lazy::new(|| contract("DYDX", "0x1e0447b19bb6ecfdae1e4ae1694b0c3659614e4e"));
 
-pub static BALANCER_PROXY: Lazy<Address> =
-    Lazy::new(|| parse_address("0x3E66B66Fd1d0b02fDa6C811Da9E0547970DB2f21"));
+pub static BALANCER_PROXY: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "BALANCER_PROXY",
+        "0x3E66B66Fd1d0b02fDa6C811Da9E0547970DB2f21",
+    )
+});
+
+pub static KYBER_PROXY: Lazy<Address> =
+    Lazy::new(|| contract("KYBER_PROXY", "0x9AAb3f75489902f3a48495025729a0AF77d4b11e"));
+
+pub static BANCOR_NETWORK: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "BANCOR_NETWORK",
+        "0x2F9EC37d6CcFFf1caB21733BdaDEdE11c823cCB0",
+    )
+});
 
-pub static CURVE_REGISTRY: Lazy<Address> =
-    Lazy::new(|| parse_address("0x7D86446dDb609eD0F5f8684AcF30380a356b2B4c"));
+pub static SYNTHETIX: Lazy<Address> =
+    Lazy::new(|| contract("SYNTHETIX", "0xC011a73ee8576Fb46F5E1c5751cA3B9Fe0af2a6F"));
+
+/// The token proxy of each Synth, by its currency key
+pub static SYNTHS: Lazy<HashMap<String, Address>> = Lazy::new(|| {
+    [
+        ("sUSD", "0x57Ab1ec28D129707052df4dF418D58a2D46d5f51"),
+        ("sETH", "0x5e74C9036fb86BD7eCdcb084a0673EFc32eA31cb"),
+        ("sBTC", "0xfE18be6b3Bd88A2D2A7f928d00292E7a9963CfC6"),
+        ("sEUR", "0xD71eCFF9342A5Ced620049e616c5035F1dB98620"),
+        ("sLINK", "0xbBC455cb4F1B9e4bFC4B73970d360c8f032EfEE6"),
+        ("sDEFI", "0xe1aFe1Fd76Fd88f78cBf599ea1846231B8bA3B6B"),
+    ]
+    .iter()
+    .map(|(key, synth)| (key.to_string(), parse_address(synth)))
+    .collect()
+});
+
+pub static CURVE_REGISTRY: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "CURVE_REGISTRY",
+        "0x7D86446dDb609eD0F5f8684AcF30380a356b2B4c",
+    )
+});
 
 pub static CETH: Lazy<Address> =
-    Lazy::new(|| parse_address("4Ddc2D193948926D02f9B1fE9e1daa0718270ED5"));
+    Lazy::new(|| contract("CETH", "4Ddc2D193948926D02f9B1fE9e1daa0718270ED5"));
 
 pub static COMPTROLLER: Lazy<Address> =
-    Lazy::new(|| parse_address("3d9819210A31b4961b30EF54bE2aeD79B9c9Cd3B"));
+    Lazy::new(|| contract("COMPTROLLER", "3d9819210A31b4961b30EF54bE2aeD79B9c9Cd3B"));
 
 pub static COMP_ORACLE: Lazy<Address> =
-    Lazy::new(|| parse_address("922018674c12a7F0D394ebEEf9B58F186CdE13c1"));
+    Lazy::new(|| contract("COMP_ORACLE", "922018674c12a7F0D394ebEEf9B58F186CdE13c1"));
+
+pub static CREAM_COMPTROLLER: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "CREAM_COMPTROLLER",
+        "3d5BC3c8d13dcB8bF317092d84783c2697AE9258",
+    )
+});
+
+pub static CRETH: Lazy<Address> =
+    Lazy::new(|| contract("CRETH", "D06527D5e56A3495252A528C4987003b712860eE"));
+
+pub static IRONBANK_COMPTROLLER: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "IRONBANK_COMPTROLLER",
+        "AB1c342C7bf5Ec5F02ADEA1c2270670bCa144CbB",
+    )
+});
+
+pub static AAVE_LENDING_POOL: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "AAVE_LENDING_POOL",
+        "398eC7346DcD622eDc5ae82352F02bE94C62d119",
+    )
+});
+
+pub static AAVE_LENDING_POOL_V2: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "AAVE_LENDING_POOL_V2",
+        "7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A9",
+    )
+});
 
-pub static AAVE_LENDING_POOL: Lazy<Address> =
-    Lazy::new(|| parse_address("398eC7346DcD622eDc5ae82352F02bE94C62d119"));
+pub static AAVE_LENDING_POOL_CORE: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "AAVE_LENDING_POOL_CORE",
+        "3dfd23a6c5e8bbcfc9581d2e864a68feb6a076d3",
+    )
+});
 
-pub static AAVE_LENDING_POOL_CORE: Lazy<Address> =
-    Lazy::new(|| parse_address("3dfd23a6c5e8bbcfc9581d2e864a68feb6a076d3"));
+pub static MAKER_VAT: Lazy<Address> =
+    Lazy::new(|| contract("MAKER_VAT", "35D1b3F3D7966A1DFe207aa4514C12a259A0492B"));
+
+pub static MAKER_CAT: Lazy<Address> =
+    Lazy::new(|| contract("MAKER_CAT", "a5679C04fc3d9d8b0AaB1F0ab83555b301cA70Ea"));
+
+pub static MAKER_DOG: Lazy<Address> =
+    Lazy::new(|| contract("MAKER_DOG", "135954d155898D42C90D2a57824C690e0c7BEf1B"));
+
+/// The collateral token behind each MakerDAO ilk (collateral type)
+pub static MAKER_ILKS: Lazy<HashMap<String, Address>> = Lazy::new(|| {
+    [
+        ("ETH-A", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+        ("ETH-B", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+        ("ETH-C", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+        ("WBTC-A", "0x2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599"),
+        ("RENBTC-A", "0xEB4C2781e4ebA804CE9a9803C67d0893436bB27D"),
+        ("USDC-A", "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+        ("USDC-B", "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+        ("USDT-A", "0xdAC17F958D2ee523a2206206994597C13D831ec7"),
+        ("TUSD-A", "0x0000000000085d4780B73119b644AE5ecd22b376"),
+        ("PAXUSD-A", "0x8E870D67F660D95d5be530380D0eC0bd388289E1"),
+        ("GUSD-A", "0x056Fd409E1d7A124BD7017459dFEa2F387b6d5Cd"),
+        ("BAT-A", "0x0D8775F648430679A709E98d2b0Cb6250d2887EF"),
+        ("KNC-A", "0xdd974D5C2e2928deA5F71b9825b8b646686BD200"),
+        ("ZRX-A", "0xE41d2489571d322189246DaFA5ebDe1F4699F498"),
+        ("MANA-A", "0x0F5D2fB29fb7d3CFeE444a200298f468908cC942"),
+        ("COMP-A", "0xc00e94Cb662C3520282E6f5717214004A7f26888"),
+        ("LRC-A", "0xBBbbCA6A901c926F240b89EacB641d8Aec7AEafD"),
+        ("LINK-A", "0x514910771AF9Ca656af840dff83E8264EcF986CA"),
+        ("BAL-A", "0xba100000625a3754423978a60c9317c58a424e3D"),
+        ("YFI-A", "0x0bc529c00C6401aEF6D220BE8C6Ea1667F6Ad93e"),
+        ("UNI-A", "0x1f9840a85d5aF5bf1D1762F925BDADdC4201F984"),
+        ("AAVE-A", "0x7Fc66500c84A76Ad7e9c93437bFc5Ac33E2DDaE9"),
+    ]
+    .iter()
+    .map(|(ilk, gem)| (ilk.to_string(), parse_address(gem)))
+    .collect()
+});
+
+pub static LIQUITY_TROVE_MANAGER: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "LIQUITY_TROVE_MANAGER",
+        "A39739EF8b0231DbFA0DcdA07d7e29faAbCf4bb2",
+    )
+});
+
+pub static LIQUITY_ACTIVE_POOL: Lazy<Address> = Lazy::new(|| {
+    contract(
+        "LIQUITY_ACTIVE_POOL",
+        "DF9Eb223bAFBE5c5271415C75aeCD68C21fE3D7F",
+    )
+});
+
+pub static LUSD: Lazy<Address> =
+    Lazy::new(|| contract("LUSD", "5f98805A4E8be255a32880FDeC7F6728C6568bA0"));
+
+pub static DAI: Lazy<Address> =
+    Lazy::new(|| contract("DAI", "0x6B175474E89094C44DA98B954EEDEAC495271D0F"));
 
 pub static WETH: Lazy<Address> =
-    Lazy::new(|| parse_address("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"));
+    Lazy::new(|| contract("WETH", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"));
 
 pub static ETH: Lazy<Address> =
     Lazy::new(|| parse_address("0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee"));
 
+// Labels of known addresses. These are the embedded defaults, use
+// `registry()` to get them along with any which were loaded at runtime.
 pub static ADDRESSBOOK: Lazy<HashMap<Address, String>> = Lazy::new(|| {
-    // TODO: Read these from a CSV?
     let map: HashMap<Address, String> = [
         // 0x Exchange Proxies
         (
@@ -224,6 +435,10 @@ pub static ADDRESSBOOK: Lazy<HashMap<Address, String>> = Lazy::new(|| {
             "0x3dfd23a6c5e8bbcfc9581d2e864a68feb6a076d3",
             "AAVE: Lending Pool Core",
         ),
+        (
+            "0x7d2768de32b0b80b7a3454c06bdac94a69ddc7a9",
+            "AAVE: Lending Pool V2",
+        ),
         (
             "0xb6ad5fd2698a68917e39216304d4845625da2f57",
             "Balancer: YFI/yyDAI+yUSDC+yUSDT+yTUSD 50/50",
@@ -243,6 +458,35 @@ pub static ADDRESSBOOK: Lazy<HashMap<Address, String>> = Lazy::new(|| {
         ("0xd533a949740bb3306d119cc777fa900ba034cd52", "CRV"),
         ("0x80fb784b7ed66730e8b1dbd9820afd29931aab03", "LEND"),
         ("0x6B175474E89094C44DA98B954EEDEAC495271D0F", "DAI"),
+        (
+            "0x3d5BC3c8d13dcB8bF317092d84783c2697AE9258",
+            "Cream: Comptroller",
+        ),
+        ("0xD06527D5e56A3495252A528C4987003b712860eE", "crETH"),
+        (
+            "0xAB1c342C7bf5Ec5F02ADEA1c2270670bCa144CbB",
+            "Iron Bank: Comptroller",
+        ),
+        ("0x9AAb3f75489902f3a48495025729a0AF77d4b11e", "Kyber: Proxy"),
+        (
+            "0x2F9EC37d6CcFFf1caB21733BdaDEdE11c823cCB0",
+            "Bancor: Network",
+        ),
+        ("0xC011a73ee8576Fb46F5E1c5751cA3B9Fe0af2a6F", "SNX"),
+        ("0x5e74C9036fb86BD7eCdcb084a0673EFc32eA31cb", "sETH"),
+        ("0xfE18be6b3Bd88A2D2A7f928d00292E7a9963CfC6", "sBTC"),
+        ("0x35D1b3F3D7966A1DFe207aa4514C12a259A0492B", "Maker: Vat"),
+        (
+            "0xA39739EF8b0231DbFA0DcdA07d7e29faAbCf4bb2",
+            "Liquity: Trove Manager",
+        ),
+        (
+            "0xDF9Eb223bAFBE5c5271415C75aeCD68C21fE3D7F",
+            "Liquity: Active Pool",
+        ),
+        ("0x5f98805A4E8be255a32880FDeC7F6728C6568bA0", "LUSD"),
+        ("0xa5679C04fc3d9d8b0AaB1F0ab83555b301cA70Ea", "Maker: Cat"),
+        ("0x135954d155898D42C90D2a57824C690e0c7BEf1B", "Maker: Dog"),
         ("0xc00e94cb662c3520282e6f5717214004a7f26888", "COMP"),
         ("0x5d3a536e4d6dbd6114cc1ead35777bab948e3643", "cDAI"),
         ("0x514910771af9ca656af840dff83e8264ecf986ca", "LINK"),
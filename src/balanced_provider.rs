@@ -0,0 +1,423 @@
+use async_trait::async_trait;
+use ethers::{
+    providers::{FromErr, Middleware},
+    types::{
+        Block, BlockId, BlockNumber, Bytes, Filter, Log, NameOrAddress, Trace, Transaction,
+        TransactionReceipt, TransactionRequest, TxHash, H256, U64,
+    },
+};
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+use crate::{metrics, retry_provider::is_retryable};
+
+/// How the node for the next request is picked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Take turns
+    RoundRobin,
+    /// Prefer the node which answered the fastest recently
+    Latency,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Strategy::RoundRobin),
+            "latency" => Ok(Strategy::Latency),
+            _ => Err(format!(
+                "unknown strategy {}, expected `round-robin` or `latency`",
+                s
+            )),
+        }
+    }
+}
+
+/// Spreads the requests over several nodes, and fails over to the next node
+/// if one fails transiently.
+///
+/// Nodes which fall behind the others are skipped until they caught up again,
+/// which [`check_health`](Self::check_health) finds out. Requests which are
+/// not balanced, like watching for new blocks, go to the first node.
+///
+/// Clones share the nodes' health and latencies.
+#[derive(Clone, Debug)]
+pub struct BalancedProvider<M> {
+    nodes: Vec<M>,
+    strategy: Strategy,
+    /// How many blocks a node may be behind the others and still be used
+    max_lag: u64,
+    /// The node whose turn it is, for round-robin
+    next: Arc<AtomicUsize>,
+    state: Arc<Mutex<Vec<NodeState>>>,
+}
+
+#[derive(Clone, Debug)]
+struct NodeState {
+    healthy: bool,
+    /// The latest block of the node, as of the last health check
+    height: Option<u64>,
+    /// Moving average of the node's response times
+    latency: Option<Duration>,
+}
+
+impl Default for NodeState {
+    fn default() -> Self {
+        Self {
+            healthy: true,
+            height: None,
+            latency: None,
+        }
+    }
+}
+
+impl<M: Middleware> BalancedProvider<M>
+where
+    M::Error: 'static,
+{
+    /// Balances the requests over the nodes round-robin. Nodes may be up to 3
+    /// blocks behind.
+    ///
+    /// # Panics
+    ///
+    /// If no nodes are given
+    pub fn new(nodes: Vec<M>) -> Self {
+        assert!(!nodes.is_empty(), "at least one node is required");
+        let state = vec![NodeState::default(); nodes.len()];
+        Self {
+            nodes,
+            strategy: Strategy::RoundRobin,
+            max_lag: 3,
+            next: Arc::new(AtomicUsize::new(0)),
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Sets how the node for the next request is picked
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets how many blocks a node may be behind the others and still be used
+    pub fn with_max_lag(mut self, max_lag: u64) -> Self {
+        self.max_lag = max_lag;
+        self
+    }
+
+    /// Asks all nodes for their latest block, and only keeps using those which
+    /// respond and are at most `max_lag` blocks behind the furthest one
+    pub async fn check_health(&self) {
+        let heights =
+            futures::future::join_all(self.nodes.iter().map(|node| node.get_block_number())).await;
+        let best = heights
+            .iter()
+            .filter_map(|height| height.as_ref().ok())
+            .map(U64::as_u64)
+            .max()
+            .unwrap_or_default();
+
+        let mut state = self.state.lock().unwrap();
+        for (i, (node, height)) in state.iter_mut().zip(heights).enumerate() {
+            match height {
+                Ok(height) => {
+                    let height = height.as_u64();
+                    let lag = best.saturating_sub(height);
+                    if lag > self.max_lag {
+                        log::warn!("node {} is {} blocks behind, skipping it", i, lag);
+                    }
+                    node.height = Some(height);
+                    node.healthy = lag <= self.max_lag;
+                }
+                Err(err) => {
+                    log::warn!("node {} is unreachable, skipping it: {}", i, err);
+                    node.healthy = false;
+                }
+            }
+        }
+        metrics::RPC_HEALTHY_NODES.set(state.iter().filter(|node| node.healthy).count() as i64);
+    }
+
+    /// The nodes to try, in order. If none is healthy, all are tried.
+    fn order(&self) -> Vec<usize> {
+        let state = self.state.lock().unwrap();
+        let mut nodes = (0..self.nodes.len())
+            .filter(|i| state[*i].healthy)
+            .collect::<Vec<_>>();
+        if nodes.is_empty() {
+            nodes = (0..self.nodes.len()).collect();
+        }
+        match self.strategy {
+            Strategy::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed) % nodes.len();
+                nodes.rotate_left(start);
+            }
+            Strategy::Latency => nodes.sort_by_key(|i| state[*i].latency.unwrap_or_default()),
+        }
+        nodes
+    }
+
+    fn record_latency(&self, node: usize, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let node = &mut state[node];
+        node.latency = Some(match node.latency {
+            Some(average) => average.mul_f64(0.8) + latency.mul_f64(0.2),
+            None => latency,
+        });
+    }
+
+    fn mark_unhealthy(&self, node: usize) {
+        self.state.lock().unwrap()[node].healthy = false;
+    }
+
+    /// Sends the request to the first node in order, and to the next one
+    /// whenever a node fails transiently
+    async fn route<'a, T, F, Fut>(&'a self, request: F) -> Result<T, BalancedProviderError<M>>
+    where
+        F: Fn(&'a M) -> Fut + Send,
+        Fut: Future<Output = Result<T, M::Error>> + Send,
+    {
+        let mut order = self.order().into_iter().peekable();
+        loop {
+            let node = order.next().expect("there is at least one node");
+            let start = Instant::now();
+            let err = match request(&self.nodes[node]).await {
+                Ok(res) => {
+                    self.record_latency(node, start.elapsed());
+                    return Ok(res);
+                }
+                Err(err) => err,
+            };
+            if !is_retryable(&err) {
+                return Err(BalancedProviderError::MiddlewareError(err));
+            }
+            // skip it until the next health check
+            self.mark_unhealthy(node);
+            if order.peek().is_none() {
+                return Err(BalancedProviderError::MiddlewareError(err));
+            }
+            log::warn!("node {} failed, failing over: {}", node, err);
+        }
+    }
+}
+
+#[async_trait]
+impl<M: Middleware> Middleware for BalancedProvider<M>
+where
+    M::Error: 'static,
+{
+    type Error = BalancedProviderError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &Self::Inner {
+        &self.nodes[0]
+    }
+
+    async fn get_block_number(&self) -> Result<U64, Self::Error> {
+        self.route(|node| node.get_block_number()).await
+    }
+
+    async fn get_block<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block_hash_or_number: T,
+    ) -> Result<Option<Block<TxHash>>, Self::Error> {
+        let block = block_hash_or_number.into();
+        self.route(|node| node.get_block(block)).await
+    }
+
+    async fn get_block_with_txs<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block_hash_or_number: T,
+    ) -> Result<Option<Block<Transaction>>, Self::Error> {
+        let block = block_hash_or_number.into();
+        self.route(|node| node.get_block_with_txs(block)).await
+    }
+
+    async fn call(
+        &self,
+        tx: &TransactionRequest,
+        block: Option<BlockNumber>,
+    ) -> Result<Bytes, Self::Error> {
+        self.route(|node| node.call(tx, block)).await
+    }
+
+    async fn get_transaction<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<Transaction>, Self::Error> {
+        let hash = transaction_hash.into();
+        self.route(|node| node.get_transaction(hash)).await
+    }
+
+    async fn get_transaction_receipt<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<TransactionReceipt>, Self::Error> {
+        let hash = transaction_hash.into();
+        self.route(|node| node.get_transaction_receipt(hash)).await
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, Self::Error> {
+        self.route(|node| node.get_logs(filter)).await
+    }
+
+    async fn get_code<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        at: T,
+        block: Option<BlockNumber>,
+    ) -> Result<Bytes, Self::Error> {
+        let at = at.into();
+        self.route(|node| node.get_code(at.clone(), block)).await
+    }
+
+    async fn trace_block(&self, block: BlockNumber) -> Result<Vec<Trace>, Self::Error> {
+        self.route(|node| node.trace_block(block)).await
+    }
+
+    async fn trace_transaction(&self, hash: H256) -> Result<Vec<Trace>, Self::Error> {
+        self.route(|node| node.trace_transaction(hash)).await
+    }
+
+    async fn parity_block_receipts<T: Into<BlockNumber> + Send + Sync>(
+        &self,
+        block: T,
+    ) -> Result<Vec<TransactionReceipt>, Self::Error> {
+        let block = block.into();
+        self.route(|node| node.parity_block_receipts(block)).await
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BalancedProviderError<M: Middleware> {
+    /// Thrown when the internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+}
+
+impl<M: Middleware> FromErr<M::Error> for BalancedProviderError<M> {
+    fn from(src: M::Error) -> Self {
+        BalancedProviderError::MiddlewareError(src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::{MockProvider, Provider, ProviderError};
+    use std::sync::atomic::AtomicBool;
+
+    /// A node which can be taken down
+    #[derive(Debug, Clone)]
+    struct Node {
+        inner: Provider<MockProvider>,
+        mock: MockProvider,
+        down: Arc<AtomicBool>,
+    }
+
+    impl Node {
+        fn new() -> Self {
+            let (inner, mock) = Provider::mocked();
+            Self {
+                inner,
+                mock,
+                down: Arc::default(),
+            }
+        }
+
+        /// Answers the next `eth_blockNumber` with `block`
+        fn at(self, block: u64) -> Self {
+            self.mock.push(U64::from(block)).unwrap();
+            self
+        }
+    }
+
+    #[async_trait]
+    impl Middleware for Node {
+        type Error = ProviderError;
+        type Provider = MockProvider;
+        type Inner = Provider<MockProvider>;
+
+        fn inner(&self) -> &Self::Inner {
+            &self.inner
+        }
+
+        async fn get_block_number(&self) -> Result<U64, Self::Error> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(ProviderError::CustomError("connection refused".to_owned()));
+            }
+            self.inner.get_block_number().await
+        }
+    }
+
+    async fn block_number<M: Middleware>(provider: &BalancedProvider<M>) -> u64
+    where
+        M::Error: 'static,
+    {
+        provider.get_block_number().await.unwrap().as_u64()
+    }
+
+    #[tokio::test]
+    async fn takes_turns() {
+        let provider = BalancedProvider::new(vec![Node::new().at(1), Node::new().at(2)]);
+        assert_eq!(block_number(&provider).await, 1);
+        assert_eq!(block_number(&provider).await, 2);
+    }
+
+    #[tokio::test]
+    async fn fails_over() {
+        let nodes = vec![Node::new().at(1), Node::new().at(2).at(2)];
+        nodes[0].down.store(true, Ordering::SeqCst);
+        let provider = BalancedProvider::new(nodes);
+
+        assert_eq!(block_number(&provider).await, 2);
+        // the failed node is skipped from now on
+        assert_eq!(block_number(&provider).await, 2);
+    }
+
+    #[tokio::test]
+    async fn skips_nodes_which_fell_behind() {
+        // the mocks answer the latest pushed response first
+        let nodes = vec![Node::new().at(100), Node::new().at(100).at(95)];
+        let provider = BalancedProvider::new(nodes).with_max_lag(3);
+        provider.check_health().await;
+
+        let state = provider.state.lock().unwrap().clone();
+        assert_eq!(state[0].height, Some(100));
+        assert_eq!(state[1].height, Some(95));
+        assert_eq!(provider.order(), vec![0]);
+    }
+
+    #[tokio::test]
+    async fn prefers_fast_nodes() {
+        let nodes = vec![Node::new(), Node::new(), Node::new()];
+        let provider = BalancedProvider::new(nodes).with_strategy(Strategy::Latency);
+        provider.record_latency(0, Duration::from_millis(30));
+        provider.record_latency(1, Duration::from_millis(10));
+        provider.record_latency(2, Duration::from_millis(20));
+        assert_eq!(provider.order(), vec![1, 2, 0]);
+
+        // a slow response moves the node back
+        for _ in 0..10 {
+            provider.record_latency(1, Duration::from_millis(100));
+        }
+        assert_eq!(provider.order(), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn parses_strategies() {
+        assert_eq!("latency".parse(), Ok(Strategy::Latency));
+        assert_eq!("round-robin".parse(), Ok(Strategy::RoundRobin));
+        assert!("random".parse::<Strategy>().is_err());
+    }
+}
@@ -84,6 +84,7 @@ impl<M: Middleware> Middleware for CachedProvider<M> {
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum CachedProviderError<M: Middleware> {
     /// Thrown when the internal middleware errors
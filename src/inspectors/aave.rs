@@ -1,19 +1,35 @@
 use crate::{
-    addresses::AAVE_LENDING_POOL,
-    types::{actions::Liquidation, Classification, Inspection, Protocol},
+    addresses::{AAVE_LENDING_POOL, AAVE_LENDING_POOL_V2},
+    is_subtrace,
+    types::{
+        actions::{FlashLoan, Liquidation},
+        Classification, Inspection, Protocol,
+    },
     Inspector,
 };
 use ethers::{
-    abi::Abi,
+    abi::{parse_abi, Abi},
     contract::BaseContract,
-    types::{Address, U256},
+    types::{Address, Bytes, Call as TraceCall, U256},
 };
 
 type LiquidationCall = (Address, Address, Address, U256, bool);
+type FlashLoanV1 = (Address, Address, U256, Bytes);
+type FlashLoanV2 = (
+    Address,
+    Vec<Address>,
+    Vec<U256>,
+    Vec<U256>,
+    Address,
+    Bytes,
+    u16,
+);
+type TransferOnLiquidation = (Address, Address, U256);
 
 #[derive(Clone, Debug)]
 pub struct Aave {
     pub pool: BaseContract,
+    pub pool_v2: BaseContract,
 }
 
 impl Aave {
@@ -23,41 +39,135 @@ impl Aave {
                 serde_json::from_str::<Abi>(include_str!("../../abi/aavepool.json"))
                     .expect("could not parse aave abi")
             }),
+            pool_v2: BaseContract::from(
+                parse_abi(&[
+                    "function liquidationCall(address collateralAsset, address debtAsset, address user, uint256 debtToCover, bool receiveAToken)",
+                    "function flashLoan(address receiverAddress, address[] calldata assets, uint256[] calldata amounts, uint256[] calldata modes, address onBehalfOf, bytes calldata params, uint16 referralCode)",
+                    // called on the collateral aToken when `receiveAToken` is set
+                    "function transferOnLiquidation(address from, address to, uint256 value)",
+                ])
+                .expect("could not parse aave v2 abi"),
+            ),
         }
     }
+
+    fn try_as_flash_loan(&self, protocol: Protocol, call: &TraceCall) -> Option<FlashLoan> {
+        let (receiver, assets, amounts) = match protocol {
+            Protocol::Aave => {
+                let (receiver, reserve, amount, _) = self
+                    .pool
+                    .decode::<FlashLoanV1, _>("flashLoan", &call.input)
+                    .ok()?;
+                (receiver, vec![reserve], vec![amount])
+            }
+            _ => {
+                let (receiver, assets, amounts, _, _, _, _) = self
+                    .pool_v2
+                    .decode::<FlashLoanV2, _>("flashLoan", &call.input)
+                    .ok()?;
+                (receiver, assets, amounts)
+            }
+        };
+
+        Some(FlashLoan {
+            assets,
+            amounts,
+            receiver,
+            from: call.from,
+        })
+    }
+
+    /// Finds the collateral paid out to the liquidator in a V2 liquidation's
+    /// subtraces. This is either the underlying token, or the aToken if
+    /// `receiveAToken` was set, in which case it is moved via `transferOnLiquidation`.
+    fn collateral_received(
+        &self,
+        subtraces: &[Classification],
+        trace_address: &[usize],
+        liquidation: &Liquidation,
+    ) -> Option<(Address, U256)> {
+        subtraces
+            .iter()
+            .filter(|c| trace_address.is_empty() || is_subtrace(trace_address, &c.trace_address()))
+            .find_map(|c| match c {
+                Classification::Unknown(calltrace) => {
+                    let call = calltrace.as_ref();
+                    let (from, to, amount) = self
+                        .pool_v2
+                        .decode::<TransferOnLiquidation, _>("transferOnLiquidation", &call.input)
+                        .ok()?;
+                    if from == liquidation.liquidated_user && to == liquidation.from {
+                        // the aToken is the contract being called
+                        Some((call.to, amount))
+                    } else {
+                        None
+                    }
+                }
+                Classification::Known(_) => {
+                    let transfer = c.as_action()?.transfer()?;
+                    if transfer.token == liquidation.received_token
+                        && transfer.to == liquidation.from
+                    {
+                        Some((transfer.token, transfer.amount))
+                    } else {
+                        None
+                    }
+                }
+                Classification::Prune => None,
+            })
+    }
 }
 
 impl Inspector for Aave {
     fn inspect(&self, inspection: &mut Inspection) {
-        for action in inspection.actions.iter_mut() {
-            match action {
-                Classification::Unknown(ref mut calltrace) => {
-                    let call = calltrace.as_ref();
-                    if call.to == *AAVE_LENDING_POOL {
-                        inspection.protocols.insert(Protocol::Aave);
-
-                        // https://github.com/aave/aave-protocol/blob/master/contracts/lendingpool/LendingPool.sol#L805
-                        if let Ok((collateral, reserve, user, purchase_amount, _)) =
-                            self.pool
-                                .decode::<LiquidationCall, _>("liquidationCall", &call.input)
-                        {
-                            // Set the amount to 0. We'll set it at the reducer
-                            *action = Classification::new(
-                                Liquidation {
-                                    sent_token: reserve,
-                                    sent_amount: purchase_amount,
-
-                                    received_token: collateral,
-                                    received_amount: U256::zero(),
-                                    from: call.from,
-                                    liquidated_user: user,
-                                },
-                                calltrace.trace_address.clone(),
-                            );
-                        }
+        let actions = inspection.actions.to_vec();
+        for (i, action) in inspection.actions.iter_mut().enumerate() {
+            let calltrace = match action {
+                Classification::Unknown(ref calltrace) => calltrace,
+                Classification::Known(_) | Classification::Prune => continue,
+            };
+            let call = calltrace.as_ref();
+            let (protocol, pool) = if call.to == *AAVE_LENDING_POOL {
+                (Protocol::Aave, &self.pool)
+            } else if call.to == *AAVE_LENDING_POOL_V2 {
+                (Protocol::AaveV2, &self.pool_v2)
+            } else {
+                continue;
+            };
+            inspection.protocols.insert(protocol);
+
+            // https://github.com/aave/aave-protocol/blob/master/contracts/lendingpool/LendingPool.sol#L805
+            // https://github.com/aave/protocol-v2/blob/master/contracts/protocol/lendingpool/LendingPool.sol#L424
+            if let Ok((collateral, reserve, user, purchase_amount, _)) =
+                pool.decode::<LiquidationCall, _>("liquidationCall", &call.input)
+            {
+                // Set the amount to 0. We'll set it at the reducer
+                let mut liquidation = Liquidation {
+                    sent_token: reserve,
+                    sent_amount: purchase_amount,
+
+                    received_token: collateral,
+                    received_amount: U256::zero(),
+                    from: call.from,
+                    liquidated_user: user,
+                };
+
+                // V2 liquidations pay out the collateral in their own subtraces
+                if protocol == Protocol::AaveV2 {
+                    if let Some((token, amount)) = self.collateral_received(
+                        &actions[i + 1..],
+                        &calltrace.trace_address,
+                        &liquidation,
+                    ) {
+                        liquidation.received_token = token;
+                        liquidation.received_amount = amount;
                     }
                 }
-                Classification::Known(_) | Classification::Prune => {}
+
+                *action = Classification::new(liquidation, calltrace.trace_address.clone());
+            } else if let Some(flash_loan) = self.try_as_flash_loan(protocol, call) {
+                inspection.protocols.insert(Protocol::Flashloan);
+                *action = Classification::new(flash_loan, calltrace.trace_address.clone());
             }
         }
     }
@@ -67,7 +177,10 @@ impl Inspector for Aave {
 mod tests {
     use super::*;
     use crate::{
-        inspectors::ERC20, reducers::LiquidationReducer, test_helpers::read_trace, Reducer,
+        inspectors::ERC20,
+        reducers::LiquidationReducer,
+        test_helpers::{addrs, erc20, mk_call, mk_inspection, read_trace},
+        Reducer,
     };
 
     struct MyInspector {
@@ -115,4 +228,127 @@ mod tests {
             "1100830609991235507621"
         );
     }
+
+    #[test]
+    fn v2_liquidation_underlying() {
+        let addrs = addrs();
+        let (liquidator, user, collateral, debt) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let aave = Aave::new();
+        let inspector = ERC20::new();
+
+        let input = aave
+            .pool_v2
+            .encode(
+                "liquidationCall",
+                (collateral, debt, user, U256::from(100), false),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(liquidator, *AAVE_LENDING_POOL_V2, 0, input, vec![]),
+            mk_call(
+                *AAVE_LENDING_POOL_V2,
+                collateral,
+                0,
+                erc20()
+                    .encode("transfer", (liquidator, U256::from(105)))
+                    .unwrap(),
+                vec![0],
+            ),
+        ]);
+        inspector.inspect(&mut inspection);
+        aave.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::AaveV2]);
+        let liquidation = inspection.known()[0]
+            .as_ref()
+            .liquidation()
+            .cloned()
+            .unwrap();
+        assert_eq!(
+            liquidation,
+            Liquidation {
+                sent_token: debt,
+                sent_amount: 100.into(),
+                received_token: collateral,
+                received_amount: 105.into(),
+                from: liquidator,
+                liquidated_user: user,
+            }
+        );
+    }
+
+    #[test]
+    fn v2_liquidation_atoken() {
+        let addrs = addrs();
+        let (liquidator, user, collateral, debt, a_token) =
+            (addrs[0], addrs[1], addrs[2], addrs[3], addrs[4]);
+        let aave = Aave::new();
+
+        let input = aave
+            .pool_v2
+            .encode(
+                "liquidationCall",
+                (collateral, debt, user, U256::from(100), true),
+            )
+            .unwrap();
+        let transfer = aave
+            .pool_v2
+            .encode("transferOnLiquidation", (user, liquidator, U256::from(105)))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(liquidator, *AAVE_LENDING_POOL_V2, 0, input, vec![]),
+            mk_call(*AAVE_LENDING_POOL_V2, a_token, 0, transfer, vec![0]),
+        ]);
+        aave.inspect(&mut inspection);
+
+        let liquidation = inspection.known()[0]
+            .as_ref()
+            .liquidation()
+            .cloned()
+            .unwrap();
+        assert_eq!(liquidation.received_token, a_token);
+        assert_eq!(liquidation.received_amount, 105.into());
+    }
+
+    #[test]
+    fn v2_flash_loan() {
+        let addrs = addrs();
+        let (usr, receiver, token_a, token_b) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let aave = Aave::new();
+
+        let input = aave
+            .pool_v2
+            .encode(
+                "flashLoan",
+                (
+                    receiver,
+                    vec![token_a, token_b],
+                    vec![U256::from(100), U256::from(200)],
+                    vec![U256::zero(), U256::zero()],
+                    usr,
+                    Bytes::default(),
+                    0u16,
+                ),
+            )
+            .unwrap();
+        let mut inspection =
+            mk_inspection(vec![mk_call(usr, *AAVE_LENDING_POOL_V2, 0, input, vec![])]);
+        aave.inspect(&mut inspection);
+
+        assert_eq!(
+            inspection.protocols,
+            crate::set![Protocol::AaveV2, Protocol::Flashloan]
+        );
+        let known = inspection.known();
+        let flash_loan = known[0].as_ref().flash_loan().unwrap();
+        assert_eq!(
+            *flash_loan,
+            FlashLoan {
+                assets: vec![token_a, token_b],
+                amounts: vec![100.into(), 200.into()],
+                receiver,
+                from: usr,
+            }
+        );
+    }
 }
@@ -1,18 +1,19 @@
 use crate::{
     addresses::BALANCER_PROXY,
     inspectors::find_matching,
+    is_subtrace,
     traits::Inspector,
     types::{actions::Trade, Classification, Inspection, Protocol},
 };
 
 use ethers::{
-    abi::Abi,
+    abi::{Abi, ParamType},
     contract::BaseContract,
     types::{Address, Call as TraceCall, U256},
 };
 
 #[derive(Debug, Clone)]
-/// An inspector for Uniswap
+/// An inspector for Balancer pools and the Balancer Exchange Proxy
 pub struct Balancer {
     bpool: BaseContract,
     bproxy: BaseContract,
@@ -20,10 +21,56 @@ pub struct Balancer {
 
 type Swap = (Address, U256, Address, U256, U256);
 
+// Type aliases for the Exchange Proxy's swap sequences
+// (pool, tokenIn, tokenOut, swapAmount, limitReturnAmount, maxPrice)
+type ProxySwap = (Address, Address, Address, U256, U256, U256);
+type BatchSwapExactIn = (Vec<ProxySwap>, Address, Address, U256, U256);
+type BatchSwapExactOut = (Vec<ProxySwap>, Address, Address, U256);
+type MultihopBatchSwapExactIn = (Vec<Vec<ProxySwap>>, Address, Address, U256, U256);
+type MultihopBatchSwapExactOut = (Vec<Vec<ProxySwap>>, Address, Address, U256);
+type SmartSwap = (Address, Address, U256, U256, U256);
+
+/// A call to the Exchange Proxy along with the pools it routes through
+struct ProxyCall {
+    idx: usize,
+    trace_address: Vec<usize>,
+    /// The pools of all the swap sequences. Empty for `smartSwap*` calls, where
+    /// the pools are picked on-chain by the proxy
+    pools: Vec<Address>,
+    /// Whether any of the pool swaps were classified as trades
+    matched: bool,
+}
+
+impl ProxyCall {
+    fn routes(&self, trace_address: &[usize], pool: &Address) -> bool {
+        // all the calls of a tx are subtraces of its top-level call
+        (self.trace_address.is_empty() || is_subtrace(&self.trace_address, trace_address))
+            && (self.pools.is_empty() || self.pools.contains(pool))
+    }
+}
+
 impl Inspector for Balancer {
     fn inspect(&self, inspection: &mut Inspection) {
         let actions = inspection.actions.to_vec();
         let mut prune = Vec::new();
+
+        // find all the proxy calls first so that the pool swaps which happen
+        // as their subtraces can be linked to them
+        let mut proxies = actions
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, action)| {
+                let calltrace = action.as_call()?;
+                let pools = self.proxy_pools(calltrace.as_ref())?;
+                Some(ProxyCall {
+                    idx,
+                    trace_address: calltrace.trace_address.clone(),
+                    pools,
+                    matched: false,
+                })
+            })
+            .collect::<Vec<_>>();
+
         for i in 0..inspection.actions.len() {
             let action = &mut inspection.actions[i];
 
@@ -63,24 +110,41 @@ impl Inspector for Balancer {
                     true,
                 );
 
-                match (t1, t2) {
-                    (Some((j, t1)), Some((k, t2))) => {
-                        if t1.from != t2.to || t2.from != t1.to {
-                            continue;
+                if let (Some((j, t1)), Some((k, t2))) = (t1, t2) {
+                    if t1.from != t2.to || t2.from != t1.to {
+                        continue;
+                    }
+
+                    // swaps routed via the Exchange Proxy are linked to the proxy call
+                    let trace_address = match proxies
+                        .iter_mut()
+                        .find(|proxy| proxy.routes(&calltrace.trace_address, &call.to))
+                    {
+                        Some(proxy) => {
+                            proxy.matched = true;
+                            proxy.trace_address.clone()
                         }
+                        None => Vec::new(),
+                    };
 
-                        *action =
-                            Classification::new(Trade::new(t1.clone(), t2.clone()), Vec::new());
-                        prune.push(j);
-                        prune.push(k);
+                    *action =
+                        Classification::new(Trade::new(t1.clone(), t2.clone()), trace_address);
+                    prune.push(j);
+                    prune.push(k);
 
-                        inspection.protocols.insert(Protocol::Balancer);
-                    }
-                    _ => {}
-                };
+                    inspection.protocols.insert(Protocol::Balancer);
+                }
             }
         }
 
+        // the proxy calls are fully described by their pool trades
+        prune.extend(
+            proxies
+                .iter()
+                .filter(|proxy| proxy.matched)
+                .map(|proxy| proxy.idx),
+        );
+
         prune
             .iter()
             .for_each(|p| inspection.actions[*p] = Classification::Prune);
@@ -90,8 +154,46 @@ impl Inspector for Balancer {
 
 impl Balancer {
     fn check(&self, call: &TraceCall) -> bool {
-        // TODO: Adjust for exchange proxy calls
-        call.to == *BALANCER_PROXY
+        call.to == *BALANCER_PROXY || self.proxy_pools(call).is_some()
+    }
+
+    /// Decodes an Exchange Proxy swap and returns the pools of its swap sequences
+    fn proxy_pools(&self, call: &TraceCall) -> Option<Vec<Address>> {
+        let swaps = if let Ok((swaps, _, _, _, _)) = self
+            .bproxy
+            .decode::<BatchSwapExactIn, _>("batchSwapExactIn", &call.input)
+        {
+            swaps
+        } else if let Ok((swaps, _, _, _)) = self
+            .bproxy
+            .decode::<BatchSwapExactOut, _>("batchSwapExactOut", &call.input)
+        {
+            swaps
+        } else if let Ok((sequences, _, _, _, _)) = self
+            .bproxy
+            .decode::<MultihopBatchSwapExactIn, _>("multihopBatchSwapExactIn", &call.input)
+        {
+            sequences.into_iter().flatten().collect()
+        } else if let Ok((sequences, _, _, _)) = self
+            .bproxy
+            .decode::<MultihopBatchSwapExactOut, _>("multihopBatchSwapExactOut", &call.input)
+        {
+            sequences.into_iter().flatten().collect()
+        } else if self
+            .bproxy
+            .decode::<SmartSwap, _>("smartSwapExactIn", &call.input)
+            .is_ok()
+            || self
+                .bproxy
+                .decode::<SmartSwap, _>("smartSwapExactOut", &call.input)
+                .is_ok()
+        {
+            Vec::new()
+        } else {
+            return None;
+        };
+
+        Some(swaps.into_iter().map(|swap| swap.0).collect())
     }
 
     /// Constructor
@@ -99,11 +201,25 @@ impl Balancer {
         Self {
             bpool: BaseContract::from({
                 serde_json::from_str::<Abi>(include_str!("../../abi/bpool.json"))
-                    .expect("could not parse uniswap abi")
+                    .expect("could not parse balancer pool abi")
             }),
             bproxy: BaseContract::from({
-                serde_json::from_str::<Abi>(include_str!("../../abi/bproxy.json"))
-                    .expect("could not parse uniswap abi")
+                let mut abi = serde_json::from_str::<Abi>(include_str!("../../abi/bproxy.json"))
+                    .expect("could not parse balancer proxy abi");
+                // ethabi does not parse the components of nested tuple arrays,
+                // so the `tuple[][]` swap sequences get patched in by hand
+                let swaps = abi
+                    .function("batchSwapExactIn")
+                    .expect("no batchSwapExactIn in balancer proxy abi")
+                    .inputs[0]
+                    .kind
+                    .clone();
+                for name in &["multihopBatchSwapExactIn", "multihopBatchSwapExactOut"] {
+                    for function in abi.functions.get_mut(*name).into_iter().flatten() {
+                        function.inputs[0].kind = ParamType::Array(Box::new(swaps.clone()));
+                    }
+                }
+                abi
             }),
         }
     }
@@ -197,4 +313,116 @@ mod tests {
         let t2 = known[2].as_ref().transfer().unwrap();
         assert_eq!(ADDRESSBOOK.get(&t2.token).unwrap(), "COMP",);
     }
+
+    #[test]
+    // A bot routes a 2-hop swap through the Exchange Proxy
+    fn proxy_multihop() {
+        let addrs = addrs();
+        let (usr, bot, pool1, pool2) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let (token_a, token_b, token_c) = (addrs[4], addrs[5], addrs[6]);
+        let proxy = *BALANCER_PROXY;
+        let bal = MyInspector::new();
+        let erc20 = erc20();
+
+        let sequences: Vec<Vec<ProxySwap>> = vec![vec![
+            (pool1, token_a, token_b, 100.into(), 0.into(), U256::MAX),
+            (pool2, token_b, token_c, 0.into(), 0.into(), U256::MAX),
+        ]];
+        let multihop = bal
+            .balancer
+            .bproxy
+            .encode(
+                "multihopBatchSwapExactIn",
+                (
+                    sequences,
+                    token_a,
+                    token_c,
+                    U256::from(100),
+                    U256::from(110),
+                ),
+            )
+            .unwrap();
+        let swap = |token_in: Address, amount: u64, token_out: Address| {
+            bal.balancer
+                .bpool
+                .encode(
+                    "swapExactAmountIn",
+                    (
+                        token_in,
+                        U256::from(amount),
+                        token_out,
+                        U256::zero(),
+                        U256::MAX,
+                    ),
+                )
+                .unwrap()
+        };
+        let transfer_from = |from: Address, to: Address, amount: u64| {
+            erc20
+                .encode("transferFrom", (from, to, U256::from(amount)))
+                .unwrap()
+        };
+        let transfer =
+            |to: Address, amount: u64| erc20.encode("transfer", (to, U256::from(amount))).unwrap();
+
+        let mut inspection = mk_inspection(vec![
+            mk_call(usr, bot, 0, Default::default(), vec![]),
+            mk_call(bot, proxy, 0, multihop, vec![0]),
+            mk_call(
+                proxy,
+                token_a,
+                0,
+                transfer_from(bot, proxy, 100),
+                vec![0, 0],
+            ),
+            mk_call(proxy, pool1, 0, swap(token_a, 100, token_b), vec![0, 1]),
+            mk_call(
+                pool1,
+                token_a,
+                0,
+                transfer_from(proxy, pool1, 100),
+                vec![0, 1, 0],
+            ),
+            mk_call(pool1, token_b, 0, transfer(proxy, 50), vec![0, 1, 1]),
+            mk_call(proxy, pool2, 0, swap(token_b, 50, token_c), vec![0, 2]),
+            mk_call(
+                pool2,
+                token_b,
+                0,
+                transfer_from(proxy, pool2, 50),
+                vec![0, 2, 0],
+            ),
+            mk_call(pool2, token_c, 0, transfer(proxy, 120), vec![0, 2, 1]),
+            mk_call(proxy, token_c, 0, transfer(bot, 120), vec![0, 3]),
+        ]);
+        bal.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::Balancer]);
+        // the proxy call is replaced by the pool trades
+        assert_eq!(inspection.unknown().len(), 1);
+
+        let trades = inspection
+            .known()
+            .into_iter()
+            .filter(|action| action.as_ref().trade().is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(trades.len(), 3);
+
+        // the bot's trade with the proxy
+        let outer = trades[0].as_ref().trade().unwrap();
+        assert_eq!((outer.t1.token, outer.t1.amount), (token_a, 100.into()));
+        assert_eq!((outer.t2.token, outer.t2.amount), (token_c, 120.into()));
+
+        // both hops are linked to the proxy call
+        assert!(trades[1..]
+            .iter()
+            .all(|trade| trade.trace_address == vec![0]));
+        let hop1 = trades[1].as_ref().trade().unwrap();
+        assert_eq!((hop1.t1.token, hop1.t1.to), (token_a, pool1));
+        assert_eq!((hop1.t2.token, hop1.t2.amount), (token_b, 50.into()));
+
+        let hop2 = trades[2].as_ref().trade().unwrap();
+        assert_eq!((hop2.t1.token, hop2.t1.to), (token_b, pool2));
+        assert_eq!((hop2.t2.token, hop2.t2.amount), (token_c, 120.into()));
+    }
 }
@@ -0,0 +1,284 @@
+use crate::{
+    addresses::BANCOR_NETWORK,
+    inspectors::{last_transfer_to, prune_subtraces, ERC20},
+    traits::Inspector,
+    types::{
+        actions::{Trade, Transfer},
+        Classification, Inspection, Protocol,
+    },
+};
+
+use ethers::{
+    abi::parse_abi,
+    contract::BaseContract,
+    types::{Address, Call as TraceCall, U256},
+};
+
+// Type aliases for the Bancor Network's conversion functions
+type Convert = (Vec<Address>, U256, U256);
+type ConvertFor = (Vec<Address>, U256, U256, Address);
+type Convert2 = (Vec<Address>, U256, U256, Address, U256);
+type ConvertByPath = (Vec<Address>, U256, U256, Address, Address, U256);
+
+#[derive(Debug, Clone)]
+/// An inspector for conversions via the Bancor Network
+pub struct Bancor {
+    network: BaseContract,
+    erc20: ERC20,
+}
+
+impl Inspector for Bancor {
+    fn inspect(&self, inspection: &mut Inspection) {
+        let actions = inspection.actions.to_vec();
+        let mut prune = Vec::new();
+        for i in 0..inspection.actions.len() {
+            let action = &mut inspection.actions[i];
+
+            if let Some(calltrace) = action.as_call() {
+                let call = calltrace.as_ref();
+                if call.to != *BANCOR_NETWORK {
+                    continue;
+                }
+                inspection.protocols.insert(Protocol::Bancor);
+
+                let (path, amount, beneficiary) = match self.try_as_conversion(call) {
+                    Some(conversion) => conversion,
+                    None => continue,
+                };
+                // the path alternates between reserve tokens and the
+                // converters' anchors, starting and ending in a reserve token
+                let (src, dest) = match (path.first(), path.last()) {
+                    (Some(src), Some(dest)) if path.len() >= 3 => (*src, *dest),
+                    _ => continue,
+                };
+                let received = match last_transfer_to(&self.erc20, &actions, i, dest, beneficiary) {
+                    Some(received) => received,
+                    None => continue,
+                };
+
+                *action = Classification::new(
+                    Trade {
+                        t1: Transfer {
+                            from: call.from,
+                            to: call.to,
+                            amount,
+                            token: src,
+                        },
+                        t2: Transfer {
+                            from: call.to,
+                            to: beneficiary,
+                            amount: received.amount,
+                            token: dest,
+                        },
+                    },
+                    calltrace.trace_address.clone(),
+                );
+                prune.push(i);
+            }
+        }
+
+        // the converters' transfers are accounted for in the trade
+        prune
+            .into_iter()
+            .for_each(|idx| prune_subtraces(&mut inspection.actions, idx));
+    }
+}
+
+impl Bancor {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            network: BaseContract::from(
+                parse_abi(&[
+                    "function convert(address[] _path, uint256 _amount, uint256 _minReturn) payable returns (uint256)",
+                    "function convertFor(address[] _path, uint256 _amount, uint256 _minReturn, address _beneficiary) payable returns (uint256)",
+                    "function convert2(address[] _path, uint256 _amount, uint256 _minReturn, address _affiliateAccount, uint256 _affiliateFee) payable returns (uint256)",
+                    "function convertFor2(address[] _path, uint256 _amount, uint256 _minReturn, address _beneficiary, address _affiliateAccount, uint256 _affiliateFee) payable returns (uint256)",
+                    "function convertByPath(address[] _path, uint256 _amount, uint256 _minReturn, address _beneficiary, address _affiliateAccount, uint256 _affiliateFee) payable returns (uint256)",
+                    "function claimAndConvert(address[] _path, uint256 _amount, uint256 _minReturn) returns (uint256)",
+                    "function claimAndConvertFor(address[] _path, uint256 _amount, uint256 _minReturn, address _beneficiary) returns (uint256)",
+                ])
+                .expect("could not parse bancor abi"),
+            ),
+            erc20: ERC20::new(),
+        }
+    }
+
+    /// Returns the (path, amount, beneficiary) of a conversion
+    fn try_as_conversion(&self, call: &TraceCall) -> Option<(Vec<Address>, U256, Address)> {
+        let input = &call.input;
+        let (path, amount, beneficiary) = if let Ok((path, amount, _)) = self
+            .network
+            .decode::<Convert, _>("convert", input)
+            .or_else(|_| self.network.decode::<Convert, _>("claimAndConvert", input))
+        {
+            (path, amount, call.from)
+        } else if let Ok((path, amount, _, beneficiary)) = self
+            .network
+            .decode::<ConvertFor, _>("convertFor", input)
+            .or_else(|_| {
+                self.network
+                    .decode::<ConvertFor, _>("claimAndConvertFor", input)
+            })
+        {
+            (path, amount, beneficiary)
+        } else if let Ok((path, amount, ..)) = self.network.decode::<Convert2, _>("convert2", input)
+        {
+            (path, amount, call.from)
+        } else if let Ok((path, amount, _, beneficiary, ..)) = self
+            .network
+            .decode::<ConvertByPath, _>("convertByPath", input)
+            .or_else(|_| {
+                self.network
+                    .decode::<ConvertByPath, _>("convertFor2", input)
+            })
+        {
+            (path, amount, beneficiary)
+        } else {
+            return None;
+        };
+
+        // `convertByPath` pays out to the caller if no beneficiary is set
+        let beneficiary = if beneficiary.is_zero() {
+            call.from
+        } else {
+            beneficiary
+        };
+        Some((path, amount, beneficiary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addresses::ETH,
+        inspectors::ERC20,
+        reducers::{ArbitrageReducer, TradeReducer},
+        test_helpers::*,
+        Reducer,
+    };
+    use ethers::types::Bytes;
+
+    struct MyInspector {
+        bancor: Bancor,
+        erc20: ERC20,
+        trade: TradeReducer,
+        arbitrage: ArbitrageReducer,
+    }
+
+    impl MyInspector {
+        fn inspect(&self, inspection: &mut Inspection) {
+            self.bancor.inspect(inspection);
+            self.erc20.inspect(inspection);
+            self.trade.reduce(inspection);
+            self.arbitrage.reduce(inspection);
+            inspection.prune();
+        }
+
+        fn new() -> Self {
+            Self {
+                bancor: Bancor::new(),
+                erc20: ERC20::new(),
+                trade: TradeReducer::new(),
+                arbitrage: ArbitrageReducer::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn eth_to_bnt() {
+        let addrs = addrs();
+        let (usr, anchor, converter, bnt) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let inspector = MyInspector::new();
+
+        let input = inspector
+            .bancor
+            .network
+            .encode(
+                "convertByPath",
+                (
+                    vec![*ETH, anchor, bnt],
+                    U256::from(100),
+                    U256::from(90),
+                    Address::zero(),
+                    Address::zero(),
+                    U256::zero(),
+                ),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(usr, *BANCOR_NETWORK, 100, input, vec![]),
+            mk_call(*BANCOR_NETWORK, converter, 100, Bytes::default(), vec![0]),
+            mk_call(
+                converter,
+                bnt,
+                0,
+                erc20().encode("transfer", (usr, U256::from(95))).unwrap(),
+                vec![1],
+            ),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::Bancor]);
+        let known = inspection.known();
+        assert_eq!(known.len(), 1);
+        let trade = known[0].as_ref().trade().unwrap();
+        assert_eq!((trade.t1.token, trade.t1.amount), (*ETH, 100.into()));
+        assert_eq!((trade.t2.token, trade.t2.amount), (bnt, 95.into()));
+        assert_eq!(trade.t2.to, usr);
+    }
+
+    #[test]
+    fn arb_through_bancor() {
+        let addrs = addrs();
+        let (bot, anchor, converter, pair, token) =
+            (addrs[0], addrs[1], addrs[2], addrs[3], addrs[4]);
+        let inspector = MyInspector::new();
+
+        let input = inspector
+            .bancor
+            .network
+            .encode(
+                "convert",
+                (vec![*ETH, anchor, token], U256::from(100), U256::from(1)),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(bot, *BANCOR_NETWORK, 100, input, vec![0]),
+            mk_call(
+                *BANCOR_NETWORK,
+                converter,
+                100,
+                Bytes::default(),
+                vec![0, 0],
+            ),
+            mk_call(
+                converter,
+                token,
+                0,
+                erc20().encode("transfer", (bot, U256::from(50))).unwrap(),
+                vec![0, 1],
+            ),
+            // sell the tokens back for more ETH elsewhere
+            mk_call(
+                bot,
+                token,
+                0,
+                erc20().encode("transfer", (pair, U256::from(50))).unwrap(),
+                vec![1],
+            ),
+            mk_call(pair, bot, 110, Bytes::default(), vec![2]),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        let known = inspection.known();
+        let arb = known
+            .iter()
+            .find_map(|x| x.as_ref().arbitrage())
+            .cloned()
+            .unwrap();
+        assert_eq!(arb.profit, 10.into());
+        assert_eq!(arb.token, *ETH);
+    }
+}
@@ -1,11 +1,13 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::future::Future;
 use std::ops::Range;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use ethers::prelude::Middleware;
-use ethers::types::{Block, BlockNumber, Trace, Transaction, TransactionReceipt, TxHash, U256};
+use ethers::types::{
+    Address, Block, BlockNumber, Trace, Transaction, TransactionReceipt, TxHash, U256,
+};
 use futures::{
     stream::{self, FuturesUnordered},
     Stream, StreamExt, TryFutureExt,
@@ -13,11 +15,12 @@ use futures::{
 use itertools::Itertools;
 use thiserror::Error;
 
-use crate::mevdb::BatchInserts;
+use crate::sinks::{BatchInserts, Sink};
 use crate::types::{EvalError, Evaluation};
 use crate::{
+    metrics,
     types::inspection::{Inspection, TraceWrapper},
-    HistoricalPrice, Inspector, MevDB, Reducer,
+    HistoricalPrice, Inspector, PairRegistry, Reducer,
 };
 use std::sync::Arc;
 
@@ -25,6 +28,10 @@ use std::sync::Arc;
 pub struct BatchInspector {
     inspectors: Vec<Box<dyn Inspector + Send + Sync>>,
     reducers: Vec<Box<dyn Reducer + Send + Sync>>,
+    /// Txs whose target is in this set are skipped
+    filter: HashSet<Address>,
+    /// Pairs created in the inspected blocks are registered here
+    pairs: Option<Arc<PairRegistry>>,
 }
 
 impl BatchInspector {
@@ -36,9 +43,30 @@ impl BatchInspector {
         Self {
             inspectors,
             reducers,
+            filter: HashSet::new(),
+            pairs: None,
+        }
+    }
+
+    /// Registers the pairs created in each block's receipts in the registry
+    pub fn with_pairs(mut self, pairs: Arc<PairRegistry>) -> Self {
+        self.pairs = Some(pairs);
+        self
+    }
+
+    /// Picks up the pairs created by the txs of these receipts
+    pub fn register_pairs(&self, receipts: &[TransactionReceipt]) {
+        if let Some(ref pairs) = self.pairs {
+            pairs.ingest_logs(receipts.iter().flat_map(|receipt| receipt.logs.iter()));
         }
     }
 
+    /// Skips all txs which are sent to any of the provided addresses
+    pub fn with_filter(mut self, filter: impl IntoIterator<Item = Address>) -> Self {
+        self.filter.extend(filter);
+        self
+    }
+
     /// Given a trace iterator, it groups all traces for the same tx hash
     /// and then inspects them and all of their subtraces
     pub fn inspect_many(&self, traces: impl IntoIterator<Item = Trace>) -> Vec<Inspection> {
@@ -60,10 +88,11 @@ impl BatchInspector {
         use std::convert::TryFrom;
         let mut res = None;
         if let Ok(mut i) = Inspection::try_from(TraceWrapper(traces)) {
-            if !i.actions.is_empty() {
+            if !i.actions.is_empty() && !self.filter.contains(&i.contract) {
                 self.inspect(&mut i);
                 self.reduce(&mut i);
                 i.prune();
+                metrics::record_inspection(&i);
                 res = Some(i);
             }
         }
@@ -188,9 +217,10 @@ impl<M: Middleware + Unpin + 'static> BatchEvaluator<M> {
         }
     }
 
-    /// Turn this stream into a `BatchInserter` that inserts all the `Evaluation`s
-    pub fn insert_all<'a>(self, mev_db: MevDB) -> BatchInserts<'a, M> {
-        BatchInserts::new(mev_db, self)
+    /// Turn this stream into a `BatchInserter` that writes all the `Evaluation`s
+    /// to the sink
+    pub fn insert_all<'a, S: Sink + 'static>(self, sink: S) -> BatchInserts<'a, M> {
+        BatchInserts::new(sink, self)
     }
 
     fn queue_in_evaluation(&mut self, inspection: Inspection, gas_used: U256, gas_price: U256) {
@@ -199,10 +229,13 @@ impl<M: Middleware + Unpin + 'static> BatchEvaluator<M> {
         let prices = Arc::clone(&self.prices);
         let eval = Box::pin(async move {
             Evaluation::new(inspection, prices.as_ref(), gas_used, gas_price)
-                .map_err(move |error| BatchEvaluationError::Evaluation {
-                    block_number,
-                    hash,
-                    error,
+                .map_err(move |error| {
+                    metrics::record_evaluation_error(error.kind());
+                    BatchEvaluationError::Evaluation {
+                        block_number,
+                        hash,
+                        error,
+                    }
                 })
                 .await
         });
@@ -219,6 +252,7 @@ impl<M: Middleware + Unpin + 'static> Stream for BatchEvaluator<M> {
         // queue in buffered evaluation jobs
         while this.evaluations_queue.len() < this.max {
             if let Some((inspection, gas_used, gas_price)) = this.waiting_inspections.pop_front() {
+                metrics::WAITING_INSPECTIONS.dec();
                 this.queue_in_evaluation(inspection, gas_used, gas_price);
                 log::trace!(
                     "queued new evaluation job, active: {}, waiting: {}",
@@ -234,6 +268,8 @@ impl<M: Middleware + Unpin + 'static> Stream for BatchEvaluator<M> {
             match this.block_infos.as_mut().poll_next(cx) {
                 Poll::Ready(Some(Ok((traces, block, receipts)))) => {
                     log::trace!("fetched block infos for block {:?}", block.number);
+                    metrics::BLOCKS_FETCHED.inc();
+                    this.inspector.register_pairs(&receipts);
                     let gas_price_txs = block
                         .transactions
                         .iter()
@@ -266,12 +302,14 @@ impl<M: Middleware + Unpin + 'static> Stream for BatchEvaluator<M> {
                         } else {
                             this.waiting_inspections
                                 .push_back((inspection, gas_used, gas_price));
+                            metrics::WAITING_INSPECTIONS.inc();
                         }
                     }
                 }
                 Poll::Ready(Some(Err(err))) => {
                     return {
                         log::error!("failed to fetch block: {:?}", err);
+                        metrics::record_evaluation_error(err.kind());
                         Poll::Ready(Some(Err(err)))
                     }
                 }
@@ -313,6 +351,13 @@ impl<M: Middleware + Unpin + 'static> Stream for BatchEvaluator<M> {
     }
 }
 
+impl<M: Middleware + 'static> Drop for BatchEvaluator<M> {
+    fn drop(&mut self) {
+        // the inspections which are dropped won't be evaluated anymore
+        metrics::WAITING_INSPECTIONS.sub(self.waiting_inspections.len() as i64);
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum BatchEvaluationError<M: Middleware + 'static> {
     #[error("Block {0} does not exist")]
@@ -341,6 +386,26 @@ pub enum BatchEvaluationError<M: Middleware + 'static> {
     },
 }
 
+impl<M: Middleware + 'static> BatchEvaluationError<M> {
+    /// The name of this kind of error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BatchEvaluationError::NotFound(_) => "block_not_found",
+            BatchEvaluationError::Evaluation { error, .. } => error.kind(),
+            BatchEvaluationError::Block { .. } => "block",
+        }
+    }
+
+    /// The block which could not be fetched, if that is why this failed
+    pub fn failed_block(&self) -> Option<u64> {
+        match self {
+            BatchEvaluationError::NotFound(block_number)
+            | BatchEvaluationError::Block { block_number, .. } => Some(*block_number),
+            BatchEvaluationError::Evaluation { .. } => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ethers::types::U256;
@@ -481,6 +546,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn filtered_target() {
+        let input = std::fs::read_to_string("res/triangular_arb.json").unwrap();
+        let traces: Vec<Trace> = serde_json::from_str(&input).unwrap();
+        let target = match traces[0].action {
+            ethers::types::Action::Call(ref call) => call.to,
+            _ => unreachable!("the first trace is a call"),
+        };
+
+        let inspector = BatchInspector::new(
+            vec![Box::new(ERC20::new()), Box::new(Uniswap::new())],
+            vec![],
+        );
+        assert!(inspector.inspect_one(traces.clone()).is_some());
+
+        let inspector = inspector.with_filter(vec![target]);
+        assert!(inspector.inspect_one(traces).is_none());
+    }
+
     #[test]
     fn curve_arb() {
         let mut inspection = read_trace("curve_arb.json");
@@ -538,8 +622,6 @@ mod tests {
         inspector.reduce(&mut inspection);
         inspection.prune();
 
-        let known = inspection.known();
-
         assert_eq!(inspection.status, Status::Reverted);
         assert_eq!(inspection.protocols, set![Protocol::Uniswap])
     }
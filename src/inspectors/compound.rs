@@ -1,6 +1,8 @@
 use crate::{
     actions_after,
-    addresses::{CETH, COMPTROLLER, COMP_ORACLE, WETH},
+    addresses::{
+        CETH, COMPTROLLER, COMP_ORACLE, CREAM_COMPTROLLER, CRETH, IRONBANK_COMPTROLLER, WETH,
+    },
     traits::Inspector,
     types::{
         actions::{Liquidation, SpecificAction},
@@ -14,7 +16,7 @@ use ethers::{
     types::{Address, Call, CallType, U256},
 };
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 type LiquidateBorrow = (Address, U256, Address);
 type LiquidateBorrowEth = (Address, Address);
@@ -34,12 +36,54 @@ abigen!(
 abigen!(CToken, "abi/ctoken.json",);
 
 #[derive(Debug, Clone)]
-/// An inspector for Compound liquidations
+/// A deployment of Compound or of one of its forks which share the cToken ABI
+pub struct CompoundDeployment {
+    pub comptroller: Address,
+    /// The cToken which wraps ETH, if any
+    pub native_ctoken: Option<Address>,
+    /// The label used for liquidations on this deployment's markets
+    pub protocol: Protocol,
+}
+
+impl CompoundDeployment {
+    /// Compound and the forks which get inspected by default
+    ///
+    /// Rari's Fuse pools are not among them: every pool is a separate
+    /// deployment with its own comptroller, created permissionlessly through
+    /// the Fuse pool directory, so they can't be listed here statically.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            CompoundDeployment {
+                comptroller: *COMPTROLLER,
+                native_ctoken: Some(*CETH),
+                protocol: Protocol::Compound,
+            },
+            CompoundDeployment {
+                comptroller: *CREAM_COMPTROLLER,
+                native_ctoken: Some(*CRETH),
+                protocol: Protocol::Cream,
+            },
+            CompoundDeployment {
+                comptroller: *IRONBANK_COMPTROLLER,
+                native_ctoken: None,
+                protocol: Protocol::IronBank,
+            },
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+/// An inspector for Compound (and Compound fork) liquidations
 pub struct Compound {
     ctoken: BaseContract,
     cether: BaseContract,
     comptroller: BaseContract,
     ctoken_to_token: HashMap<Address, Address>,
+    /// The protocol each cToken belongs to
+    ctoken_to_protocol: HashMap<Address, Protocol>,
+    /// The comptrollers and price oracles, which get called by pre-flight checks
+    comptrollers: HashSet<Address>,
+    oracles: HashSet<Address>,
 }
 
 impl Inspector for Compound {
@@ -50,8 +94,12 @@ impl Inspector for Compound {
             let (action, subtraces) = actions_after(&mut inspection.actions, i);
 
             // if the provided action is a liquidation, start parsing all the subtraces
-            if let Some((mut liquidation, trace)) = self.try_as_liquidation(&action) {
-                inspection.protocols.insert(Protocol::Compound);
+            if let Some((mut liquidation, trace)) = self.try_as_liquidation(action) {
+                let ctoken = action
+                    .as_call()
+                    .map(|call| call.call.to)
+                    .unwrap_or_default();
+                inspection.protocols.insert(self.protocol(&ctoken));
 
                 // omit the double-counted Dcall
                 if let Some(ref call_type) = action.as_call().map(|call| &call.call.call_type) {
@@ -70,7 +118,7 @@ impl Inspector for Compound {
                     }
                     found = true;
                 }
-            } else if self.is_preflight(&action) && !found {
+            } else if self.is_preflight(action) && !found {
                 // insert an empty liquidation for the actions upstream
                 *action = Classification::new(SpecificAction::LiquidationCheck, Vec::new());
                 // a pre-flight is only marked as "Checked" if a successful
@@ -82,8 +130,20 @@ impl Inspector for Compound {
 }
 
 impl Compound {
-    /// Constructor
+    /// Constructor for the mainnet Compound deployment with the given markets
     pub fn new<T: IntoIterator<Item = (Address, Address)>>(ctoken_to_token: T) -> Self {
+        Self::empty().with_deployment(
+            &CompoundDeployment {
+                comptroller: *COMPTROLLER,
+                native_ctoken: Some(*CETH),
+                protocol: Protocol::Compound,
+            },
+            Some(*COMP_ORACLE),
+            ctoken_to_token,
+        )
+    }
+
+    fn empty() -> Self {
         Self {
             ctoken: BaseContract::from({
                 serde_json::from_str::<Abi>(include_str!("../../abi/ctoken.json"))
@@ -97,44 +157,70 @@ impl Compound {
                 serde_json::from_str::<Abi>(include_str!("../../abi/comptroller.json"))
                     .expect("could not parse ctoken abi")
             }),
-            ctoken_to_token: ctoken_to_token.into_iter().collect(),
+            ctoken_to_token: HashMap::new(),
+            ctoken_to_protocol: HashMap::new(),
+            comptrollers: HashSet::new(),
+            oracles: HashSet::new(),
         }
     }
 
-    /// Instantiates Compound with all live markets
-    ///
-    /// # Panics
-    ///
-    /// - If the `Ctoken.underlying` call fails
+    /// Adds a deployment's markets (cToken to underlying token) and its price oracle
+    pub fn with_deployment<T: IntoIterator<Item = (Address, Address)>>(
+        mut self,
+        deployment: &CompoundDeployment,
+        oracle: Option<Address>,
+        ctoken_to_token: T,
+    ) -> Self {
+        for (ctoken, token) in ctoken_to_token {
+            self.ctoken_to_token.insert(ctoken, token);
+            self.ctoken_to_protocol.insert(ctoken, deployment.protocol);
+        }
+        self.comptrollers.insert(deployment.comptroller);
+        self.oracles.extend(oracle);
+        self
+    }
+
+    /// Instantiates Compound with all live markets of the provided deployments.
+    /// Markets whose underlying token can't be fetched are skipped.
     pub async fn create<M: Middleware>(
         provider: std::sync::Arc<M>,
+        deployments: &[CompoundDeployment],
     ) -> Result<Self, ContractError<M>> {
-        let comptroller = Comptroller::new(*COMPTROLLER, provider.clone());
-
-        let markets = comptroller.get_all_markets().call().await?;
-        let futs = markets
-            .into_iter()
-            .map(|market| {
-                let provider = provider.clone();
-                async move {
-                    if market != *CETH {
-                        (
-                            market,
-                            CToken::new(market, provider)
-                                .underlying()
-                                .call()
-                                .await
-                                .expect("could not get underlying"),
-                        )
-                    } else {
-                        (market, *WETH)
+        let mut compound = Self::empty();
+        for deployment in deployments {
+            let comptroller = Comptroller::new(deployment.comptroller, provider.clone());
+
+            let oracle = comptroller.oracle().call().await?;
+            let markets = comptroller.get_all_markets().call().await?;
+            let futs = markets
+                .into_iter()
+                .map(|market| {
+                    let provider = provider.clone();
+                    async move {
+                        if Some(market) == deployment.native_ctoken {
+                            return Some((market, *WETH));
+                        }
+                        match CToken::new(market, provider).underlying().call().await {
+                            Ok(token) => Some((market, token)),
+                            Err(err) => {
+                                log::warn!(
+                                    "skipping {:?} market {:?}, could not get its underlying: {}",
+                                    deployment.protocol,
+                                    market,
+                                    err
+                                );
+                                None
+                            }
+                        }
                     }
-                }
-            })
-            .collect::<Vec<_>>();
-        let res = futures::future::join_all(futs).await;
+                })
+                .collect::<Vec<_>>();
+            let res = futures::future::join_all(futs).await.into_iter().flatten();
 
-        Ok(Compound::new(res))
+            compound = compound.with_deployment(deployment, Some(oracle), res);
+        }
+
+        Ok(compound)
     }
 
     /// Find the liquidation action
@@ -206,14 +292,22 @@ impl Compound {
             Classification::Unknown(ref calltrace) => {
                 let call = calltrace.as_ref();
                 // checks if liquidation is allowed
-                call.to == *COMPTROLLER && call.input.as_ref().starts_with(&self.comptroller.as_ref().function("liquidateBorrowAllowed").unwrap().selector()) ||
+                self.comptrollers.contains(&call.to) && call.input.as_ref().starts_with(&self.comptroller.as_ref().function("liquidateBorrowAllowed").unwrap().selector()) ||
                     // checks oracle price
-                    call.to == *COMP_ORACLE && call.input.as_ref().starts_with(&ethers::utils::id("getUnderlyingPrice(address)"))
+                    self.oracles.contains(&call.to) && call.input.as_ref().starts_with(&ethers::utils::id("getUnderlyingPrice(address)"))
             }
             _ => false,
         }
     }
 
+    // the protocol a cToken belongs to, defaulting to Compound for unknown markets
+    fn protocol(&self, ctoken: &Address) -> Protocol {
+        self.ctoken_to_protocol
+            .get(ctoken)
+            .cloned()
+            .unwrap_or(Protocol::Compound)
+    }
+
     // helper for converting cToken to Token address.
     // TODO: Should this also include decimals? Or should we assume that
     // cTokens always use 8 decimals
@@ -221,7 +315,7 @@ impl Compound {
         if let Some(inner) = self.ctoken_to_token.get(address) {
             inner
         } else {
-            &address
+            address
         }
     }
 }
@@ -266,14 +360,43 @@ mod tests {
         assert_eq!(inspection.status, Status::Success);
     }
 
+    #[test]
+    fn fork_liquidation_is_labelled() {
+        let mut inspection = read_trace("compound_liquidation.json");
+        let ctoken_to_token = vec![(
+            parse_address("0xb3319f5d18bc0d84dd1b4825dcde5d5f7266d407"),
+            parse_address("0xe41d2489571d322189246dafa5ebde1f4699f498"),
+        )];
+        // pretend that the market belongs to a fork
+        let fork = CompoundDeployment {
+            comptroller: *CREAM_COMPTROLLER,
+            native_ctoken: Some(*CRETH),
+            protocol: Protocol::Cream,
+        };
+        let compound = Compound::empty().with_deployment(&fork, None, ctoken_to_token);
+        compound.inspect(&mut inspection);
+
+        let liquidation = inspection
+            .known()
+            .iter()
+            .find_map(|x| x.as_ref().liquidation())
+            .cloned()
+            .unwrap();
+        assert_eq!(ADDRESSBOOK.get(&liquidation.sent_token).unwrap(), "ZRX");
+        assert_eq!(inspection.protocols, crate::set![Protocol::Cream]);
+    }
+
     #[tokio::test]
     async fn instantiate() {
         let provider =
             Provider::try_from("https://mainnet.infura.io/v3/c60b0bb42f8a4c6481ecd229eddaca27")
                 .unwrap();
-        let compound = Compound::create(std::sync::Arc::new(provider))
-            .await
-            .unwrap();
+        let compound = Compound::create(
+            std::sync::Arc::new(provider),
+            &CompoundDeployment::defaults(),
+        )
+        .await
+        .unwrap();
 
         // cZRX -> ZRX
         assert_eq!(
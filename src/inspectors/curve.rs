@@ -121,7 +121,7 @@ impl Curve {
     }
 
     fn check(&self, call: &TraceCall) -> bool {
-        if !self.pools.is_empty() && self.pools.get(&call.to).is_none() {
+        if !self.pools.is_empty() && !self.pools.contains_key(&call.to) {
             return false;
         }
         for function in self.pool.as_ref().functions() {
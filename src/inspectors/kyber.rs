@@ -0,0 +1,197 @@
+use crate::{
+    addresses::{ETH, KYBER_PROXY},
+    inspectors::{last_transfer_to, prune_subtraces, ERC20},
+    traits::Inspector,
+    types::{
+        actions::{Trade, Transfer},
+        Classification, Inspection, Protocol,
+    },
+};
+
+use ethers::{
+    abi::parse_abi,
+    contract::BaseContract,
+    types::{Address, Bytes, Call as TraceCall, U256},
+};
+
+// Type aliases for the Kyber Network Proxy's trade functions
+type KyberTrade = (Address, U256, Address, Address, U256, U256, Address);
+type TradeWithHint = (Address, U256, Address, Address, U256, U256, Address, Bytes);
+type TradeWithHintAndFee = (
+    Address,
+    U256,
+    Address,
+    Address,
+    U256,
+    U256,
+    Address,
+    U256,
+    Bytes,
+);
+type SwapTokenToToken = (Address, U256, Address, U256);
+
+#[derive(Debug, Clone)]
+/// An inspector for trades via the Kyber Network Proxy
+pub struct Kyber {
+    proxy: BaseContract,
+    erc20: ERC20,
+}
+
+impl Inspector for Kyber {
+    fn inspect(&self, inspection: &mut Inspection) {
+        let actions = inspection.actions.to_vec();
+        let mut prune = Vec::new();
+        for i in 0..inspection.actions.len() {
+            let action = &mut inspection.actions[i];
+
+            if let Some(calltrace) = action.as_call() {
+                let call = calltrace.as_ref();
+                if call.to != *KYBER_PROXY {
+                    continue;
+                }
+                inspection.protocols.insert(Protocol::Kyber);
+
+                let (src, src_amount, dest, dest_address) = match self.try_as_trade(call) {
+                    Some(trade) => trade,
+                    None => continue,
+                };
+                // the proxy pays out the tokens it received from the reserves
+                let received = match last_transfer_to(&self.erc20, &actions, i, dest, dest_address)
+                {
+                    Some(received) => received,
+                    None => continue,
+                };
+
+                *action = Classification::new(
+                    Trade {
+                        t1: Transfer {
+                            from: call.from,
+                            to: call.to,
+                            amount: src_amount,
+                            token: src,
+                        },
+                        t2: Transfer {
+                            from: call.to,
+                            to: dest_address,
+                            amount: received.amount,
+                            token: dest,
+                        },
+                    },
+                    calltrace.trace_address.clone(),
+                );
+                prune.push(i);
+            }
+        }
+
+        // the reserves' transfers are accounted for in the trade
+        prune
+            .into_iter()
+            .for_each(|idx| prune_subtraces(&mut inspection.actions, idx));
+    }
+}
+
+impl Kyber {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            proxy: BaseContract::from(
+                parse_abi(&[
+                    "function trade(address src, uint256 srcAmount, address dest, address destAddress, uint256 maxDestAmount, uint256 minConversionRate, address walletId) payable returns (uint256)",
+                    "function tradeWithHint(address src, uint256 srcAmount, address dest, address destAddress, uint256 maxDestAmount, uint256 minConversionRate, address walletId, bytes hint) payable returns (uint256)",
+                    "function tradeWithHintAndFee(address src, uint256 srcAmount, address dest, address destAddress, uint256 maxDestAmount, uint256 minConversionRate, address platformWallet, uint256 platformFeeBps, bytes hint) payable returns (uint256)",
+                    "function swapEtherToToken(address token, uint256 minConversionRate) payable returns (uint256)",
+                    "function swapTokenToEther(address token, uint256 srcAmount, uint256 minConversionRate) returns (uint256)",
+                    "function swapTokenToToken(address src, uint256 srcAmount, address dest, uint256 minConversionRate) returns (uint256)",
+                ])
+                .expect("could not parse kyber abi"),
+            ),
+            erc20: ERC20::new(),
+        }
+    }
+
+    /// Returns the (src, srcAmount, dest, destAddress) of a proxy call
+    fn try_as_trade(&self, call: &TraceCall) -> Option<(Address, U256, Address, Address)> {
+        let input = &call.input;
+        if let Ok((src, src_amount, dest, dest_address, ..)) =
+            self.proxy.decode::<KyberTrade, _>("trade", input)
+        {
+            Some((src, src_amount, dest, dest_address))
+        } else if let Ok((src, src_amount, dest, dest_address, ..)) = self
+            .proxy
+            .decode::<TradeWithHint, _>("tradeWithHint", input)
+        {
+            Some((src, src_amount, dest, dest_address))
+        } else if let Ok((src, src_amount, dest, dest_address, ..)) = self
+            .proxy
+            .decode::<TradeWithHintAndFee, _>("tradeWithHintAndFee", input)
+        {
+            Some((src, src_amount, dest, dest_address))
+        } else if let Ok((token, _)) = self
+            .proxy
+            .decode::<(Address, U256), _>("swapEtherToToken", input)
+        {
+            Some((*ETH, call.value, token, call.from))
+        } else if let Ok((token, src_amount, _)) = self
+            .proxy
+            .decode::<(Address, U256, U256), _>("swapTokenToEther", input)
+        {
+            Some((token, src_amount, *ETH, call.from))
+        } else if let Ok((src, src_amount, dest, _)) = self
+            .proxy
+            .decode::<SwapTokenToToken, _>("swapTokenToToken", input)
+        {
+            Some((src, src_amount, dest, call.from))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{addresses::ADDRESSBOOK, inspectors::ERC20, test_helpers::*};
+
+    struct MyInspector {
+        kyber: Kyber,
+        erc20: ERC20,
+    }
+
+    impl MyInspector {
+        fn inspect(&self, inspection: &mut Inspection) {
+            self.kyber.inspect(inspection);
+            self.erc20.inspect(inspection);
+            inspection.prune();
+        }
+
+        fn new() -> Self {
+            Self {
+                kyber: Kyber::new(),
+                erc20: ERC20::new(),
+            }
+        }
+    }
+
+    #[test]
+    // https://etherscan.io/tx/0x93690c02fc4d58734225d898ea4091df104040450c0f204b6bf6f6850ac4602f
+    // The liquidator swaps 99k USDC for YFI via Kyber
+    fn usdc_to_yfi() {
+        let mut inspection =
+            get_trace("0x93690c02fc4d58734225d898ea4091df104040450c0f204b6bf6f6850ac4602f");
+        let inspector = MyInspector::new();
+        inspector.inspect(&mut inspection);
+
+        assert!(inspection.protocols.contains(&Protocol::Kyber));
+        let known = inspection.known();
+        let trade = known
+            .iter()
+            .find_map(|x| x.as_ref().trade())
+            .cloned()
+            .unwrap();
+        assert_eq!(ADDRESSBOOK.get(&trade.t1.token).unwrap(), "USDC");
+        assert_eq!(trade.t1.amount, U256::from(0x171533fbde_u64));
+        assert_eq!(ADDRESSBOOK.get(&trade.t2.token).unwrap(), "YFI");
+        assert_eq!(trade.t1.from, trade.t2.to);
+        assert_eq!(trade.t2.amount.to_string(), "5722594594602333796");
+    }
+}
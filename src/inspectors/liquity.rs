@@ -0,0 +1,362 @@
+use crate::{
+    addresses::{ETH, LIQUITY_ACTIVE_POOL, LIQUITY_TROVE_MANAGER, LUSD},
+    is_subtrace,
+    types::{
+        actions::{Liquidation, Redemption, Transfer},
+        Classification, Inspection, Protocol,
+    },
+    Inspector,
+};
+use ethers::{
+    abi::parse_abi,
+    contract::BaseContract,
+    types::{Address, Call as TraceCall, U256},
+};
+
+type RedeemCollateral = (U256, Address, Address, Address, U256, U256, U256);
+type PoolTransfer = (Address, Address, U256);
+
+#[derive(Debug, Clone)]
+/// An inspector for Liquity trove liquidations and LUSD redemptions
+pub struct Liquity {
+    trove_manager: BaseContract,
+    pools: BaseContract,
+}
+
+/// What the caller of the Trove Manager got out of the call
+#[derive(Default)]
+struct Received {
+    eth: U256,
+    /// The LUSD gas compensation, and the index of the call which paid it
+    lusd: Option<(usize, Transfer)>,
+    /// The LUSD burned by a redemption
+    burned: U256,
+    prune: Vec<usize>,
+}
+
+impl Inspector for Liquity {
+    fn inspect(&self, inspection: &mut Inspection) {
+        let actions = inspection.actions.to_vec();
+        let mut prune = Vec::new();
+        for i in 0..inspection.actions.len() {
+            let calltrace = match actions[i].as_call() {
+                Some(calltrace) if calltrace.call.to == *LIQUITY_TROVE_MANAGER => calltrace,
+                _ => continue,
+            };
+            let call = calltrace.as_ref();
+            let trace_address = calltrace.trace_address.clone();
+            inspection.protocols.insert(Protocol::Liquity);
+
+            let received = self.received(call, &actions, i, &trace_address);
+            if let Some(liquidated_user) = self.try_as_liquidation(call) {
+                inspection.actions[i] = Classification::new(
+                    Liquidation {
+                        // the debt is absorbed by the Stability Pool, so the
+                        // liquidator does not pay anything
+                        sent_token: *LUSD,
+                        sent_amount: U256::zero(),
+                        received_token: *ETH,
+                        received_amount: received.eth,
+                        from: call.from,
+                        liquidated_user,
+                    },
+                    trace_address,
+                );
+
+                // the gas compensation is paid out separately from the Gas
+                // Pool, it is not part of the liquidation's proceeds
+                if let Some((idx, transfer)) = received.lusd {
+                    inspection.actions[idx] =
+                        Classification::new(transfer, actions[idx].trace_address());
+                }
+            } else if self
+                .trove_manager
+                .decode::<RedeemCollateral, _>("redeemCollateral", &call.input)
+                .is_ok()
+            {
+                inspection.actions[i] = Classification::new(
+                    Redemption {
+                        sent_token: *LUSD,
+                        sent_amount: received.burned,
+                        received_token: *ETH,
+                        received_amount: received.eth,
+                        from: call.from,
+                    },
+                    trace_address,
+                );
+            } else {
+                continue;
+            }
+            prune.extend(received.prune);
+        }
+
+        for idx in prune {
+            inspection.actions[idx] = Classification::Prune;
+        }
+    }
+}
+
+impl Liquity {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            trove_manager: BaseContract::from(
+                parse_abi(&[
+                    "function liquidate(address _borrower)",
+                    "function liquidateTroves(uint256 _n)",
+                    "function batchLiquidateTroves(address[] calldata _troveArray)",
+                    "function redeemCollateral(uint256 _LUSDamount, address _firstRedemptionHint, address _upperPartialRedemptionHint, address _lowerPartialRedemptionHint, uint256 _partialRedemptionHintNICR, uint256 _maxIterations, uint256 _maxFeePercentage)",
+                ])
+                .expect("could not parse liquity trove manager abi"),
+            ),
+            pools: BaseContract::from(
+                parse_abi(&[
+                    // ActivePool
+                    "function sendETH(address _account, uint256 _amount)",
+                    // LUSDToken
+                    "function returnFromPool(address _poolAddress, address _receiver, uint256 _amount)",
+                    "function burn(address _account, uint256 _amount)",
+                ])
+                .expect("could not parse liquity pools abi"),
+            ),
+        }
+    }
+
+    /// Returns the liquidated trove's owner. Liquidations of multiple troves
+    /// get the zero address.
+    fn try_as_liquidation(&self, call: &TraceCall) -> Option<Address> {
+        if let Ok(borrower) = self
+            .trove_manager
+            .decode::<Address, _>("liquidate", &call.input)
+        {
+            Some(borrower)
+        } else if let Ok(troves) = self
+            .trove_manager
+            .decode::<Vec<Address>, _>("batchLiquidateTroves", &call.input)
+        {
+            match troves.as_slice() {
+                [borrower] => Some(*borrower),
+                _ => Some(Address::zero()),
+            }
+        } else if self
+            .trove_manager
+            .decode::<U256, _>("liquidateTroves", &call.input)
+            .is_ok()
+        {
+            Some(Address::zero())
+        } else {
+            None
+        }
+    }
+
+    /// Walks the subtraces of the Trove Manager call at `i` and collects
+    /// the ETH and LUSD that were sent to (or burned from) its caller
+    fn received(
+        &self,
+        call: &TraceCall,
+        actions: &[Classification],
+        i: usize,
+        trace_address: &[usize],
+    ) -> Received {
+        let mut received = Received::default();
+        let subtraces = actions.iter().enumerate().skip(i + 1).filter(|(_, t)| {
+            trace_address.is_empty() || is_subtrace(trace_address, &t.trace_address())
+        });
+        for (idx, action) in subtraces {
+            match action {
+                Classification::Unknown(calltrace) => {
+                    let sub = calltrace.as_ref();
+                    if let Ok((account, amount)) = self
+                        .pools
+                        .decode::<(Address, U256), _>("sendETH", &sub.input)
+                    {
+                        if sub.to == *LIQUITY_ACTIVE_POOL && account == call.from {
+                            received.eth += amount;
+                            received.prune.push(idx);
+                        }
+                    } else if let Ok((pool, receiver, amount)) = self
+                        .pools
+                        .decode::<PoolTransfer, _>("returnFromPool", &sub.input)
+                    {
+                        if sub.to == *LUSD && receiver == call.from {
+                            let transfer = Transfer {
+                                token: *LUSD,
+                                from: pool,
+                                to: receiver,
+                                amount,
+                            };
+                            received.lusd = Some((idx, transfer));
+                        }
+                    } else if let Ok((account, amount)) =
+                        self.pools.decode::<(Address, U256), _>("burn", &sub.input)
+                    {
+                        if sub.to == *LUSD && account == call.from {
+                            received.burned += amount;
+                            received.prune.push(idx);
+                        }
+                    }
+                }
+                Classification::Known(_) => {
+                    // the ETH which the Active Pool sends in `sendETH`
+                    if let Some(transfer) = action.as_action().and_then(|x| x.transfer()) {
+                        if transfer.token == *ETH
+                            && transfer.from == *LIQUITY_ACTIVE_POOL
+                            && transfer.to == call.from
+                        {
+                            received.prune.push(idx);
+                        }
+                    }
+                }
+                Classification::Prune => (),
+            }
+        }
+        received
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{inspectors::ERC20, test_helpers::*};
+    use ethers::types::Bytes;
+
+    struct MyInspector {
+        erc20: ERC20,
+        liquity: Liquity,
+    }
+
+    impl MyInspector {
+        fn inspect(&self, inspection: &mut Inspection) {
+            self.erc20.inspect(inspection);
+            self.liquity.inspect(inspection);
+            inspection.prune();
+        }
+
+        fn new() -> Self {
+            Self {
+                erc20: ERC20::new(),
+                liquity: Liquity::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn liquidate() {
+        let addrs = addrs();
+        let (liquidator, borrower, gas_pool) = (addrs[0], addrs[1], addrs[2]);
+        let inspector = MyInspector::new();
+        let liquity = &inspector.liquity;
+
+        let input = liquity.trove_manager.encode("liquidate", borrower).unwrap();
+        let send_eth = liquity
+            .pools
+            .encode("sendETH", (liquidator, U256::from(5)))
+            .unwrap();
+        let gas_compensation = liquity
+            .pools
+            .encode("returnFromPool", (gas_pool, liquidator, U256::from(200)))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(liquidator, *LIQUITY_TROVE_MANAGER, 0, input, vec![]),
+            mk_call(
+                *LIQUITY_TROVE_MANAGER,
+                *LIQUITY_ACTIVE_POOL,
+                0,
+                send_eth,
+                vec![0],
+            ),
+            mk_call(
+                *LIQUITY_ACTIVE_POOL,
+                liquidator,
+                5,
+                Bytes::default(),
+                vec![0, 0],
+            ),
+            mk_call(*LIQUITY_TROVE_MANAGER, *LUSD, 0, gas_compensation, vec![1]),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::Liquity]);
+        let known = inspection.known();
+        assert_eq!(known.len(), 2);
+        let liquidation = known[0].as_ref().liquidation().unwrap();
+        assert_eq!(
+            (liquidation.received_token, liquidation.received_amount),
+            (*ETH, 5.into())
+        );
+        assert_eq!(liquidation.liquidated_user, borrower);
+        assert_eq!(
+            *known[1].as_ref().transfer().unwrap(),
+            Transfer {
+                token: *LUSD,
+                from: gas_pool,
+                to: liquidator,
+                amount: 200.into(),
+            }
+        );
+    }
+
+    #[test]
+    fn redeem() {
+        let addrs = addrs();
+        let redeemer = addrs[0];
+        let inspector = MyInspector::new();
+        let liquity = &inspector.liquity;
+
+        let input = liquity
+            .trove_manager
+            .encode(
+                "redeemCollateral",
+                (
+                    U256::from(1000),
+                    Address::zero(),
+                    Address::zero(),
+                    Address::zero(),
+                    U256::zero(),
+                    U256::zero(),
+                    U256::from(5),
+                ),
+            )
+            .unwrap();
+        let burn = liquity
+            .pools
+            .encode("burn", (redeemer, U256::from(1000)))
+            .unwrap();
+        let send_eth = liquity
+            .pools
+            .encode("sendETH", (redeemer, U256::from(3)))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(redeemer, *LIQUITY_TROVE_MANAGER, 0, input, vec![]),
+            mk_call(*LIQUITY_TROVE_MANAGER, *LUSD, 0, burn, vec![0]),
+            mk_call(
+                *LIQUITY_TROVE_MANAGER,
+                *LIQUITY_ACTIVE_POOL,
+                0,
+                send_eth,
+                vec![1],
+            ),
+            mk_call(
+                *LIQUITY_ACTIVE_POOL,
+                redeemer,
+                3,
+                Bytes::default(),
+                vec![1, 0],
+            ),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        let known = inspection.known();
+        assert_eq!(known.len(), 1);
+        assert_eq!(
+            *known[0].as_ref().redemption().unwrap(),
+            Redemption {
+                sent_token: *LUSD,
+                sent_amount: 1000.into(),
+                received_token: *ETH,
+                received_amount: 3.into(),
+                from: redeemer,
+            }
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use crate::{
+    addresses::{DAI, MAKER_CAT, MAKER_DOG, MAKER_ILKS, MAKER_VAT},
+    is_subtrace,
+    types::{actions::Liquidation, Classification, Inspection, Protocol},
+    Inspector,
+};
+use ethers::{
+    abi::parse_abi,
+    contract::BaseContract,
+    types::{Address, Bytes, Call as TraceCall, U256},
+};
+
+type Bite = ([u8; 32], Address);
+type Bark = ([u8; 32], Address, Address);
+type Bid = (U256, U256, U256);
+type Take = (U256, U256, U256, Address, Bytes);
+type Flux = ([u8; 32], Address, Address, U256);
+type Move = (Address, Address, U256);
+
+#[derive(Debug, Clone)]
+/// An inspector for MakerDAO liquidations, covering both the Cat/Flipper
+/// auctions and the Dog/Clipper auctions of Liquidations 2.0
+pub struct Maker {
+    auctions: BaseContract,
+    vat: BaseContract,
+}
+
+/// A Vat call made by one of the auction contracts, which moves
+/// the DAI and the collateral around
+enum VatCall {
+    Flux(Flux),
+    Move(Move),
+}
+
+impl Inspector for Maker {
+    fn inspect(&self, inspection: &mut Inspection) {
+        let actions = inspection.actions.to_vec();
+        let mut prune = Vec::new();
+        for i in 0..inspection.actions.len() {
+            let action = &mut inspection.actions[i];
+            let calltrace = match action.as_call() {
+                Some(calltrace) => calltrace,
+                None => continue,
+            };
+            let call = calltrace.as_ref();
+
+            let liquidation = if call.to == *MAKER_CAT {
+                self.try_as_bite(call)
+            } else if call.to == *MAKER_DOG {
+                self.try_as_bark(call)
+            } else {
+                // Flippers and Clippers are deployed per ilk, so rather than
+                // tracking their addresses we require that they touch the Vat
+                let trace_address = &calltrace.trace_address;
+                let vat_calls = actions[i + 1..]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, t)| {
+                        trace_address.is_empty() || is_subtrace(trace_address, &t.trace_address())
+                    })
+                    .filter_map(|(j, t)| {
+                        let sub = t.as_call()?.as_ref();
+                        if sub.from == call.to && sub.to == *MAKER_VAT {
+                            Some((i + 1 + j, self.try_as_vat_call(sub)?))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                if vat_calls.is_empty() {
+                    continue;
+                }
+
+                let liquidation = self.try_as_auction(call, &vat_calls);
+                if liquidation.is_some() {
+                    prune.extend(vat_calls.iter().map(|(idx, _)| *idx));
+                }
+                liquidation
+            };
+
+            if let Some(liquidation) = liquidation {
+                inspection.protocols.insert(Protocol::Maker);
+                *action = Classification::new(liquidation, calltrace.trace_address.clone());
+            }
+        }
+
+        for idx in prune {
+            inspection.actions[idx] = Classification::Prune;
+        }
+    }
+}
+
+impl Maker {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            auctions: BaseContract::from(
+                parse_abi(&[
+                    // Cat
+                    "function bite(bytes32 ilk, address urn) returns (uint256 id)",
+                    // Dog
+                    "function bark(bytes32 ilk, address urn, address kpr) returns (uint256 id)",
+                    // Flipper
+                    "function tend(uint256 id, uint256 lot, uint256 bid)",
+                    "function dent(uint256 id, uint256 lot, uint256 bid)",
+                    "function deal(uint256 id)",
+                    // Clipper
+                    "function take(uint256 id, uint256 amt, uint256 max, address who, bytes calldata data)",
+                ])
+                .expect("could not parse maker auctions abi"),
+            ),
+            vat: BaseContract::from(
+                parse_abi(&[
+                    "function flux(bytes32 ilk, address src, address dst, uint256 wad)",
+                    "function move(address src, address dst, uint256 rad)",
+                ])
+                .expect("could not parse maker vat abi"),
+            ),
+        }
+    }
+
+    fn try_as_bite(&self, call: &TraceCall) -> Option<Liquidation> {
+        let (ilk, urn) = self.auctions.decode::<Bite, _>("bite", &call.input).ok()?;
+        // the vault is only seized here, the DAI gets paid in the Flipper auction
+        Some(Liquidation {
+            sent_token: *DAI,
+            sent_amount: U256::zero(),
+            received_token: gem(ilk)?,
+            received_amount: U256::zero(),
+            from: call.from,
+            liquidated_user: urn,
+        })
+    }
+
+    fn try_as_bark(&self, call: &TraceCall) -> Option<Liquidation> {
+        let (ilk, urn, kpr) = self.auctions.decode::<Bark, _>("bark", &call.input).ok()?;
+        // the vault is only seized here, the DAI gets paid in the Clipper auction
+        Some(Liquidation {
+            sent_token: *DAI,
+            sent_amount: U256::zero(),
+            received_token: gem(ilk)?,
+            received_amount: U256::zero(),
+            from: kpr,
+            liquidated_user: urn,
+        })
+    }
+
+    fn try_as_vat_call(&self, call: &TraceCall) -> Option<VatCall> {
+        if let Ok(flux) = self.vat.decode::<Flux, _>("flux", &call.input) {
+            Some(VatCall::Flux(flux))
+        } else if let Ok(mv) = self.vat.decode::<Move, _>("move", &call.input) {
+            Some(VatCall::Move(mv))
+        } else {
+            None
+        }
+    }
+
+    /// Decodes a Flipper or Clipper auction call. The auctions do not know
+    /// the liquidated vault, so it is left empty. Calls which do not move any
+    /// collateral of a known ilk are not classified, since their collateral
+    /// token cannot be told.
+    fn try_as_auction(
+        &self,
+        call: &TraceCall,
+        vat_calls: &[(usize, VatCall)],
+    ) -> Option<Liquidation> {
+        // the collateral sent out of the auction to `dst`
+        let flux_to = |dst: Address| {
+            vat_calls.iter().find_map(|(_, vat_call)| match vat_call {
+                VatCall::Flux((_, _, to, wad)) if *to == dst => Some(*wad),
+                _ => None,
+            })
+        };
+        // any collateral moved by the auction tells us its ilk
+        let ilk = vat_calls.iter().find_map(|(_, vat_call)| match vat_call {
+            VatCall::Flux((ilk, ..)) => Some(*ilk),
+            _ => None,
+        })?;
+
+        let mut liquidation = Liquidation {
+            sent_token: *DAI,
+            sent_amount: U256::zero(),
+            received_token: gem(ilk)?,
+            received_amount: U256::zero(),
+            from: call.from,
+            liquidated_user: Address::zero(),
+        };
+
+        if let Ok((_, lot, bid)) = self
+            .auctions
+            .decode::<Bid, _>("tend", &call.input)
+            .or_else(|_| self.auctions.decode::<Bid, _>("dent", &call.input))
+        {
+            // bids are only settled in `deal`, so these are the amounts
+            // the keeper commits to
+            liquidation.sent_amount = rad_to_wad(bid);
+            liquidation.received_amount = lot;
+        } else if self.auctions.decode::<U256, _>("deal", &call.input).is_ok() {
+            // anyone can settle, the collateral goes to the winning bidder
+            let (dst, wad) = vat_calls.iter().find_map(|(_, vat_call)| match vat_call {
+                VatCall::Flux((_, _, dst, wad)) => Some((*dst, *wad)),
+                _ => None,
+            })?;
+            liquidation.from = dst;
+            liquidation.received_amount = wad;
+        } else if let Ok((_, _, _, who, _)) = self.auctions.decode::<Take, _>("take", &call.input) {
+            let wad = flux_to(who)?;
+            let owe = vat_calls
+                .iter()
+                .filter_map(|(_, vat_call)| match vat_call {
+                    VatCall::Move((src, _, rad)) if *src == call.from => Some(*rad),
+                    _ => None,
+                })
+                .fold(U256::zero(), |acc, rad| acc + rad);
+
+            liquidation.received_amount = wad;
+            liquidation.sent_amount = rad_to_wad(owe);
+        } else {
+            return None;
+        }
+
+        Some(liquidation)
+    }
+}
+
+/// Returns the collateral token of an ilk, if it's known
+fn gem(ilk: [u8; 32]) -> Option<Address> {
+    let end = ilk.iter().position(|b| *b == 0).unwrap_or(ilk.len());
+    let name = String::from_utf8_lossy(&ilk[..end]);
+    MAKER_ILKS.get(name.as_ref()).cloned()
+}
+
+/// DAI balances in the Vat are denominated in rad (45 decimals)
+fn rad_to_wad(rad: U256) -> U256 {
+    rad / U256::exp10(27)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addresses::WETH,
+        inspectors::ERC20,
+        reducers::LiquidationReducer,
+        test_helpers::*,
+        types::actions::{ProfitableLiquidation, Trade, Transfer},
+        Reducer,
+    };
+
+    struct MyInspector {
+        erc20: ERC20,
+        maker: Maker,
+        reducer: LiquidationReducer,
+    }
+
+    impl MyInspector {
+        fn inspect(&self, inspection: &mut Inspection) {
+            self.erc20.inspect(inspection);
+            self.maker.inspect(inspection);
+            self.reducer.reduce(inspection);
+            inspection.prune();
+        }
+
+        fn new() -> Self {
+            Self {
+                erc20: ERC20::new(),
+                maker: Maker::new(),
+                reducer: LiquidationReducer::new(),
+            }
+        }
+    }
+
+    fn ilk(name: &str) -> [u8; 32] {
+        let mut ilk = [0; 32];
+        ilk[..name.len()].copy_from_slice(name.as_bytes());
+        ilk
+    }
+
+    #[test]
+    fn bite() {
+        let addrs = addrs();
+        let (keeper, urn) = (addrs[0], addrs[1]);
+        let inspector = MyInspector::new();
+
+        let input = inspector
+            .maker
+            .auctions
+            .encode("bite", (ilk("ETH-A"), urn))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![mk_call(keeper, *MAKER_CAT, 0, input, vec![])]);
+        inspector.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::Maker]);
+        let known = inspection.known();
+        let liquidation = known[0].as_ref().liquidation().unwrap();
+        assert_eq!(liquidation.received_token, *WETH);
+        assert_eq!(liquidation.liquidated_user, urn);
+        assert_eq!(liquidation.from, keeper);
+    }
+
+    #[test]
+    fn ignores_unknown_ilks() {
+        let addrs = addrs();
+        let inspector = MyInspector::new();
+
+        let input = inspector
+            .maker
+            .auctions
+            .encode("bite", (ilk("NEW-A"), addrs[1]))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![mk_call(addrs[0], *MAKER_CAT, 0, input, vec![])]);
+        inspector.inspect(&mut inspection);
+
+        assert!(inspection.known().is_empty());
+        assert!(inspection.protocols.is_empty());
+    }
+
+    #[test]
+    fn take_and_sell() {
+        let addrs = addrs();
+        let (eoa, keeper, clipper, vow, join, pair) =
+            (addrs[0], addrs[1], addrs[2], addrs[3], addrs[4], addrs[5]);
+        let inspector = MyInspector::new();
+        let maker = &inspector.maker;
+        let ray = U256::exp10(27);
+
+        let take = maker
+            .auctions
+            .encode(
+                "take",
+                (
+                    U256::from(1),
+                    U256::from(10),
+                    U256::from(11) * ray,
+                    keeper,
+                    Bytes::default(),
+                ),
+            )
+            .unwrap();
+        let flux = maker
+            .vat
+            .encode("flux", (ilk("ETH-A"), clipper, keeper, U256::from(10)))
+            .unwrap();
+        let mv = maker
+            .vat
+            .encode("move", (keeper, vow, U256::from(100) * ray))
+            .unwrap();
+        // the keeper sells 8 of the 10 collateral it received for the DAI it owes
+        let trade = Trade {
+            t1: Transfer {
+                token: *WETH,
+                from: keeper,
+                to: pair,
+                amount: 8.into(),
+            },
+            t2: Transfer {
+                token: *DAI,
+                from: pair,
+                to: keeper,
+                amount: 100.into(),
+            },
+        };
+
+        let mut inspection = mk_inspection(vec![
+            mk_call(eoa, keeper, 0, Bytes::default(), vec![]),
+            mk_call(keeper, clipper, 0, take, vec![0]),
+            mk_call(clipper, *MAKER_VAT, 0, flux, vec![0, 0]),
+            // the keeper exits the collateral from the Vat
+            mk_call(
+                join,
+                *WETH,
+                0,
+                erc20()
+                    .encode("transfer", (keeper, U256::from(10)))
+                    .unwrap(),
+                vec![0, 1, 0],
+            ),
+            Classification::new(trade.clone(), vec![0, 1, 1]),
+            mk_call(clipper, *MAKER_VAT, 0, mv, vec![0, 2]),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::Maker]);
+        let known = inspection.known();
+        assert_eq!(known.len(), 2);
+        assert_eq!(
+            *known[0].as_ref().profitable_liquidation().unwrap(),
+            ProfitableLiquidation {
+                token: *WETH,
+                profit: 2.into(),
+                liquidation: Liquidation {
+                    sent_token: *DAI,
+                    sent_amount: 100.into(),
+                    received_token: *WETH,
+                    received_amount: 10.into(),
+                    from: keeper,
+                    liquidated_user: Address::zero(),
+                },
+            }
+        );
+        assert_eq!(*known[1].as_ref().trade().unwrap(), trade);
+    }
+
+    #[test]
+    fn ignores_calls_without_vat() {
+        let addrs = addrs();
+        let inspector = MyInspector::new();
+
+        let input = inspector
+            .maker
+            .auctions
+            .encode("deal", U256::from(1))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![mk_call(addrs[0], addrs[1], 0, input, vec![])]);
+        inspector.inspect(&mut inspection);
+
+        assert!(inspection.known().is_empty());
+        assert!(inspection.protocols.is_empty());
+    }
+}
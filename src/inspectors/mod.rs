@@ -8,6 +8,10 @@ mod uniswap;
 /// A Uniswap inspector
 pub use uniswap::Uniswap;
 
+mod uniswap_v1;
+/// A Uniswap V1 inspector
+pub use uniswap_v1::UniswapV1;
+
 mod curve;
 /// A Curve inspector
 pub use curve::Curve;
@@ -20,6 +24,14 @@ mod aave;
 /// An Aave inspector
 pub use aave::Aave;
 
+mod maker;
+/// A MakerDAO inspector
+pub use maker::Maker;
+
+mod liquity;
+/// A Liquity inspector
+pub use liquity::Liquity;
+
 mod erc20;
 /// ERC20 Inspector, to be used for parsing subtraces involving transfer/transferFrom
 pub use erc20::ERC20;
@@ -28,13 +40,40 @@ mod batch;
 /// Takes multiple inspectors
 pub use batch::{BatchEvaluationError, BatchInspector};
 
+mod kyber;
+/// A Kyber inspector
+pub use kyber::Kyber;
+
+mod bancor;
+/// A Bancor inspector
+pub use bancor::Bancor;
+
+mod synthetix;
+/// A Synthetix inspector
+pub use synthetix::Synthetix;
+
 mod compound;
-pub use compound::Compound;
+pub use compound::{Compound, CompoundDeployment};
 
 mod zeroex;
 pub use zeroex::ZeroEx;
 
-use crate::types::{actions::SpecificAction, Classification};
+mod oneinch;
+/// A 1inch inspector
+pub use oneinch::OneInch;
+
+mod paraswap;
+/// A Paraswap inspector
+pub use paraswap::Paraswap;
+
+use crate::{
+    is_subtrace,
+    types::{
+        actions::{SpecificAction, Transfer},
+        Classification,
+    },
+};
+use ethers::types::Address;
 
 /// Given an iterator over index,Classification tuples, it will try to cast
 /// each classification to the given specific action (depending on the function given
@@ -58,7 +97,7 @@ where
     actions.find_map(|(j, a)| {
         if check_all || !found_known {
             if let Some(action) = a.as_action() {
-                if let Some(t) = cast(&action) {
+                if let Some(t) = cast(action) {
                     if check_fn(t) {
                         return Some((j, t));
                     }
@@ -69,3 +108,59 @@ where
         None
     })
 }
+
+/// Returns the transfers made in the subtraces of the call at index `i`, along
+/// with their indices. Subtraces which have not been classified yet are parsed
+/// with `erc20`, so that this can be used by inspectors which run before the
+/// `ERC20` one.
+pub(crate) fn subtrace_transfers<'a>(
+    erc20: &'a ERC20,
+    actions: &'a [Classification],
+    i: usize,
+) -> impl Iterator<Item = (usize, Transfer)> + 'a {
+    let trace_address = actions[i].trace_address();
+    actions
+        .iter()
+        .enumerate()
+        .skip(i + 1)
+        .filter(move |(_, t)| {
+            trace_address.is_empty() || is_subtrace(&trace_address, &t.trace_address())
+        })
+        .filter_map(move |(idx, t)| match t {
+            Classification::Known(_) => t
+                .as_action()
+                .and_then(|t| t.transfer())
+                .map(|t| (idx, t.clone())),
+            Classification::Unknown(call) => match erc20.try_parse(call.as_ref()) {
+                Some(SpecificAction::Transfer(transfer)) => Some((idx, transfer)),
+                _ => None,
+            },
+            Classification::Prune => None,
+        })
+}
+
+/// Returns the last transfer of `token` to `to` which was made in the subtraces
+/// of the call at index `i`. Used for finding the amount a swap paid out.
+pub(crate) fn last_transfer_to(
+    erc20: &ERC20,
+    actions: &[Classification],
+    i: usize,
+    token: Address,
+    to: Address,
+) -> Option<Transfer> {
+    subtrace_transfers(erc20, actions, i)
+        .map(|(_, t)| t)
+        .filter(|t| t.token == token && t.to == to)
+        .last()
+}
+
+/// Prunes all the subtraces of the call at index `i`. Unlike
+/// `Classification::prune_subcalls`, this also covers the top level call.
+pub(crate) fn prune_subtraces(actions: &mut [Classification], i: usize) {
+    let trace_address = actions[i].trace_address();
+    for action in actions[i + 1..].iter_mut() {
+        if trace_address.is_empty() || is_subtrace(&trace_address, &action.trace_address()) {
+            *action = Classification::Prune;
+        }
+    }
+}
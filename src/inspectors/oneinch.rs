@@ -0,0 +1,293 @@
+use crate::{
+    is_subtrace,
+    registry::registry,
+    traits::Inspector,
+    types::{actions::AggregatorSwap, Classification, Inspection, Protocol},
+};
+
+use ethers::{
+    abi::{Abi, Token},
+    contract::BaseContract,
+    types::{Address, Bytes, Call as TraceCall, U256},
+};
+
+// Type aliases for the 1inch routers' swap descriptions
+// (srcToken, dstToken, srcReceiver, dstReceiver, amount, minReturnAmount, ..)
+type SwapDescriptionV2 = (
+    Address,
+    Address,
+    Address,
+    Address,
+    U256,
+    U256,
+    U256,
+    U256,
+    Address,
+    Bytes,
+);
+type SwapDescriptionV3 = (Address, Address, Address, Address, U256, U256, U256, Bytes);
+type SwapV2 = (Address, SwapDescriptionV2, Token);
+type SwapV3 = (Address, SwapDescriptionV3, Bytes);
+type Unoswap = (Address, U256, U256, Vec<[u8; 32]>);
+
+#[derive(Debug, Clone)]
+/// An inspector for the 1inch v2 and v3 aggregation routers
+pub struct OneInch {
+    v2: BaseContract,
+    v3: BaseContract,
+}
+
+impl Inspector for OneInch {
+    fn inspect(&self, inspection: &mut Inspection) {
+        let actions = inspection.actions.to_vec();
+        for i in 0..inspection.actions.len() {
+            let action = &mut inspection.actions[i];
+
+            if let Some(calltrace) = action.as_call() {
+                let call = calltrace.as_ref();
+                if registry().protocol(&call.to) != Some(Protocol::OneInch) {
+                    continue;
+                }
+                inspection.protocols.insert(Protocol::OneInch);
+
+                let swap = if let Some(swap) = self.try_as_swap(call) {
+                    swap
+                } else if let Some((token_in, amount_in, min_amount_out)) =
+                    self.try_as_unoswap(call)
+                {
+                    // `unoswap` only encodes the pools, so the token out is the
+                    // last token that the router sends back to the caller
+                    let trace_address = &calltrace.trace_address;
+                    let token_out = actions[i + 1..]
+                        .iter()
+                        .filter(|t| {
+                            trace_address.is_empty()
+                                || is_subtrace(trace_address, &t.trace_address())
+                        })
+                        .filter_map(|t| t.as_action().and_then(|t| t.transfer()))
+                        .filter(|t| t.to == call.from)
+                        .last()
+                        .map(|t| t.token);
+                    let token_out = match token_out {
+                        Some(token_out) => token_out,
+                        None => continue,
+                    };
+
+                    AggregatorSwap {
+                        token_in,
+                        amount_in,
+                        token_out,
+                        min_amount_out,
+                        from: call.from,
+                        recipient: call.from,
+                    }
+                } else {
+                    continue;
+                };
+
+                *action = Classification::new(swap, calltrace.trace_address.clone());
+            }
+        }
+    }
+}
+
+impl OneInch {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            v2: BaseContract::from({
+                serde_json::from_str::<Abi>(include_str!("../../abi/oneinchv2.json"))
+                    .expect("could not parse 1inch v2 abi")
+            }),
+            v3: BaseContract::from({
+                serde_json::from_str::<Abi>(include_str!("../../abi/oneinchv3.json"))
+                    .expect("could not parse 1inch v3 abi")
+            }),
+        }
+    }
+
+    fn try_as_swap(&self, call: &TraceCall) -> Option<AggregatorSwap> {
+        let (token_in, token_out, dst_receiver, amount_in, min_amount_out) =
+            if let Ok((_, desc, _)) = self.v3.decode::<SwapV3, _>("swap", &call.input) {
+                (desc.0, desc.1, desc.3, desc.4, desc.5)
+            } else if let Ok((_, desc, _)) = self.v2.decode::<SwapV2, _>("swap", &call.input) {
+                (desc.0, desc.1, desc.3, desc.4, desc.5)
+            } else {
+                return None;
+            };
+
+        Some(AggregatorSwap {
+            token_in,
+            amount_in,
+            token_out,
+            min_amount_out,
+            from: call.from,
+            // the router pays out to the caller if no receiver is set
+            recipient: if dst_receiver.is_zero() {
+                call.from
+            } else {
+                dst_receiver
+            },
+        })
+    }
+
+    fn try_as_unoswap(&self, call: &TraceCall) -> Option<(Address, U256, U256)> {
+        let (token_in, amount_in, min_amount_out, _) =
+            self.v3.decode::<Unoswap, _>("unoswap", &call.input).ok()?;
+        Some((token_in, amount_in, min_amount_out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addresses::{parse_address, ETH},
+        inspectors::ERC20,
+        test_helpers::*,
+    };
+
+    struct MyInspector {
+        erc20: ERC20,
+        oneinch: OneInch,
+    }
+
+    impl MyInspector {
+        fn inspect(&self, inspection: &mut Inspection) {
+            self.erc20.inspect(inspection);
+            self.oneinch.inspect(inspection);
+            inspection.prune();
+        }
+
+        fn new() -> Self {
+            Self {
+                erc20: ERC20::new(),
+                oneinch: OneInch::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn v3_swap() {
+        let addrs = addrs();
+        let (usr, executor, receiver, token_in, token_out) =
+            (addrs[0], addrs[1], addrs[2], addrs[3], addrs[4]);
+        let router = parse_address("0x11111112542d85b3ef69ae05771c2dccff4faa26");
+        let inspector = MyInspector::new();
+
+        let desc: SwapDescriptionV3 = (
+            token_in,
+            token_out,
+            executor,
+            receiver,
+            100.into(),
+            90.into(),
+            0.into(),
+            Default::default(),
+        );
+        let input = inspector
+            .oneinch
+            .v3
+            .encode("swap", (executor, desc, Bytes::default()))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![mk_call(usr, router, 0, input, vec![])]);
+        inspector.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::OneInch]);
+        let known = inspection.known();
+        let swap = known[0].as_ref().aggregator_swap().unwrap();
+        assert_eq!(
+            *swap,
+            AggregatorSwap {
+                token_in,
+                amount_in: 100.into(),
+                token_out,
+                min_amount_out: 90.into(),
+                from: usr,
+                recipient: receiver,
+            }
+        );
+    }
+
+    #[test]
+    fn v2_swap_to_sender() {
+        let addrs = addrs();
+        let (usr, executor, token_in) = (addrs[0], addrs[1], addrs[2]);
+        let router = parse_address("0x111111125434b319222cdbf8c261674adb56f3ae");
+        let inspector = MyInspector::new();
+
+        let desc: SwapDescriptionV2 = (
+            token_in,
+            *ETH,
+            executor,
+            // pays out to the sender
+            Address::zero(),
+            100.into(),
+            90.into(),
+            95.into(),
+            0.into(),
+            Address::zero(),
+            Default::default(),
+        );
+        let calls: Vec<(U256, U256, U256, Bytes)> = vec![];
+        let input = inspector
+            .oneinch
+            .v2
+            .encode("swap", (executor, desc, calls))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![mk_call(usr, router, 0, input, vec![])]);
+        inspector.inspect(&mut inspection);
+
+        let known = inspection.known();
+        let swap = known[0].as_ref().aggregator_swap().unwrap();
+        assert_eq!(swap.token_out, *ETH);
+        assert_eq!(swap.recipient, usr);
+    }
+
+    #[test]
+    fn unoswap_token_out_from_transfers() {
+        let addrs = addrs();
+        let (usr, pair, token_in, token_out) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let router = parse_address("0x11111112542d85b3ef69ae05771c2dccff4faa26");
+        let inspector = MyInspector::new();
+        let erc20 = erc20();
+
+        let input = inspector
+            .oneinch
+            .v3
+            .encode(
+                "unoswap",
+                (token_in, U256::from(100), U256::from(90), vec![[0u8; 32]]),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(usr, router, 0, input, vec![]),
+            mk_call(
+                router,
+                token_in,
+                0,
+                erc20
+                    .encode("transferFrom", (usr, pair, U256::from(100)))
+                    .unwrap(),
+                vec![0],
+            ),
+            mk_call(
+                pair,
+                token_out,
+                0,
+                erc20.encode("transfer", (usr, U256::from(95))).unwrap(),
+                vec![1],
+            ),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        let known = inspection.known();
+        let swap = known[0].as_ref().aggregator_swap().unwrap();
+        assert_eq!(swap.token_in, token_in);
+        assert_eq!(swap.token_out, token_out);
+        assert_eq!(swap.min_amount_out, 90.into());
+        assert_eq!(swap.recipient, usr);
+        // the underlying transfers are kept for the reducers
+        assert_eq!(known.len(), 3);
+    }
+}
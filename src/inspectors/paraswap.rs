@@ -0,0 +1,234 @@
+use crate::{
+    registry::registry,
+    traits::Inspector,
+    types::{actions::AggregatorSwap, Classification, Inspection, Protocol},
+};
+
+use ethers::{
+    abi::{Abi, Token},
+    contract::BaseContract,
+    types::{Address, Call as TraceCall, U256},
+};
+
+// Type aliases for Paraswap's `SellData`
+// (fromToken, fromAmount, toAmount, expectedAmount, beneficiary, referrer, useReduxToken, path)
+type SellData = (Address, U256, U256, U256, Address, String, bool, Token);
+type SwapOnUniswap = (U256, U256, Vec<Address>, u8);
+type SwapOnUniswapFork = (Address, [u8; 32], U256, U256, Vec<Address>, u8);
+
+#[derive(Debug, Clone)]
+/// An inspector for the Paraswap Augustus swapper
+pub struct Paraswap {
+    augustus: BaseContract,
+}
+
+impl Inspector for Paraswap {
+    fn inspect(&self, inspection: &mut Inspection) {
+        for action in inspection.actions.iter_mut() {
+            if let Some(calltrace) = action.as_call() {
+                let call = calltrace.as_ref();
+                if registry().protocol(&call.to) != Some(Protocol::Paraswap) {
+                    continue;
+                }
+                inspection.protocols.insert(Protocol::Paraswap);
+
+                if let Some(swap) = self.try_as_swap(call) {
+                    *action = Classification::new(swap, calltrace.trace_address.clone());
+                }
+            }
+        }
+    }
+}
+
+impl Paraswap {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            augustus: BaseContract::from({
+                serde_json::from_str::<Abi>(include_str!("../../abi/paraswap.json"))
+                    .expect("could not parse paraswap abi")
+            }),
+        }
+    }
+
+    fn try_as_swap(&self, call: &TraceCall) -> Option<AggregatorSwap> {
+        let (token_in, amount_in, token_out, min_amount_out, beneficiary) = if let Ok(data) = self
+            .augustus
+            .decode::<SellData, _>("multiSwap", &call.input)
+        {
+            let token_out = last_path_token(data.7)?;
+            (data.0, data.1, token_out, data.2, data.4)
+        } else if let Ok(data) = self.augustus.decode::<SellData, _>("megaSwap", &call.input) {
+            // each mega path is a (fromAmountPercent, path) pair, all of which
+            // end in the same token
+            let path = match data.7 {
+                Token::Array(paths) => match paths.into_iter().next()? {
+                    Token::Tuple(mut inner) if inner.len() == 2 => inner.pop()?,
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            let token_out = last_path_token(path)?;
+            (data.0, data.1, token_out, data.2, data.4)
+        } else if let Ok((amount_in, min_amount_out, path, _)) = self
+            .augustus
+            .decode::<SwapOnUniswap, _>("swapOnUniswap", &call.input)
+        {
+            (
+                *path.first()?,
+                amount_in,
+                *path.last()?,
+                min_amount_out,
+                Address::zero(),
+            )
+        } else if let Ok((_, _, amount_in, min_amount_out, path, _)) =
+            self.augustus
+                .decode::<SwapOnUniswapFork, _>("swapOnUniswapFork", &call.input)
+        {
+            (
+                *path.first()?,
+                amount_in,
+                *path.last()?,
+                min_amount_out,
+                Address::zero(),
+            )
+        } else {
+            return None;
+        };
+
+        Some(AggregatorSwap {
+            token_in,
+            amount_in,
+            token_out,
+            min_amount_out,
+            from: call.from,
+            // Augustus pays out to the caller if no beneficiary is set
+            recipient: if beneficiary.is_zero() {
+                call.from
+            } else {
+                beneficiary
+            },
+        })
+    }
+}
+
+/// Given a `Path[]` token, it returns the `to` token of its last hop
+fn last_path_token(path: Token) -> Option<Address> {
+    match path {
+        Token::Array(hops) => match hops.into_iter().last()? {
+            Token::Tuple(hop) => hop.into_iter().next()?.into_address(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{addresses::parse_address, test_helpers::*};
+    use ethers::types::Bytes;
+
+    // (exchange, targetExchange, percent, payload, networkFee)
+    type Route = (Address, Address, U256, Bytes, U256);
+    // (to, totalNetworkFee, routes)
+    type Path = (Address, U256, Vec<Route>);
+
+    fn augustus() -> Address {
+        parse_address("0x1bd435f3c054b6e901b7b108a0ab7617c808677b")
+    }
+
+    #[test]
+    fn multi_swap() {
+        let addrs = addrs();
+        let (usr, beneficiary, exchange, token_in, token_mid, token_out) =
+            (addrs[0], addrs[1], addrs[2], addrs[3], addrs[4], addrs[5]);
+        let paraswap = Paraswap::new();
+
+        let route: Route = (exchange, exchange, 10000.into(), Bytes::default(), 0.into());
+        let path: Vec<Path> = vec![
+            (token_mid, 0.into(), vec![route.clone()]),
+            (token_out, 0.into(), vec![route]),
+        ];
+        let data = (
+            token_in,
+            U256::from(100),
+            U256::from(90),
+            U256::from(95),
+            beneficiary,
+            "paraswap".to_owned(),
+            false,
+            path,
+        );
+        // wrapped so that the struct does not get flattened into separate arguments
+        let input = paraswap.augustus.encode("multiSwap", (data,)).unwrap();
+        let mut inspection = mk_inspection(vec![mk_call(usr, augustus(), 0, input, vec![])]);
+        paraswap.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::Paraswap]);
+        let known = inspection.known();
+        let swap = known[0].as_ref().aggregator_swap().unwrap();
+        assert_eq!(
+            *swap,
+            AggregatorSwap {
+                token_in,
+                amount_in: 100.into(),
+                token_out,
+                min_amount_out: 90.into(),
+                from: usr,
+                recipient: beneficiary,
+            }
+        );
+    }
+
+    #[test]
+    fn swap_on_uniswap() {
+        let addrs = addrs();
+        let (usr, token_in, token_out) = (addrs[0], addrs[1], addrs[2]);
+        let paraswap = Paraswap::new();
+
+        let input = paraswap
+            .augustus
+            .encode(
+                "swapOnUniswap",
+                (
+                    U256::from(100),
+                    U256::from(90),
+                    vec![token_in, token_out],
+                    0u8,
+                ),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![mk_call(usr, augustus(), 0, input, vec![])]);
+        paraswap.inspect(&mut inspection);
+
+        let known = inspection.known();
+        let swap = known[0].as_ref().aggregator_swap().unwrap();
+        assert_eq!((swap.token_in, swap.token_out), (token_in, token_out));
+        assert_eq!(swap.recipient, usr);
+    }
+
+    #[test]
+    fn other_contracts_ignored() {
+        let addrs = addrs();
+        let paraswap = Paraswap::new();
+
+        let input = paraswap
+            .augustus
+            .encode(
+                "swapOnUniswap",
+                (
+                    U256::from(100),
+                    U256::from(90),
+                    vec![addrs[1], addrs[2]],
+                    0u8,
+                ),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![mk_call(addrs[0], addrs[3], 0, input, vec![])]);
+        paraswap.inspect(&mut inspection);
+
+        assert!(inspection.known().is_empty());
+        assert!(inspection.protocols.is_empty());
+    }
+}
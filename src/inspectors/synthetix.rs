@@ -0,0 +1,196 @@
+use crate::{
+    addresses::{SYNTHETIX, SYNTHS},
+    inspectors::prune_subtraces,
+    is_subtrace,
+    traits::Inspector,
+    types::{
+        actions::{Trade, Transfer},
+        Classification, Inspection, Protocol,
+    },
+};
+
+use ethers::{
+    abi::parse_abi,
+    contract::BaseContract,
+    types::{Address, U256},
+};
+
+// Type aliases for Synthetix's exchange functions
+type Exchange = ([u8; 32], U256, [u8; 32]);
+type ExchangeWithTracking = ([u8; 32], U256, [u8; 32], Address, [u8; 32]);
+
+#[derive(Debug, Clone)]
+/// An inspector for Synth exchanges via the Synthetix proxy
+pub struct Synthetix {
+    synthetix: BaseContract,
+    synth: BaseContract,
+}
+
+impl Inspector for Synthetix {
+    fn inspect(&self, inspection: &mut Inspection) {
+        let actions = inspection.actions.to_vec();
+        let mut prune = Vec::new();
+        for i in 0..inspection.actions.len() {
+            let action = &mut inspection.actions[i];
+
+            if let Some(calltrace) = action.as_call() {
+                let call = calltrace.as_ref();
+                if call.to != *SYNTHETIX {
+                    continue;
+                }
+
+                let (src_key, src_amount, dest_key) = if let Ok(exchange) = self
+                    .synthetix
+                    .decode::<Exchange, _>("exchange", &call.input)
+                {
+                    exchange
+                } else if let Ok((src_key, src_amount, dest_key, ..)) =
+                    self.synthetix
+                        .decode::<ExchangeWithTracking, _>("exchangeWithTracking", &call.input)
+                {
+                    (src_key, src_amount, dest_key)
+                } else {
+                    // the proxy is also the SNX token
+                    continue;
+                };
+                inspection.protocols.insert(Protocol::Synthetix);
+
+                let (src, dest) = match (synth(src_key), synth(dest_key)) {
+                    (Some(src), Some(dest)) => (src, dest),
+                    _ => continue,
+                };
+
+                // the destination synth is issued straight to the caller,
+                // after the exchange fee is taken
+                let trace_address = &calltrace.trace_address;
+                let received = actions[i + 1..]
+                    .iter()
+                    .filter(|t| {
+                        trace_address.is_empty() || is_subtrace(trace_address, &t.trace_address())
+                    })
+                    .filter_map(|t| {
+                        let issue = t.as_call()?.as_ref();
+                        self.synth
+                            .decode::<(Address, U256), _>("issue", &issue.input)
+                            .ok()
+                    })
+                    .filter(|(account, _)| *account == call.from)
+                    .last();
+                let received = match received {
+                    Some((_, amount)) => amount,
+                    None => continue,
+                };
+
+                *action = Classification::new(
+                    Trade {
+                        t1: Transfer {
+                            from: call.from,
+                            to: call.to,
+                            amount: src_amount,
+                            token: src,
+                        },
+                        t2: Transfer {
+                            from: call.to,
+                            to: call.from,
+                            amount: received,
+                            token: dest,
+                        },
+                    },
+                    calltrace.trace_address.clone(),
+                );
+                prune.push(i);
+            }
+        }
+
+        // the burns and issues are accounted for in the trade
+        prune
+            .into_iter()
+            .for_each(|idx| prune_subtraces(&mut inspection.actions, idx));
+    }
+}
+
+impl Synthetix {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            synthetix: BaseContract::from(
+                parse_abi(&[
+                    "function exchange(bytes32 sourceCurrencyKey, uint256 sourceAmount, bytes32 destinationCurrencyKey) returns (uint256 amountReceived)",
+                    "function exchangeWithTracking(bytes32 sourceCurrencyKey, uint256 sourceAmount, bytes32 destinationCurrencyKey, address originator, bytes32 trackingCode) returns (uint256 amountReceived)",
+                ])
+                .expect("could not parse synthetix abi"),
+            ),
+            synth: BaseContract::from(
+                parse_abi(&["function issue(address account, uint256 amount)"])
+                    .expect("could not parse synth abi"),
+            ),
+        }
+    }
+}
+
+/// Returns the token of a Synth given its currency key
+fn synth(key: [u8; 32]) -> Option<Address> {
+    let end = key.iter().position(|b| *b == 0).unwrap_or(key.len());
+    let key = String::from_utf8_lossy(&key[..end]);
+    SYNTHS.get(key.as_ref()).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn key(name: &str) -> [u8; 32] {
+        let mut key = [0; 32];
+        key[..name.len()].copy_from_slice(name.as_bytes());
+        key
+    }
+
+    #[test]
+    fn susd_to_seth() {
+        let addrs = addrs();
+        let (usr, implementation, seth_impl) = (addrs[0], addrs[1], addrs[2]);
+        let inspector = Synthetix::new();
+
+        let input = inspector
+            .synthetix
+            .encode("exchange", (key("sUSD"), U256::from(1000), key("sETH")))
+            .unwrap();
+        let issue = inspector
+            .synth
+            .encode("issue", (usr, U256::from(3)))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(usr, *SYNTHETIX, 0, input.clone(), vec![]),
+            // the proxy forwards the call to the implementation
+            mk_call(*SYNTHETIX, implementation, 0, input, vec![0]),
+            mk_call(implementation, seth_impl, 0, issue, vec![0, 0]),
+        ]);
+        inspector.inspect(&mut inspection);
+        inspection.prune();
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::Synthetix]);
+        let known = inspection.known();
+        assert_eq!(known.len(), 1);
+        let trade = known[0].as_ref().trade().unwrap();
+        assert_eq!(trade.t1.token, SYNTHS["sUSD"]);
+        assert_eq!(trade.t1.amount, 1000.into());
+        assert_eq!(trade.t2.token, SYNTHS["sETH"]);
+        assert_eq!(trade.t2.amount, 3.into());
+        assert_eq!(trade.t2.to, usr);
+    }
+
+    #[test]
+    fn snx_transfers_ignored() {
+        let addrs = addrs();
+        let inspector = Synthetix::new();
+
+        let input = erc20()
+            .encode("transfer", (addrs[1], U256::from(1)))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![mk_call(addrs[0], *SYNTHETIX, 0, input, vec![])]);
+        inspector.inspect(&mut inspection);
+
+        assert!(inspection.protocols.is_empty());
+    }
+}
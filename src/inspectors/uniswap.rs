@@ -1,6 +1,8 @@
 use crate::{
-    addresses::{AAVE_LENDING_POOL_CORE, PROTOCOLS},
+    addresses::AAVE_LENDING_POOL_CORE,
     inspectors::find_matching,
+    pairs::{PairFactory, PairRegistry},
+    registry::registry,
     traits::Inspector,
     types::{
         actions::{AddLiquidity as AddLiquidityAct, Trade},
@@ -8,11 +10,15 @@ use crate::{
     },
 };
 
-use ethers::{abi::Abi, contract::BaseContract};
+use ethers::{
+    abi::{parse_abi, Abi},
+    contract::BaseContract,
+};
 use ethers::{
     contract::decode_function_data,
     types::{Address, Bytes, Call as TraceCall, CallType, U256},
 };
+use std::sync::Arc;
 
 // Type aliases for Uniswap's `swap` return types
 type SwapTokensFor = (U256, U256, Vec<Address>, Address, U256);
@@ -25,6 +31,9 @@ type AddLiquidity = (Address, Address, U256, U256, U256, U256, Address, U256);
 pub struct Uniswap {
     router: BaseContract,
     pair: BaseContract,
+    factory: BaseContract,
+    /// Pairs which are not in the address book are looked up here
+    pairs: Arc<PairRegistry>,
 }
 
 impl Inspector for Uniswap {
@@ -59,11 +68,11 @@ impl Inspector for Uniswap {
                     self.pair.decode::<PairSwap, _>("swap", &call.input)
                 {
                     // add the protocol
-                    let protocol = uniswappy(&call);
-                    inspection.protocols.insert(protocol);
+                    let mut protocol = self.protocol(call);
 
                     // skip flashswaps -- TODO: Get an example tx.
                     if !bytes.as_ref().is_empty() {
+                        inspection.protocols.insert(protocol);
                         eprintln!("Flashswaps are not supported. {:?}", inspection.hash);
                         continue;
                     }
@@ -93,6 +102,18 @@ impl Inspector for Uniswap {
                         );
 
                         if let Some((idx_out, transfer_out)) = res {
+                            // the pair's tokens tell us if it was deployed by
+                            // any of the known factories
+                            if protocol == Protocol::Uniswappy {
+                                if let Some(discovered) = self.pairs.discover(
+                                    call.to,
+                                    transfer_in.token,
+                                    transfer_out.token,
+                                ) {
+                                    protocol = discovered;
+                                }
+                            }
+
                             // change the action to a trade
                             *action = Classification::new(
                                 Trade {
@@ -109,11 +130,22 @@ impl Inspector for Uniswap {
                             prune.push(idx_out);
                         }
                     }
+                    inspection.protocols.insert(protocol);
                 } else if (call.call_type == CallType::StaticCall && preflight) || self.check(call)
                 {
-                    let protocol = uniswappy(&call);
+                    let protocol = self.protocol(call);
                     inspection.protocols.insert(protocol);
                     *action = Classification::Prune;
+                } else if let Some(factory) = self.pairs.factory(&call.to) {
+                    // keep track of the pairs as they get created
+                    if let Ok((token_a, token_b)) = self
+                        .factory
+                        .decode::<(Address, Address), _>("createPair", &call.input)
+                    {
+                        if let Some(pair) = factory.pair_for(token_a, token_b) {
+                            self.pairs.register(pair, factory.protocol);
+                        }
+                    }
                 }
             }
         }
@@ -139,16 +171,6 @@ impl Inspector for Uniswap {
     }
 }
 
-fn uniswappy(call: &TraceCall) -> Protocol {
-    if let Some(protocol) = PROTOCOLS.get(&call.to) {
-        *protocol
-    } else if let Some(protocol) = PROTOCOLS.get(&call.from) {
-        *protocol
-    } else {
-        Protocol::Uniswappy
-    }
-}
-
 impl Uniswap {
     /// Constructor
     pub fn new() -> Self {
@@ -161,6 +183,31 @@ impl Uniswap {
                 serde_json::from_str::<Abi>(include_str!("../../abi/unipair.json"))
                     .expect("could not parse uniswap abi")
             }),
+            factory: BaseContract::from(
+                parse_abi(&[
+                    "function createPair(address tokenA, address tokenB) returns (address pair)",
+                ])
+                .expect("could not parse uniswap factory abi"),
+            ),
+            pairs: Arc::new(PairRegistry::new(PairFactory::defaults())),
+        }
+    }
+
+    /// Sets the registry which pairs get discovered into
+    pub fn with_pairs(mut self, pairs: Arc<PairRegistry>) -> Self {
+        self.pairs = pairs;
+        self
+    }
+
+    fn protocol(&self, call: &TraceCall) -> Protocol {
+        if let Some(protocol) = registry().protocol(&call.to) {
+            protocol
+        } else if let Some(protocol) = self.pairs.get(&call.to) {
+            protocol
+        } else if let Some(protocol) = registry().protocol(&call.from) {
+            protocol
+        } else {
+            Protocol::Uniswappy
         }
     }
 
@@ -395,6 +442,48 @@ pub mod tests {
         let _t3 = known[3].as_ref().transfer().unwrap();
     }
 
+    #[test]
+    // a pair which was created after the address book was generated
+    fn discovers_new_pair() {
+        let addrs = addrs();
+        let (bot, token_a, token_b) = (addrs[0], addrs[1], addrs[2]);
+        let pair = PairFactory::defaults()[0]
+            .pair_for(token_a, token_b)
+            .unwrap();
+        let uni = MyInspector::new();
+
+        let swap = uni
+            .uni
+            .pair
+            .encode(
+                "swap",
+                (U256::zero(), U256::from(50), bot, Bytes::default()),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(
+                bot,
+                token_a,
+                0,
+                erc20().encode("transfer", (pair, U256::from(100))).unwrap(),
+                vec![0],
+            ),
+            mk_call(bot, pair, 0, swap, vec![1]),
+            mk_call(
+                pair,
+                token_b,
+                0,
+                erc20().encode("transfer", (bot, U256::from(50))).unwrap(),
+                vec![1, 0],
+            ),
+        ]);
+        uni.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::Uniswap]);
+        assert!(inspection.known()[0].as_ref().trade().is_some());
+        assert_eq!(uni.uni.pairs.get(&pair), Some(Protocol::Uniswap));
+    }
+
     mod simple_transfers {
         use super::*;
 
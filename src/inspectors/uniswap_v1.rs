@@ -0,0 +1,462 @@
+use crate::{
+    addresses::ETH,
+    inspectors::{prune_subtraces, ERC20},
+    is_subtrace,
+    registry::registry,
+    traits::Inspector,
+    types::{
+        actions::{SpecificAction, Trade, Transfer},
+        Classification, Inspection, Protocol,
+    },
+};
+
+use ethers::{
+    abi::parse_abi,
+    contract::BaseContract,
+    types::{Address, Call as TraceCall, U256},
+};
+
+// Type aliases for the V1 exchanges' token to token swaps
+type TokenToTokenSwap = (U256, U256, U256, U256, Address);
+type TokenToTokenTransfer = (U256, U256, U256, U256, Address, Address);
+
+#[derive(Debug, Clone)]
+/// An inspector for swaps on Uniswap V1 exchanges. Unlike V2, the ETH side of
+/// a V1 swap is paid as call value instead of as a WETH transfer.
+pub struct UniswapV1 {
+    exchange: BaseContract,
+    erc20: ERC20,
+}
+
+/// The sides of a V1 swap as given in its calldata
+enum Swap {
+    /// ETH is sold for the exchange's token
+    EthToToken { recipient: Address },
+    /// The exchange's token is sold for ETH
+    TokenToEth { recipient: Address },
+    /// The exchange's token is sold for ETH, which is then sold for `token`
+    /// on that token's exchange
+    TokenToToken { recipient: Address, token: Address },
+}
+
+impl Inspector for UniswapV1 {
+    fn inspect(&self, inspection: &mut Inspection) {
+        let actions = inspection.actions.to_vec();
+        let mut prune = Vec::new();
+        for i in 0..inspection.actions.len() {
+            let action = &mut inspection.actions[i];
+
+            if let Some(calltrace) = action.as_call() {
+                let call = calltrace.as_ref();
+                if registry().protocol(&call.to) != Some(Protocol::UniswapV1) {
+                    continue;
+                }
+                let swap = match self.try_as_swap(call) {
+                    Some(swap) => swap,
+                    None => continue,
+                };
+                inspection.protocols.insert(Protocol::UniswapV1);
+
+                let transfers = self.transfers(&actions, i);
+                let trade = match swap {
+                    Swap::EthToToken { recipient } => {
+                        // `*Output` swaps refund any ETH that was not needed
+                        let refund = transfers
+                            .iter()
+                            .filter(|t| t.token == *ETH && t.from == call.to && t.to == call.from)
+                            .fold(U256::zero(), |acc, t| acc + t.amount);
+                        let bought = transfers
+                            .iter()
+                            .rev()
+                            .find(|t| t.token != *ETH && t.from == call.to && t.to == recipient);
+                        bought.map(|bought| Trade {
+                            t1: Transfer {
+                                from: call.from,
+                                to: call.to,
+                                amount: call.value.saturating_sub(refund),
+                                token: *ETH,
+                            },
+                            t2: bought.clone(),
+                        })
+                    }
+                    Swap::TokenToEth { recipient } => {
+                        let sold = sold(&transfers, call);
+                        let bought = transfers
+                            .iter()
+                            .rev()
+                            .find(|t| t.token == *ETH && t.from == call.to && t.to == recipient);
+                        sold.zip(bought).map(|(sold, bought)| Trade {
+                            t1: sold.clone(),
+                            t2: bought.clone(),
+                        })
+                    }
+                    Swap::TokenToToken { recipient, token } => {
+                        let sold = sold(&transfers, call);
+                        // the other exchange pays out directly to the recipient
+                        let bought = transfers
+                            .iter()
+                            .rev()
+                            .find(|t| t.token == token && t.to == recipient);
+                        sold.zip(bought).map(|(sold, bought)| Trade {
+                            t1: sold.clone(),
+                            t2: Transfer {
+                                from: call.to,
+                                to: recipient,
+                                amount: bought.amount,
+                                token,
+                            },
+                        })
+                    }
+                };
+
+                if let Some(trade) = trade {
+                    *action = Classification::new(trade, calltrace.trace_address.clone());
+                    prune.push(i);
+                }
+            }
+        }
+
+        // the exchanges' transfers are accounted for in the trade
+        prune
+            .into_iter()
+            .for_each(|idx| prune_subtraces(&mut inspection.actions, idx));
+    }
+}
+
+/// Returns the transfer of the exchange's token from the caller to the exchange
+fn sold<'a>(transfers: &'a [Transfer], call: &TraceCall) -> Option<&'a Transfer> {
+    transfers
+        .iter()
+        .find(|t| t.token != *ETH && t.from == call.from && t.to == call.to)
+}
+
+impl UniswapV1 {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            exchange: BaseContract::from(
+                parse_abi(&[
+                    "function ethToTokenSwapInput(uint256 min_tokens, uint256 deadline) payable returns (uint256)",
+                    "function ethToTokenTransferInput(uint256 min_tokens, uint256 deadline, address recipient) payable returns (uint256)",
+                    "function ethToTokenSwapOutput(uint256 tokens_bought, uint256 deadline) payable returns (uint256)",
+                    "function ethToTokenTransferOutput(uint256 tokens_bought, uint256 deadline, address recipient) payable returns (uint256)",
+                    "function tokenToEthSwapInput(uint256 tokens_sold, uint256 min_eth, uint256 deadline) returns (uint256)",
+                    "function tokenToEthTransferInput(uint256 tokens_sold, uint256 min_eth, uint256 deadline, address recipient) returns (uint256)",
+                    "function tokenToEthSwapOutput(uint256 eth_bought, uint256 max_tokens, uint256 deadline) returns (uint256)",
+                    "function tokenToEthTransferOutput(uint256 eth_bought, uint256 max_tokens, uint256 deadline, address recipient) returns (uint256)",
+                    "function tokenToTokenSwapInput(uint256 tokens_sold, uint256 min_tokens_bought, uint256 min_eth_bought, uint256 deadline, address token_addr) returns (uint256)",
+                    "function tokenToTokenTransferInput(uint256 tokens_sold, uint256 min_tokens_bought, uint256 min_eth_bought, uint256 deadline, address recipient, address token_addr) returns (uint256)",
+                    "function tokenToTokenSwapOutput(uint256 tokens_bought, uint256 max_tokens_sold, uint256 max_eth_sold, uint256 deadline, address token_addr) returns (uint256)",
+                    "function tokenToTokenTransferOutput(uint256 tokens_bought, uint256 max_tokens_sold, uint256 max_eth_sold, uint256 deadline, address recipient, address token_addr) returns (uint256)",
+                ])
+                .expect("could not parse uniswap v1 abi"),
+            ),
+            erc20: ERC20::new(),
+        }
+    }
+
+    fn try_as_swap(&self, call: &TraceCall) -> Option<Swap> {
+        let input = &call.input;
+        let decode2 = |name: &str| self.exchange.decode::<(U256, U256), _>(name, input);
+        let decode3 = |name: &str| self.exchange.decode::<(U256, U256, U256), _>(name, input);
+        let decode4 = |name: &str| {
+            self.exchange
+                .decode::<(U256, U256, U256, Address), _>(name, input)
+        };
+
+        let swap = if decode2("ethToTokenSwapInput")
+            .or_else(|_| decode2("ethToTokenSwapOutput"))
+            .is_ok()
+        {
+            Swap::EthToToken {
+                recipient: call.from,
+            }
+        } else if let Ok((_, _, recipient)) = self
+            .exchange
+            .decode::<(U256, U256, Address), _>("ethToTokenTransferInput", input)
+            .or_else(|_| {
+                self.exchange
+                    .decode::<(U256, U256, Address), _>("ethToTokenTransferOutput", input)
+            })
+        {
+            Swap::EthToToken { recipient }
+        } else if decode3("tokenToEthSwapInput")
+            .or_else(|_| decode3("tokenToEthSwapOutput"))
+            .is_ok()
+        {
+            Swap::TokenToEth {
+                recipient: call.from,
+            }
+        } else if let Ok((.., recipient)) =
+            decode4("tokenToEthTransferInput").or_else(|_| decode4("tokenToEthTransferOutput"))
+        {
+            Swap::TokenToEth { recipient }
+        } else if let Ok((.., token)) = self
+            .exchange
+            .decode::<TokenToTokenSwap, _>("tokenToTokenSwapInput", input)
+            .or_else(|_| {
+                self.exchange
+                    .decode::<TokenToTokenSwap, _>("tokenToTokenSwapOutput", input)
+            })
+        {
+            Swap::TokenToToken {
+                recipient: call.from,
+                token,
+            }
+        } else if let Ok((.., recipient, token)) = self
+            .exchange
+            .decode::<TokenToTokenTransfer, _>("tokenToTokenTransferInput", input)
+            .or_else(|_| {
+                self.exchange
+                    .decode::<TokenToTokenTransfer, _>("tokenToTokenTransferOutput", input)
+            })
+        {
+            Swap::TokenToToken { recipient, token }
+        } else {
+            return None;
+        };
+        Some(swap)
+    }
+
+    /// Returns the token and ETH transfers made in the subtraces of the call at `i`
+    fn transfers(&self, actions: &[Classification], i: usize) -> Vec<Transfer> {
+        let trace_address = actions[i].trace_address();
+        actions[i + 1..]
+            .iter()
+            .filter(|t| trace_address.is_empty() || is_subtrace(&trace_address, &t.trace_address()))
+            .filter_map(|t| match t {
+                Classification::Known(_) => t.as_action().and_then(|t| t.transfer()).cloned(),
+                Classification::Unknown(calltrace) => {
+                    let call = calltrace.as_ref();
+                    // V1 pays out ETH with `send`, which the ERC20 inspector skips
+                    // due to its 2300 gas stipend
+                    if !call.value.is_zero() && call.input.as_ref().is_empty() {
+                        Some(Transfer {
+                            from: call.from,
+                            to: call.to,
+                            amount: call.value,
+                            token: *ETH,
+                        })
+                    } else {
+                        match self.erc20.try_parse(call) {
+                            Some(SpecificAction::Transfer(transfer)) => Some(transfer),
+                            _ => None,
+                        }
+                    }
+                }
+                Classification::Prune => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addresses::{ADDRESSBOOK, PROTOCOLS},
+        reducers::{ArbitrageReducer, TradeReducer},
+        test_helpers::*,
+        Reducer,
+    };
+    use ethers::types::Bytes;
+
+    struct MyInspector {
+        uniswap: UniswapV1,
+        erc20: ERC20,
+        trade: TradeReducer,
+        arbitrage: ArbitrageReducer,
+    }
+
+    impl MyInspector {
+        fn inspect(&self, inspection: &mut Inspection) {
+            self.uniswap.inspect(inspection);
+            self.erc20.inspect(inspection);
+            self.trade.reduce(inspection);
+            self.arbitrage.reduce(inspection);
+            inspection.prune();
+        }
+
+        fn new() -> Self {
+            Self {
+                uniswap: UniswapV1::new(),
+                erc20: ERC20::new(),
+                trade: TradeReducer::new(),
+                arbitrage: ArbitrageReducer::new(),
+            }
+        }
+    }
+
+    /// Returns 2 V1 exchanges
+    fn exchanges() -> (Address, Address) {
+        let mut exchanges = PROTOCOLS
+            .iter()
+            .filter(|(_, protocol)| **protocol == Protocol::UniswapV1)
+            .map(|(addr, _)| *addr)
+            .filter(|addr| !ADDRESSBOOK.contains_key(addr));
+        (exchanges.next().unwrap(), exchanges.next().unwrap())
+    }
+
+    fn eth_payout(
+        from: Address,
+        to: Address,
+        value: u64,
+        trace_address: Vec<usize>,
+    ) -> Classification {
+        let mut call = mk_call(from, to, value, Bytes::default(), trace_address);
+        if let Classification::Unknown(ref mut calltrace) = call {
+            calltrace.call.gas = 2300.into();
+        }
+        call
+    }
+
+    #[test]
+    fn eth_to_token_with_refund() {
+        let addrs = addrs();
+        let (usr, token) = (addrs[0], addrs[1]);
+        let (exchange, _) = exchanges();
+        let inspector = MyInspector::new();
+
+        let input = inspector
+            .uniswap
+            .exchange
+            .encode("ethToTokenSwapOutput", (U256::from(50), U256::zero()))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(usr, exchange, 100, input, vec![]),
+            mk_call(
+                exchange,
+                token,
+                0,
+                erc20().encode("transfer", (usr, U256::from(50))).unwrap(),
+                vec![0],
+            ),
+            eth_payout(exchange, usr, 10, vec![1]),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::UniswapV1]);
+        let known = inspection.known();
+        assert_eq!(known.len(), 1);
+        let trade = known[0].as_ref().trade().unwrap();
+        assert_eq!((trade.t1.token, trade.t1.amount), (*ETH, 90.into()));
+        assert_eq!((trade.t2.token, trade.t2.amount), (token, 50.into()));
+        assert_eq!(trade.t2.to, usr);
+    }
+
+    #[test]
+    fn token_to_token() {
+        let addrs = addrs();
+        let (usr, recipient, token_a, token_b) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let (exchange_a, exchange_b) = exchanges();
+        let inspector = MyInspector::new();
+
+        let input = inspector
+            .uniswap
+            .exchange
+            .encode(
+                "tokenToTokenTransferInput",
+                (
+                    U256::from(100),
+                    U256::from(1),
+                    U256::from(1),
+                    U256::zero(),
+                    recipient,
+                    token_b,
+                ),
+            )
+            .unwrap();
+        let inner = inspector
+            .uniswap
+            .exchange
+            .encode(
+                "ethToTokenTransferInput",
+                (U256::from(1), U256::zero(), recipient),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(usr, exchange_a, 0, input, vec![]),
+            mk_call(
+                exchange_a,
+                token_a,
+                0,
+                erc20()
+                    .encode("transferFrom", (usr, exchange_a, U256::from(100)))
+                    .unwrap(),
+                vec![0],
+            ),
+            // the ETH is sold on the other token's exchange
+            mk_call(exchange_a, exchange_b, 7, inner, vec![1]),
+            mk_call(
+                exchange_b,
+                token_b,
+                0,
+                erc20()
+                    .encode("transfer", (recipient, U256::from(30)))
+                    .unwrap(),
+                vec![1, 0],
+            ),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        let known = inspection.known();
+        assert_eq!(known.len(), 1);
+        let trade = known[0].as_ref().trade().unwrap();
+        assert_eq!((trade.t1.token, trade.t1.amount), (token_a, 100.into()));
+        assert_eq!((trade.t2.token, trade.t2.amount), (token_b, 30.into()));
+        assert_eq!(trade.t2.to, recipient);
+    }
+
+    #[test]
+    fn arb_between_exchanges() {
+        let addrs = addrs();
+        let (bot, token) = (addrs[0], addrs[1]);
+        let (exchange_a, exchange_b) = exchanges();
+        let inspector = MyInspector::new();
+
+        let buy = inspector
+            .uniswap
+            .exchange
+            .encode("ethToTokenSwapInput", (U256::from(1), U256::zero()))
+            .unwrap();
+        let sell = inspector
+            .uniswap
+            .exchange
+            .encode(
+                "tokenToEthSwapInput",
+                (U256::from(50), U256::from(1), U256::zero()),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(bot, exchange_a, 100, buy, vec![0]),
+            mk_call(
+                exchange_a,
+                token,
+                0,
+                erc20().encode("transfer", (bot, U256::from(50))).unwrap(),
+                vec![0, 0],
+            ),
+            mk_call(bot, exchange_b, 0, sell, vec![1]),
+            mk_call(
+                exchange_b,
+                token,
+                0,
+                erc20()
+                    .encode("transferFrom", (bot, exchange_b, U256::from(50)))
+                    .unwrap(),
+                vec![1, 0],
+            ),
+            eth_payout(exchange_b, bot, 110, vec![1, 1]),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        let known = inspection.known();
+        let arb = known
+            .iter()
+            .find_map(|x| x.as_ref().arbitrage())
+            .cloned()
+            .unwrap();
+        assert_eq!(arb.profit, 10.into());
+        assert_eq!(arb.token, *ETH);
+    }
+}
@@ -1,23 +1,87 @@
 use crate::{
-    addresses::PROTOCOLS,
+    addresses::{ETH, ZEROX, ZEROX_EXCHANGE_PROXY, ZEROX_STAKING},
+    inspectors::{last_transfer_to, prune_subtraces, subtrace_transfers, ERC20},
+    is_subtrace,
+    registry::registry,
     traits::Inspector,
-    types::{actions::Transfer, Classification, Inspection, Protocol},
+    types::{
+        actions::{Trade, Transfer},
+        Classification, Inspection, Protocol,
+    },
 };
 
 use ethers::{
-    abi::parse_abi,
+    abi::{parse_abi, Abi, Token},
     contract::BaseContract,
-    types::{Address, Bytes, U256},
+    types::{Address, Bytes, Call as TraceCall, U256},
 };
 
 #[derive(Debug, Clone)]
-/// An inspector for ZeroEx Exchange Proxy transfers
+/// An inspector for ZeroEx Exchange Proxy transfers and native order fills
 pub struct ZeroEx {
     bridge: BaseContract,
+    exchange_proxy: BaseContract,
+    exchange_v3: BaseContract,
+    erc20: ERC20,
 }
 
 type BridgeTransfer = (Address, Address, Address, U256, Bytes);
 
+// (makerToken, takerToken, makerAmount, takerAmount, takerTokenFeeAmount, maker, ..)
+type LimitOrder = (
+    Address,
+    Address,
+    U256,
+    U256,
+    U256,
+    Address,
+    Address,
+    Address,
+    Address,
+    [u8; 32],
+    U256,
+    U256,
+);
+// (makerToken, takerToken, makerAmount, takerAmount, maker, ..)
+type RfqOrder = (
+    Address,
+    Address,
+    U256,
+    U256,
+    Address,
+    Address,
+    Address,
+    [u8; 32],
+    U256,
+    U256,
+);
+type Signature = (u8, u8, [u8; 32], [u8; 32]);
+type TransformERC20 = (Address, Address, U256, U256, Token);
+// (makerAddress, .., makerAssetData, takerAssetData, ..)
+type OrderV3 = (
+    Address,
+    Address,
+    Address,
+    Address,
+    U256,
+    U256,
+    U256,
+    U256,
+    U256,
+    U256,
+    Bytes,
+    Bytes,
+    Bytes,
+    Bytes,
+);
+
+/// A native order fill between a maker and a taker
+struct Fill {
+    maker: Address,
+    maker_token: Address,
+    taker_token: Address,
+}
+
 impl ZeroEx {
     pub fn new() -> Self {
         let bridge = BaseContract::from(
@@ -25,14 +89,172 @@ impl ZeroEx {
     "function bridgeTransferFrom(address tokenAddress, address from, address to, uint256 amount, bytes calldata bridgeData)"
             ]).expect("could not parse bridge abi"));
 
-        Self { bridge }
+        let exchange_proxy = BaseContract::from(
+            serde_json::from_str::<Abi>(include_str!("../../abi/zeroexproxy.json"))
+                .expect("could not parse 0x exchange proxy abi"),
+        );
+        let exchange_v3 = BaseContract::from(
+            serde_json::from_str::<Abi>(include_str!("../../abi/zeroexv3.json"))
+                .expect("could not parse 0x v3 exchange abi"),
+        );
+
+        Self {
+            bridge,
+            exchange_proxy,
+            exchange_v3,
+            erc20: ERC20::new(),
+        }
+    }
+
+    fn try_as_fill(&self, call: &TraceCall) -> Option<Fill> {
+        if call.to == *ZEROX_EXCHANGE_PROXY {
+            if let Ok((order, _, _)) = self
+                .exchange_proxy
+                .decode::<(LimitOrder, Signature, U256), _>("fillLimitOrder", &call.input)
+            {
+                return Some(Fill {
+                    maker: order.5,
+                    maker_token: order.0,
+                    taker_token: order.1,
+                });
+            } else if let Ok((order, _, _)) = self
+                .exchange_proxy
+                .decode::<(RfqOrder, Signature, U256), _>("fillRfqOrder", &call.input)
+            {
+                return Some(Fill {
+                    maker: order.4,
+                    maker_token: order.0,
+                    taker_token: order.1,
+                });
+            }
+        } else if call.to == *ZEROX {
+            if let Ok((order, _, _)) = self
+                .exchange_v3
+                .decode::<(OrderV3, U256, Bytes), _>("fillOrder", &call.input)
+            {
+                return Some(Fill {
+                    maker: order.0,
+                    maker_token: erc20_asset(order.10.as_ref())?,
+                    taker_token: erc20_asset(order.11.as_ref())?,
+                });
+            }
+        }
+        None
+    }
+
+    /// Returns the trade made by a fill
+    fn fill_trade(
+        &self,
+        actions: &[Classification],
+        i: usize,
+        call: &TraceCall,
+        fill: Fill,
+    ) -> Option<Trade> {
+        let taker = call.from;
+        let paid = last_transfer_to(&self.erc20, actions, i, fill.taker_token, fill.maker)?;
+        let received = last_transfer_to(&self.erc20, actions, i, fill.maker_token, taker)?;
+
+        Some(Trade {
+            t1: Transfer {
+                from: taker,
+                to: fill.maker,
+                amount: paid.amount,
+                token: fill.taker_token,
+            },
+            t2: Transfer {
+                from: fill.maker,
+                to: taker,
+                amount: received.amount,
+                token: fill.maker_token,
+            },
+        })
+    }
+
+    /// Returns the protocol fees paid by the fill at `i`, i.e. the ETH which
+    /// the exchange forwards to the fee collector. Any other ETH sent by the
+    /// fill (e.g. the refund of what exceeded the fee) is left alone.
+    fn protocol_fees(&self, actions: &[Classification], i: usize) -> Vec<(usize, Transfer)> {
+        let trace_address = actions[i].trace_address();
+        actions
+            .iter()
+            .enumerate()
+            .skip(i + 1)
+            .filter(|(_, t)| {
+                trace_address.is_empty() || is_subtrace(&trace_address, &t.trace_address())
+            })
+            .filter_map(|(idx, t)| Some((idx, t.as_call()?.as_ref())))
+            .filter(|(_, sub)| !sub.value.is_zero() && sub.to == *ZEROX_STAKING)
+            .map(|(idx, sub)| {
+                let fee = Transfer {
+                    from: sub.from,
+                    to: sub.to,
+                    amount: sub.value,
+                    token: *ETH,
+                };
+                (idx, fee)
+            })
+            .collect()
+    }
+
+    /// Returns the trade made by a `transformERC20` call, along with the
+    /// indices of the taker's transfers which it accounts for. The transfers
+    /// in between are the transformations' own trades, which are left for
+    /// the inspectors of the venues they were made on.
+    fn try_as_transform(
+        &self,
+        actions: &[Classification],
+        i: usize,
+        call: &TraceCall,
+    ) -> Option<(Trade, Vec<usize>)> {
+        if call.to != *ZEROX_EXCHANGE_PROXY {
+            return None;
+        }
+        let (input_token, output_token, input_amount, _, _) = self
+            .exchange_proxy
+            .decode::<TransformERC20, _>("transformERC20", &call.input)
+            .ok()?;
+        let (idx_out, received) = subtrace_transfers(&self.erc20, actions, i)
+            .filter(|(_, t)| t.token == output_token && t.to == call.from)
+            .last()?;
+        let mut legs = subtrace_transfers(&self.erc20, actions, i)
+            .find(|(_, t)| t.token == input_token && t.from == call.from)
+            .map(|(idx_in, _)| vec![idx_in])
+            .unwrap_or_default();
+        legs.push(idx_out);
+
+        let trade = Trade {
+            t1: Transfer {
+                from: call.from,
+                to: call.to,
+                amount: input_amount,
+                token: input_token,
+            },
+            t2: Transfer {
+                from: call.to,
+                to: call.from,
+                amount: received.amount,
+                token: output_token,
+            },
+        };
+        Some((trade, legs))
     }
 }
 
+/// Returns the token of ERC20 asset data, i.e. `ERC20Token(address)` encoded
+fn erc20_asset(data: &[u8]) -> Option<Address> {
+    const ERC20_PROXY_ID: [u8; 4] = [0xf4, 0x72, 0x61, 0xb0];
+    if data.len() < 36 || data[..4] != ERC20_PROXY_ID {
+        return None;
+    }
+    Some(Address::from_slice(&data[16..36]))
+}
+
 impl Inspector for ZeroEx {
     fn inspect(&self, inspection: &mut Inspection) {
         let actions = inspection.actions.to_vec();
         let mut prune = Vec::new();
+        let mut fills = Vec::new();
+        let mut legs = Vec::new();
         for i in 0..inspection.actions.len() {
             let action = &mut inspection.actions[i];
 
@@ -47,8 +269,8 @@ impl Inspector for ZeroEx {
                     inspection.protocols.insert(Protocol::ZeroEx);
 
                     // the bridge call will tell us which sub-protocol was used
-                    if let Some(protocol) = PROTOCOLS.get(&transfer.1) {
-                        inspection.protocols.insert(*protocol);
+                    if let Some(protocol) = registry().protocol(&transfer.1) {
+                        inspection.protocols.insert(protocol);
                     }
 
                     // change this to a transfer
@@ -64,6 +286,16 @@ impl Inspector for ZeroEx {
 
                     // keep the index to prune all the subcalls
                     prune.push(i);
+                } else if let Some(fill) = self.try_as_fill(call) {
+                    inspection.protocols.insert(Protocol::ZeroEx);
+                    if let Some(trade) = self.fill_trade(&actions, i, call, fill) {
+                        *action = Classification::new(trade, calltrace.trace_address.clone());
+                        fills.push(i);
+                    }
+                } else if let Some((trade, transfers)) = self.try_as_transform(&actions, i, call) {
+                    inspection.protocols.insert(Protocol::ZeroEx);
+                    *action = Classification::new(trade, calltrace.trace_address.clone());
+                    legs.extend(transfers);
                 }
             }
         }
@@ -72,6 +304,18 @@ impl Inspector for ZeroEx {
         prune
             .into_iter()
             .for_each(|idx| actions[idx].prune_subcalls(&mut inspection.actions));
+        // the fills' transfers are accounted for in their trades, except for
+        // the protocol fees which are paid on top
+        for idx in fills {
+            let fees = self.protocol_fees(&actions, idx);
+            prune_subtraces(&mut inspection.actions, idx);
+            for (fee_idx, fee) in fees {
+                inspection.actions[fee_idx] =
+                    Classification::new(fee, actions[fee_idx].trace_address());
+            }
+        }
+        legs.into_iter()
+            .for_each(|idx| inspection.actions[idx] = Classification::Prune);
     }
 }
 
@@ -149,4 +393,314 @@ mod tests {
         // the trade required more than we put in (TODO: is this correct?)
         assert_ne!(t1.amount, balancer.t1.amount + uniswap.t1.amount);
     }
+
+    #[test]
+    // The same `transformERC20` as above, had it paid out to the taker
+    fn transform_keeps_venue_trades() {
+        let mut inspection = read_trace("exchange_proxy.json");
+        let taker: Address = "0x431e81e5dfb5a24541b5ff8762bdef3f32f96354"
+            .parse()
+            .unwrap();
+        let wallet: Address = "0x22f9dcf4647084d6c31b2765f6910cd85c178c18"
+            .parse()
+            .unwrap();
+        let paid = U256::from_dec_str("1000000000000000000000").unwrap();
+        let received = U256::from_dec_str("23425690000000000000").unwrap();
+        inspection.actions.push(mk_call(
+            wallet,
+            taker,
+            received,
+            Bytes::default(),
+            vec![0, 2],
+        ));
+        let zeroex = MyInspector::new();
+        zeroex.inspect(&mut inspection);
+        assert_eq!(
+            inspection.protocols,
+            crate::set![Protocol::ZeroEx, Protocol::Balancer, Protocol::Uniswap]
+        );
+        let known = inspection.known();
+        assert_eq!(known.len(), 3);
+
+        let trade = known[0].as_ref().trade().unwrap();
+        assert_eq!((trade.t1.from, trade.t1.amount), (taker, paid));
+        assert_eq!((trade.t2.to, trade.t2.amount), (taker, received));
+        assert_eq!(trade.t2.token, *ETH);
+
+        // the venues' trades are still there
+        let balancer = known[1].as_ref().trade().unwrap();
+        assert_eq!(
+            balancer.t1.amount,
+            U256::from_dec_str("384007192433857968681").unwrap()
+        );
+        let uniswap = known[2].as_ref().trade().unwrap();
+        assert_eq!(
+            uniswap.t1.amount,
+            U256::from_dec_str("622513125832506272941").unwrap()
+        );
+    }
+
+    fn signature() -> Signature {
+        (0, 2, [0; 32], [0; 32])
+    }
+
+    #[test]
+    fn rfq_fill() {
+        let addrs = addrs();
+        let (taker, maker, implementation, usdc, weth) =
+            (addrs[0], addrs[1], addrs[2], addrs[3], addrs[4]);
+        let inspector = MyInspector::new();
+
+        let order: RfqOrder = (
+            weth,
+            usdc,
+            U256::from(1),
+            U256::from(2000),
+            maker,
+            taker,
+            taker,
+            [0; 32],
+            U256::from(1_700_000_000),
+            U256::zero(),
+        );
+        let input = inspector
+            .zeroex
+            .exchange_proxy
+            .encode("fillRfqOrder", (order, signature(), U256::from(2000)))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(taker, *ZEROX_EXCHANGE_PROXY, 0, input.clone(), vec![]),
+            // the proxy delegates to the feature implementation
+            mk_call(*ZEROX_EXCHANGE_PROXY, implementation, 0, input, vec![0]),
+            mk_call(
+                *ZEROX_EXCHANGE_PROXY,
+                usdc,
+                0,
+                erc20()
+                    .encode("transferFrom", (taker, maker, U256::from(2000)))
+                    .unwrap(),
+                vec![0, 0],
+            ),
+            mk_call(
+                *ZEROX_EXCHANGE_PROXY,
+                weth,
+                0,
+                erc20()
+                    .encode("transferFrom", (maker, taker, U256::from(1)))
+                    .unwrap(),
+                vec![0, 1],
+            ),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::ZeroEx]);
+        let known = inspection.known();
+        assert_eq!(known.len(), 1);
+        let trade = known[0].as_ref().trade().unwrap();
+        assert_eq!((trade.t1.from, trade.t1.to), (taker, maker));
+        assert_eq!((trade.t1.token, trade.t1.amount), (usdc, 2000.into()));
+        assert_eq!((trade.t2.from, trade.t2.to), (maker, taker));
+        assert_eq!((trade.t2.token, trade.t2.amount), (weth, 1.into()));
+    }
+
+    #[test]
+    fn limit_fill_pays_protocol_fee() {
+        let addrs = addrs();
+        let (taker, maker, usdc, weth, other) = (addrs[0], addrs[1], addrs[2], addrs[3], addrs[4]);
+        let inspector = MyInspector::new();
+
+        let order: LimitOrder = (
+            weth,
+            usdc,
+            U256::from(1),
+            U256::from(2000),
+            U256::zero(),
+            maker,
+            Address::zero(),
+            Address::zero(),
+            Address::zero(),
+            [0; 32],
+            U256::from(1_700_000_000),
+            U256::zero(),
+        );
+        let input = inspector
+            .zeroex
+            .exchange_proxy
+            .encode("fillLimitOrder", (order, signature(), U256::from(2000)))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            // the taker sends more ETH than the fee and is refunded the rest
+            mk_call(taker, *ZEROX_EXCHANGE_PROXY, 100, input, vec![0]),
+            mk_call(
+                *ZEROX_EXCHANGE_PROXY,
+                usdc,
+                0,
+                erc20()
+                    .encode("transferFrom", (taker, maker, U256::from(2000)))
+                    .unwrap(),
+                vec![0, 0],
+            ),
+            mk_call(
+                *ZEROX_EXCHANGE_PROXY,
+                weth,
+                0,
+                erc20()
+                    .encode("transferFrom", (maker, taker, U256::from(1)))
+                    .unwrap(),
+                vec![0, 1],
+            ),
+            // the fee is forwarded to the fee collector
+            mk_call(
+                *ZEROX_EXCHANGE_PROXY,
+                *ZEROX_STAKING,
+                70,
+                Bytes::default(),
+                vec![0, 2],
+            ),
+            mk_call(
+                *ZEROX_EXCHANGE_PROXY,
+                taker,
+                20,
+                Bytes::default(),
+                vec![0, 3],
+            ),
+            // any other ETH sent along is not a fee
+            mk_call(
+                *ZEROX_EXCHANGE_PROXY,
+                other,
+                10,
+                Bytes::default(),
+                vec![0, 4],
+            ),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        let known = inspection.known();
+        assert_eq!(known.len(), 2);
+        let trade = known[0].as_ref().trade().unwrap();
+        assert_eq!((trade.t2.token, trade.t2.amount), (weth, 1.into()));
+        let fee = known[1].as_ref().transfer().unwrap();
+        assert_eq!(
+            *fee,
+            Transfer {
+                from: *ZEROX_EXCHANGE_PROXY,
+                to: *ZEROX_STAKING,
+                amount: 70.into(),
+                token: *ETH,
+            }
+        );
+    }
+
+    #[test]
+    fn v3_fill() {
+        let addrs = addrs();
+        let (taker, maker, usdc, weth) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let inspector = MyInspector::new();
+
+        let asset_data = |token: Address| {
+            let mut data = vec![0xf4, 0x72, 0x61, 0xb0];
+            data.extend_from_slice(&[0; 12]);
+            data.extend_from_slice(token.as_bytes());
+            Bytes::from(data)
+        };
+        let order: OrderV3 = (
+            maker,
+            Address::zero(),
+            Address::zero(),
+            Address::zero(),
+            U256::from(1),
+            U256::from(2000),
+            U256::zero(),
+            U256::zero(),
+            U256::from(1_700_000_000),
+            U256::zero(),
+            asset_data(weth),
+            asset_data(usdc),
+            Bytes::default(),
+            Bytes::default(),
+        );
+        let input = inspector
+            .zeroex
+            .exchange_v3
+            .encode("fillOrder", (order, U256::from(2000), Bytes::default()))
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(taker, *ZEROX, 0, input, vec![]),
+            mk_call(
+                *ZEROX,
+                usdc,
+                0,
+                erc20()
+                    .encode("transferFrom", (taker, maker, U256::from(2000)))
+                    .unwrap(),
+                vec![0],
+            ),
+            mk_call(
+                *ZEROX,
+                weth,
+                0,
+                erc20()
+                    .encode("transferFrom", (maker, taker, U256::from(1)))
+                    .unwrap(),
+                vec![1],
+            ),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        let known = inspection.known();
+        assert_eq!(known.len(), 1);
+        let trade = known[0].as_ref().trade().unwrap();
+        assert_eq!((trade.t1.token, trade.t1.amount), (usdc, 2000.into()));
+        assert_eq!((trade.t2.token, trade.t2.amount), (weth, 1.into()));
+    }
+
+    #[test]
+    fn transform_erc20() {
+        let addrs = addrs();
+        let (taker, wallet, usdc, dai) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let inspector = MyInspector::new();
+
+        let input = inspector
+            .zeroex
+            .exchange_proxy
+            .encode(
+                "transformERC20",
+                (
+                    usdc,
+                    dai,
+                    U256::from(100),
+                    U256::from(90),
+                    Token::Array(vec![]),
+                ),
+            )
+            .unwrap();
+        let mut inspection = mk_inspection(vec![
+            mk_call(taker, *ZEROX_EXCHANGE_PROXY, 0, input, vec![]),
+            mk_call(
+                *ZEROX_EXCHANGE_PROXY,
+                usdc,
+                0,
+                erc20()
+                    .encode("transferFrom", (taker, wallet, U256::from(100)))
+                    .unwrap(),
+                vec![0],
+            ),
+            mk_call(
+                wallet,
+                dai,
+                0,
+                erc20().encode("transfer", (taker, U256::from(99))).unwrap(),
+                vec![1],
+            ),
+        ]);
+        inspector.inspect(&mut inspection);
+
+        assert_eq!(inspection.protocols, crate::set![Protocol::ZeroEx]);
+        let known = inspection.known();
+        assert_eq!(known.len(), 1);
+        let trade = known[0].as_ref().trade().unwrap();
+        assert_eq!((trade.t1.token, trade.t1.amount), (usdc, 100.into()));
+        assert_eq!((trade.t2.token, trade.t2.amount), (dai, 99.into()));
+        assert_eq!(trade.t2.to, taker);
+    }
 }
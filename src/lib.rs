@@ -1,5 +1,5 @@
-#![allow(clippy::clippy::new_without_default)]
-#![allow(clippy::clippy::clippy::single_match)]
+#![allow(clippy::new_without_default)]
+#![allow(clippy::single_match)]
 //! MEV-INSPECT
 //!
 //! Utility for MEV Inspection
@@ -16,6 +16,9 @@ pub mod inspectors;
 /// Reducers
 pub mod reducers;
 
+/// The inspectors and reducers to run, and their order
+pub mod pipeline;
+
 /// Batch Inspector which tries to decode traces using
 /// multiple inspectors
 pub use inspectors::BatchInspector;
@@ -26,18 +29,46 @@ pub mod types;
 /// Various addresses which are found among protocols
 pub mod addresses;
 
+/// Address labels, protocols, filters and key contracts loaded at runtime
+pub mod registry;
+
+/// Uniswap V2 style pairs which are discovered while inspecting
+mod pairs;
+pub use pairs::{PairFactory, PairRegistry};
+
+/// Clusters of addresses which extract MEV together
+mod searchers;
+pub use searchers::{Searcher, Searchers};
+
 mod cached_provider;
 pub use cached_provider::CachedProvider;
 
+mod retry_provider;
+pub use retry_provider::{RetryProvider, RetryProviderError};
+
+mod balanced_provider;
+pub use balanced_provider::{BalancedProvider, BalancedProviderError, Strategy};
+
 #[cfg(test)]
 mod test_helpers;
 
 mod traits;
 pub use traits::*;
 
-/// PostGres trait implementations
-mod mevdb;
-pub use mevdb::{BatchInserts, MevDB};
+/// PostGres and SQLite storage backends
+pub mod storage;
+pub use storage::{MevDB, SqliteDB, Storage};
+
+/// Read-only HTTP API over the stored evaluations
+#[cfg(feature = "serve")]
+pub mod serve;
+
+/// Database and file sinks for evaluations
+pub mod sinks;
+pub use sinks::{BatchInserts, Sink};
+
+/// Prometheus metrics of the block processing pipeline
+pub mod metrics;
 
 mod prices;
 pub use prices::HistoricalPrice;
@@ -1,19 +1,32 @@
 use mev_inspect::{
-    inspectors::{Aave, Balancer, Compound, Curve, Uniswap, ZeroEx, ERC20},
-    reducers::{ArbitrageReducer, LiquidationReducer, TradeReducer},
+    inspectors::BatchEvaluationError,
+    metrics,
+    pipeline::PipelineConfig,
+    registry::{self, registry, Registry},
+    sinks::{self, InsertEvaluationError},
+    storage::{migrations::MIGRATIONS, DbError, Identifier, Table},
     types::Evaluation,
-    BatchInserts, BatchInspector, CachedProvider, HistoricalPrice, Inspector, MevDB, Reducer,
+    BalancedProvider, BatchInserts, BatchInspector, CachedProvider, HistoricalPrice, MevDB,
+    PairFactory, PairRegistry, RetryProvider, Searchers, Sink, SqliteDB, Storage, Strategy,
 };
 
 use ethers::{
-    providers::{Middleware, Provider, StreamExt},
-    types::{BlockNumber, TxHash, U256},
+    providers::{Http, Middleware, Provider, StreamExt},
+    types::{Address, BlockNumber, TxHash, U256},
 };
 
-use futures::SinkExt;
+use futures::{SinkExt, Stream};
 use gumdrop::Options;
+use itertools::Itertools;
 use std::io::Write;
-use std::{collections::HashMap, convert::TryFrom, path::PathBuf, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    convert::TryFrom,
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 #[derive(Debug, Options, Clone)]
 struct Opts {
@@ -26,18 +39,105 @@ struct Opts {
     overwrite: bool,
 
     #[options(
-        default = "http://localhost:8545",
-        help = "The tracing / archival node's URL"
+        help = "The tracing / archival node's URL, can be repeated to balance the requests over several nodes (default: http://localhost:8545)"
+    )]
+    url: Vec<String>,
+    #[options(
+        default = "round-robin",
+        help = "how to pick the node for a request: round-robin or latency"
     )]
-    url: String,
+    balance: Strategy,
+    #[options(
+        no_short,
+        default = "3",
+        help = "skip nodes which are more than this many blocks behind the others"
+    )]
+    max_lag: u64,
 
     #[options(help = "Path to where traces will be cached")]
     cache: Option<PathBuf>,
 
-    #[options(help = "Database config")]
-    db_cfg: tokio_postgres::Config,
+    #[options(help = "Path to where discovered Uniswap-style pairs will be persisted")]
+    pairs: Option<PathBuf>,
+
+    #[options(
+        help = "load address labels, protocols, filters and contracts from this TOML/JSON/CSV file (can be repeated)"
+    )]
+    registry: Vec<PathBuf>,
+
+    #[options(
+        no_short,
+        help = "load the inspectors and reducers to run, in order, from this TOML file"
+    )]
+    pipeline: Option<PathBuf>,
+
+    #[options(help = "Path to where the searchers' clusters will be persisted")]
+    searchers: Option<PathBuf>,
+
+    #[options(help = "print the results as JSON")]
+    json: bool,
+
+    #[options(help = "skip txs sent to this address (can be repeated)")]
+    filter: Vec<Address>,
+    #[options(help = "skip txs sent to the built-in list of noisy contracts")]
+    filter_known: bool,
+
+    #[options(
+        help = "write the evaluations to this .jsonl, .csv or .parquet file instead of the database"
+    )]
+    output: Option<PathBuf>,
+
+    #[options(help = "store the evaluations in this SQLite file instead of Postgres")]
+    sqlite: Option<PathBuf>,
+
+    #[options(
+        help = "Database config, as a connection string or postgres:// URL (default: $DATABASE_URL or the PG* variables)"
+    )]
+    db_cfg: Option<tokio_postgres::Config>,
     #[options(default = "mev_inspections", help = "the table of the database")]
-    db_table: String,
+    db_table: Identifier,
+    #[options(
+        help = "the Postgres schema to create the tables in (default: the search path's first)"
+    )]
+    db_schema: Option<Identifier>,
+    #[options(
+        default = "4",
+        help = "how many connections to the database to keep open and insert with"
+    )]
+    db_connections: usize,
+    #[options(
+        no_short,
+        help = "verify the database server's TLS certificate and host name (requires the `tls` feature)"
+    )]
+    db_tls_verify: bool,
+
+    #[options(
+        help = "serve Prometheus metrics on this address's /metrics while inspecting (requires the `metrics` feature)"
+    )]
+    metrics: Option<std::net::SocketAddr>,
+
+    #[options(
+        default = "5",
+        help = "how often to retry requests to the node which failed transiently"
+    )]
+    max_retries: u32,
+    #[options(
+        default = "500",
+        help = "how many milliseconds to wait before retrying a request, doubled for every retry"
+    )]
+    retry_backoff: u64,
+    #[options(help = "send at most this many requests per second to the node")]
+    rate_limit: Option<u32>,
+    #[options(
+        default = "20",
+        help = "pause requests to the node after this many failed in a row"
+    )]
+    circuit_threshold: u32,
+    #[options(
+        default = "30",
+        help = "how many seconds to pause requests to the node for"
+    )]
+    circuit_cooldown: u64,
 
     // Single tx or many blocks
     #[options(command)]
@@ -50,6 +150,39 @@ enum Command {
     Tx(TxOpts),
     #[options(help = "inspect a range of blocks")]
     Blocks(BlockOpts),
+    #[options(help = "manage the database's schema")]
+    Db(DbOpts),
+    #[cfg(feature = "serve")]
+    #[options(help = "serve the stored inspections over HTTP")]
+    Serve(ServeOpts),
+}
+
+#[cfg(feature = "serve")]
+#[derive(Debug, Options, Clone)]
+struct ServeOpts {
+    help: bool,
+    #[options(default = "127.0.0.1:3000", help = "the address to listen on")]
+    addr: std::net::SocketAddr,
+}
+
+#[derive(Debug, Options, Clone)]
+struct DbOpts {
+    help: bool,
+    #[options(command)]
+    cmd: Option<DbCommand>,
+}
+
+#[derive(Debug, Options, Clone)]
+enum DbCommand {
+    #[options(help = "apply the pending migrations")]
+    Migrate(NoOpts),
+    #[options(help = "list the migrations and whether they were applied")]
+    Status(NoOpts),
+}
+
+#[derive(Debug, Options, Clone)]
+struct NoOpts {
+    help: bool,
 }
 
 #[derive(Debug, Options, Clone)]
@@ -72,6 +205,13 @@ struct BlockOpts {
         help = "Maximum of requests each task is allowed to execute concurrently"
     )]
     max_requests: usize,
+    #[options(default = "100", help = "how many evaluations to insert at once")]
+    batch_size: usize,
+    #[options(
+        default = "1000",
+        help = "how many milliseconds evaluations may wait before a partial batch is inserted"
+    )]
+    flush_interval: u64,
 }
 
 #[tokio::main]
@@ -79,52 +219,114 @@ async fn main() -> anyhow::Result<()> {
     pretty_env_logger::init();
     let opts = Opts::parse_args_default_or_exit();
 
+    // Load the registry before anything reads an address from it
+    registry::install(Registry::with_files(&opts.registry)?)?;
+
+    // Schema management does not need a node
+    if let Some(Command::Db(ref db_opts)) = opts.cmd {
+        let cmd = db_opts
+            .cmd
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("expected a `migrate` or `status` command"))?;
+        return match opts.sqlite {
+            Some(ref path) => {
+                let db = SqliteDB::open(path, Table::new(opts.db_table.clone()))?;
+                db_command(db, cmd).await
+            }
+            None => {
+                let table = postgres_table(&opts);
+                let cfg = db_config(opts.db_cfg)?;
+                let db = MevDB::connect_pool(cfg, table, 1, opts.db_tls_verify).await?;
+                db_command(db, cmd).await
+            }
+        };
+    }
+
+    // Serving the stored inspections does not need a node either
+    #[cfg(feature = "serve")]
+    if let Some(Command::Serve(ref serve_opts)) = opts.cmd {
+        let addr = serve_opts.addr;
+        return match opts.sqlite {
+            Some(ref path) => {
+                let db = SqliteDB::open(path, Table::new(opts.db_table.clone()))?;
+                serve(check_schema(db).await?, addr).await
+            }
+            None => {
+                let table = postgres_table(&opts);
+                let cfg = db_config(opts.db_cfg)?;
+                let db = MevDB::connect_pool(cfg, table, opts.db_connections, opts.db_tls_verify)
+                    .await?;
+                serve(check_schema(db).await?, addr).await
+            }
+        };
+    }
+
+    if let Some(addr) = opts.metrics {
+        serve_metrics(addr)?;
+    }
+
+    // Reject invalid pipelines before connecting to the nodes
+    let pipeline = match opts.pipeline {
+        Some(ref path) => PipelineConfig::load(path)?,
+        None => PipelineConfig::default(),
+    };
+
     // Instantiate the provider and read from the cached files if needed
+    let provider = retrying(balanced(&opts).await?, &opts);
     if let Some(ref cache) = opts.cache {
-        let provider = CachedProvider::new(Provider::try_from(opts.url.as_str())?, cache);
-        run(provider, opts).await
+        let provider = CachedProvider::new(provider, cache);
+        run(provider, pipeline, opts).await
     } else {
-        let provider = Provider::try_from(opts.url.as_str())?;
-        run(provider, opts).await
+        run(provider, pipeline, opts).await
     }
 }
 
-async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow::Result<()> {
+async fn run<M: Middleware + Clone + 'static>(
+    provider: M,
+    pipeline: PipelineConfig,
+    opts: Opts,
+) -> anyhow::Result<()> {
     let provider = Arc::new(provider);
     // Instantiate the thing which will query historical prices
     let prices = HistoricalPrice::new(provider.clone());
 
-    let compound = Compound::create(provider.clone()).await?;
-    let curve = Curve::create(provider.clone()).await?;
-    let inspectors: Vec<Box<dyn Inspector + Send + Sync>> = vec![
-        // Classify Transfers
-        Box::new(ZeroEx::new()),
-        Box::new(ERC20::new()),
-        // Classify AMMs
-        Box::new(Balancer::new()),
-        Box::new(Uniswap::new()),
-        Box::new(curve),
-        // Classify Liquidations
-        Box::new(Aave::new()),
-        Box::new(compound),
-    ];
-
-    let reducers: Vec<Box<dyn Reducer + Send + Sync>> = vec![
-        Box::new(LiquidationReducer::new()),
-        Box::new(TradeReducer::new()),
-        Box::new(ArbitrageReducer::new()),
-    ];
-    let processor = BatchInspector::new(inspectors, reducers);
-
-    // TODO: Pass overwrite parameter
-    let mut db = MevDB::connect(opts.db_cfg, &opts.db_table).await?;
-    db.create().await?;
-    if opts.reset {
-        db.clear().await?;
-        db.create().await?;
+    let pairs = Arc::new(match opts.pairs {
+        Some(ref path) => PairRegistry::open(path, PairFactory::defaults())?,
+        None => PairRegistry::new(PairFactory::defaults()),
+    });
+    let mut filter = opts.filter.clone();
+    if opts.filter_known {
+        filter.extend(registry().filter.iter());
     }
-    log::debug!("created mevdb table");
+    let processor = pipeline
+        .build(provider.clone(), pairs.clone())
+        .await?
+        .with_filter(filter)
+        .with_pairs(pairs);
+
+    let mut sink: Box<dyn Sink> = match (&opts.output, &opts.sqlite) {
+        (Some(path), _) => sinks::open(path)?,
+        (None, Some(path)) => {
+            let db = SqliteDB::open(path, Table::new(opts.db_table.clone()))?;
+            Box::new(prepare(db, opts.reset).await?)
+        }
+        (None, None) => {
+            // TODO: Pass overwrite parameter
+            let table = postgres_table(&opts);
+            let cfg = db_config(opts.db_cfg)?;
+            let db =
+                MevDB::connect_pool(cfg, table, opts.db_connections, opts.db_tls_verify).await?;
+            Box::new(prepare(db, opts.reset).await?)
+        }
+    };
+
+    let mut searchers = match opts.searchers {
+        Some(ref path) => Searchers::open(path)?,
+        None => Searchers::new(),
+    };
 
+    let json = opts.json;
+    let poll_head = opts.metrics.is_some();
     if let Some(cmd) = opts.cmd {
         match cmd {
             Command::Tx(opts) => {
@@ -145,17 +347,35 @@ async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow
 
                     let evaluation =
                         Evaluation::new(inspection, &prices, gas_used, gas_price).await?;
-                    println!("Found: {:?}", evaluation.as_ref().hash);
-                    println!("Revenue: {:?} WEI", evaluation.profit);
-                    println!("Cost: {:?} WEI", evaluation.gas_used * evaluation.gas_price);
-                    println!("Actions: {:?}", evaluation.actions);
-                    println!("Protocols: {:?}", evaluation.inspection.protocols);
-                    println!("Status: {:?}", evaluation.inspection.status);
-                    db.insert(&evaluation).await?;
+                    let summary = evaluation.inspection.summary();
+                    let cost = evaluation.gas_used * evaluation.gas_price;
+                    let searcher = searchers.record(&evaluation);
+                    if json {
+                        let report = serde_json::json!({
+                            "summary": summary,
+                            "evaluation": evaluation,
+                            "cost": cost.to_string(),
+                            "searcher": searcher.map(|searcher| searcher.id),
+                        });
+                        println!("{}", report);
+                    } else {
+                        println!("{}", summary);
+                        println!("Evaluation: {:?}", evaluation.actions);
+                        println!("Revenue: {} WEI", evaluation.profit);
+                        println!("Cost: {} WEI", cost);
+                        if let Some(searcher) = searcher {
+                            println!("Searcher: {}", searcher);
+                        }
+                    }
+                    sink.write(&evaluation).await?;
                 } else {
                     eprintln!("No actions found for tx {:?}", opts.tx);
                 }
+                sink.finish().await?;
             }
+            Command::Db(_) => unreachable!("handled before connecting to the node"),
+            #[cfg(feature = "serve")]
+            Command::Serve(_) => unreachable!("handled before connecting to the node"),
             Command::Blocks(inner) => {
                 log::debug!("command blocks {:?}", inner);
                 let provider = Arc::new(provider);
@@ -192,9 +412,8 @@ async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow
                 };
 
                 for from in (inner.from..inner.to)
-                    .into_iter()
                     .step_by(blocks_per_task as usize)
-                    .take(num_tasks as usize)
+                    .take(num_tasks)
                 {
                     let processor = Arc::clone(&processor);
                     let eval_stream = processor.evaluate_blocks(
@@ -218,36 +437,74 @@ async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow
                 // drop the sender so that the channel gets closed
                 drop(tx);
 
-                // all the evaluations arrive at the receiver and are inserted into the DB
-                let mut inserts = BatchInserts::new(db, rx);
-                let mut insert_ctn = 0usize;
-                let mut error_ctn = 0usize;
-                while let Some(res) = inserts.next().await {
-                    match res {
-                        Ok(eval) => {
-                            insert_ctn += 1;
-                            log::info!(
-                                "Inserted tx 0x{} in block {}",
-                                eval.inspection.hash,
-                                eval.inspection.block_number,
-                            );
-                        }
-                        Err(err) => {
-                            error_ctn += 1;
-                            log::error!("failed to insert: {:?}", err)
-                        }
+                // all the evaluations arrive at the receiver and are written to the sink
+                let mut summary = Summary::default();
+                let inserts = batch_inserts(sink, rx, &inner, opts.db_connections);
+                let mut sink = insert_all(inserts, &mut searchers, json, &mut summary).await?;
+
+                // give the blocks which could not be fetched another chance,
+                // now that the node is not as busy
+                let failed = std::mem::take(&mut summary.failed_blocks);
+                if !failed.is_empty() {
+                    log::warn!("retrying {} failed blocks", failed.len());
+                    let evals = futures::stream::iter(failed.into_iter().map(|block| {
+                        Arc::clone(&processor).evaluate_blocks(
+                            Arc::clone(&provider),
+                            Arc::clone(&prices),
+                            block..block + 1,
+                            inner.max_requests,
+                        )
+                    }))
+                    .flatten();
+                    let inserts = batch_inserts(sink, evals, &inner, opts.db_connections);
+                    sink = insert_all(inserts, &mut searchers, json, &mut summary).await?;
+                }
+                sink.finish().await?;
+                for blocks in summary.processed(inner.from..inner.to) {
+                    searchers.mark_processed(blocks);
+                }
+
+                if !summary.failed_blocks.is_empty() {
+                    log::error!("failed to fetch blocks {:?}", summary.failed_blocks);
+                }
+                // keep stdout valid JSON lines
+                if !json {
+                    println!(
+                        "inserted evaluations: {}, errors: {}, block range [{}..{}) using {} tasks",
+                        summary.inserted, summary.errors, inner.from, inner.to, inner.tasks
+                    );
+                    if !summary.failed_blocks.is_empty() {
+                        println!(
+                            "failed blocks: {}",
+                            summary.failed_blocks.iter().map(u64::to_string).join(", ")
+                        );
+                    }
+                    for searcher in searchers.iter() {
+                        println!("{}", searcher);
                     }
                 }
-                println!(
-                    "inserted evaluations: {}, errors: {}, block range [{}..{}) using {} tasks",
-                    insert_ctn, error_ctn, inner.from, inner.to, inner.tasks
-                );
             }
         };
     } else {
+        // keep the head lag current even if processing a block stalls
+        if poll_head {
+            let provider = Arc::clone(&provider);
+            tokio::task::spawn(async move {
+                let mut interval = tokio::time::interval(HEAD_POLL_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    match provider.get_block_number().await {
+                        Ok(head) => metrics::set_head_block(head.as_u64()),
+                        Err(err) => log::warn!("failed to get the latest block: {:?}", err),
+                    }
+                }
+            });
+        }
+
         let mut watcher = provider.watch_blocks().await?;
         while watcher.next().await.is_some() {
             let block = provider.get_block_number().await?;
+            metrics::set_head_block(block.as_u64());
             let stdout = std::io::stdout();
             let mut lock = stdout.lock();
             writeln!(lock, "Got block: {}", block.as_u64())?;
@@ -256,22 +513,286 @@ async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow
                 block.as_u64(),
                 &provider,
                 &processor,
-                &mut db,
+                &mut *sink,
+                &mut searchers,
                 &prices,
             )
             .await?;
+            sink.flush().await?;
+            searchers.mark_processed(block.as_u64()..block.as_u64() + 1);
+            searchers.save()?;
+            metrics::set_last_processed_block(block.as_u64());
+        }
+    }
+
+    searchers.save()?;
+    Ok(())
+}
+
+/// The outcome of inserting the evaluations of a range of blocks
+#[derive(Debug, Default)]
+struct Summary {
+    inserted: usize,
+    errors: usize,
+    /// Blocks which could not be fetched from the node
+    failed_blocks: BTreeSet<u64>,
+    /// Blocks with evaluations which could not be inserted
+    failed_inserts: BTreeSet<u64>,
+    /// Whether there were errors which cannot be traced back to a block
+    unknown_errors: bool,
+}
+
+impl Summary {
+    /// Returns the sub-ranges of `blocks` whose evaluations were all inserted
+    fn processed(&self, blocks: Range<u64>) -> Vec<Range<u64>> {
+        if self.unknown_errors {
+            return Vec::new();
+        }
+        let mut res = Vec::new();
+        let mut start = blocks.start;
+        let failed = self.failed_blocks.union(&self.failed_inserts).cloned();
+        for block in failed.chain(std::iter::once(blocks.end)) {
+            if block > start {
+                res.push(start..block.min(blocks.end));
+            }
+            start = start.max(block + 1);
+        }
+        res
+    }
+}
+
+fn batch_inserts<'a, M, S>(
+    sink: Box<dyn Sink>,
+    evals: S,
+    opts: &BlockOpts,
+    connections: usize,
+) -> BatchInserts<'a, M>
+where
+    M: Middleware + Unpin + 'static,
+    S: Stream<Item = Result<Evaluation, BatchEvaluationError<M>>> + 'a,
+{
+    BatchInserts::new(sink, evals)
+        .with_batch_size(opts.batch_size)
+        .with_flush_interval(Duration::from_millis(opts.flush_interval))
+        .with_connections(connections)
+}
+
+/// Writes all evaluations to the sink and returns it again
+async fn insert_all<M: Middleware + Unpin + 'static>(
+    mut inserts: BatchInserts<'_, M>,
+    searchers: &mut Searchers,
+    json: bool,
+    summary: &mut Summary,
+) -> anyhow::Result<Box<dyn Sink>> {
+    while let Some(res) = inserts.next().await {
+        match res {
+            Ok(eval) => {
+                summary.inserted += 1;
+                let searcher = searchers.record(&eval);
+                if json {
+                    println!("{}", serde_json::to_string(&eval.inspection.summary())?);
+                }
+                match searcher {
+                    Some(searcher) => log::info!(
+                        "Inserted tx 0x{} in block {} by {}",
+                        eval.inspection.hash,
+                        eval.inspection.block_number,
+                        searcher,
+                    ),
+                    None => log::info!(
+                        "Inserted tx 0x{} in block {}",
+                        eval.inspection.hash,
+                        eval.inspection.block_number,
+                    ),
+                }
+            }
+            Err(InsertEvaluationError::BatchEvaluationError(err))
+                if err.failed_block().is_some() =>
+            {
+                log::error!("failed to fetch block: {:?}", err);
+                summary.failed_blocks.extend(err.failed_block());
+            }
+            Err(InsertEvaluationError::Insert(eval, err)) => {
+                summary.errors += 1;
+                summary.failed_inserts.insert(eval.inspection.block_number);
+                log::error!(
+                    "failed to insert tx 0x{} in block {}: {:?}",
+                    eval.inspection.hash,
+                    eval.inspection.block_number,
+                    err
+                )
+            }
+            Err(err) => {
+                summary.errors += 1;
+                summary.unknown_errors = true;
+                log::error!("failed to insert: {:?}", err)
+            }
+        }
+    }
+    Ok(inserts.into_sink().await)
+}
+
+/// How often the chain's head is polled to measure the lag of watching it
+const HEAD_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+async fn db_command<S: Storage>(mut db: S, cmd: DbCommand) -> anyhow::Result<()> {
+    match cmd {
+        DbCommand::Migrate(_) => {
+            let applied = db.migrate().await?;
+            if applied.is_empty() {
+                println!("the schema is up to date");
+            }
+            for version in applied {
+                println!("applied migration {}", version);
+            }
+        }
+        DbCommand::Status(_) => {
+            let applied = db.applied_migrations().await?;
+            for migration in MIGRATIONS {
+                let status = if applied.contains(&migration.version) {
+                    "applied"
+                } else {
+                    "pending"
+                };
+                println!(
+                    "{:>3} {:<8} {}",
+                    migration.version, status, migration.description
+                );
+            }
         }
     }
+    Ok(())
+}
+
+/// The node used if no `--url` is given
+const DEFAULT_URL: &str = "http://localhost:8545";
+
+/// How often the nodes are checked for whether they fell behind
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Balances the requests over all the `--url`s, skipping those which fell
+/// behind
+async fn balanced(opts: &Opts) -> anyhow::Result<BalancedProvider<Provider<Http>>> {
+    let nodes = if opts.url.is_empty() {
+        vec![Provider::try_from(DEFAULT_URL)?]
+    } else {
+        opts.url
+            .iter()
+            .map(|url| Provider::try_from(url.as_str()))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let provider = BalancedProvider::new(nodes)
+        .with_strategy(opts.balance)
+        .with_max_lag(opts.max_lag);
+
+    if opts.url.len() > 1 {
+        for (i, url) in opts.url.iter().enumerate() {
+            log::info!("node {}: {}", i, url);
+        }
+        provider.check_health().await;
+        let provider = provider.clone();
+        tokio::task::spawn(async move {
+            loop {
+                tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+                provider.check_health().await;
+            }
+        });
+    }
+    Ok(provider)
+}
+
+/// Retries the node's requests, rate limits them and pauses them while the
+/// node is failing, as configured by the options
+fn retrying<M: Middleware>(provider: M, opts: &Opts) -> RetryProvider<M>
+where
+    M::Error: 'static,
+{
+    let backoff = Duration::from_millis(opts.retry_backoff);
+    let provider = RetryProvider::new(provider)
+        .with_max_retries(opts.max_retries)
+        .with_backoff(backoff, backoff * 64)
+        .with_circuit_breaker(
+            opts.circuit_threshold,
+            Duration::from_secs(opts.circuit_cooldown),
+        );
+    match opts.rate_limit {
+        Some(requests_per_second) => provider.with_rate_limit(requests_per_second),
+        None => provider,
+    }
+}
+
+/// The inspections table, in the `--db-schema` if one was given
+fn postgres_table(opts: &Opts) -> Table {
+    let table = Table::new(opts.db_table.clone());
+    match opts.db_schema {
+        Some(ref schema) => table.with_schema(schema.clone()),
+        None => table,
+    }
+}
+
+/// Falls back to the environment's config if none was passed
+fn db_config(cfg: Option<tokio_postgres::Config>) -> Result<tokio_postgres::Config, DbError> {
+    match cfg {
+        Some(cfg) => Ok(cfg),
+        None => MevDB::config_from_env(),
+    }
+}
+
+/// Serving is read-only, so the schema must already be migrated
+#[cfg(feature = "serve")]
+async fn check_schema<S: Storage>(mut db: S) -> anyhow::Result<S> {
+    let applied = db.applied_migrations().await?;
+    if mev_inspect::storage::migrations::pending(&applied)
+        .next()
+        .is_some()
+    {
+        anyhow::bail!("the database's schema is out of date, run `db migrate` first");
+    }
+    Ok(db)
+}
 
+#[cfg(feature = "metrics")]
+fn serve_metrics(addr: std::net::SocketAddr) -> anyhow::Result<()> {
+    tokio::task::spawn(async move {
+        if let Err(err) = mev_inspect::metrics::serve(addr).await {
+            log::error!("failed to serve the metrics: {:?}", err);
+        }
+    });
     Ok(())
 }
 
+#[cfg(not(feature = "metrics"))]
+fn serve_metrics(_: std::net::SocketAddr) -> anyhow::Result<()> {
+    anyhow::bail!("serving metrics requires building with the `metrics` feature")
+}
+
+#[cfg(feature = "serve")]
+async fn serve<S: Storage + Clone + Sync + 'static>(
+    db: S,
+    addr: std::net::SocketAddr,
+) -> anyhow::Result<()> {
+    Ok(mev_inspect::serve::serve(db, addr).await?)
+}
+
+/// Creates the table or applies any pending migrations, re-building it if
+/// `reset` is set
+async fn prepare<S: Storage>(mut db: S, reset: bool) -> Result<S, DbError> {
+    db.create().await?;
+    if reset {
+        db.clear().await?;
+        db.create().await?;
+    }
+    log::debug!("created mevdb table");
+    Ok(db)
+}
+
 async fn process_block<M: Middleware + 'static>(
     lock: &mut std::io::StdoutLock<'_>,
     block_number: u64,
     provider: &M,
     processor: &BatchInspector,
-    db: &mut MevDB,
+    sink: &mut dyn Sink,
+    searchers: &mut Searchers,
     prices: &HistoricalPrice<M>,
 ) -> anyhow::Result<()> {
     let block_number = block_number.into();
@@ -293,6 +814,8 @@ async fn process_block<M: Middleware + 'static>(
 
     // get all the receipts
     let receipts = provider.parity_block_receipts(block_number).await?;
+    metrics::BLOCKS_FETCHED.inc();
+    processor.register_pairs(&receipts);
     let gas_used_txs = receipts
         .into_iter()
         .map(|receipt| {
@@ -316,12 +839,23 @@ async fn process_block<M: Middleware + 'static>(
             .get(&inspection.hash)
             .cloned()
             .unwrap_or_default();
-        Evaluation::new(inspection, &prices, gas_used, gas_price)
+        Evaluation::new(inspection, prices, gas_used, gas_price)
     });
-    for evaluation in futures::future::join_all(eval_futs).await {
-        if let Ok(evaluation) = evaluation {
-            db.insert(&evaluation).await?;
+    for res in futures::future::join_all(eval_futs).await {
+        let evaluation = match res {
+            Ok(evaluation) => evaluation,
+            Err(err) => {
+                metrics::record_evaluation_error(err.kind());
+                continue;
+            }
+        };
+        if let Some(searcher) = searchers.record(&evaluation) {
+            writeln!(lock, "{:?} by {}", evaluation.inspection.hash, searcher)?;
         }
+        let timer = metrics::INSERT_DURATION.start_timer();
+        sink.write(&evaluation).await?;
+        timer.observe_duration();
+        metrics::INSERTED.inc();
     }
 
     writeln!(
@@ -0,0 +1,314 @@
+//! Prometheus metrics of the block processing pipeline
+//!
+//! All metrics are registered in a single registry whose contents are
+//! rendered in the text exposition format by [`encode`]. Building with the
+//! `metrics` feature, which is on by default, also adds a server for them, see
+//! [`serve`].
+use crate::types::Inspection;
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram_with_registry, register_int_counter_vec_with_registry,
+    register_int_counter_with_registry, register_int_gauge_with_registry, Encoder, Histogram,
+    IntCounter, IntCounterVec, IntGauge, Registry, TextEncoder,
+};
+
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+/// Blocks whose traces, transactions and receipts were fetched
+pub static BLOCKS_FETCHED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter_with_registry!(
+        "mev_blocks_fetched_total",
+        "Blocks whose traces, transactions and receipts were fetched",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Inspections per involved protocol
+pub static INSPECTED_PROTOCOLS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec_with_registry!(
+        "mev_inspections_total",
+        "Inspected transactions per involved protocol",
+        &["protocol"],
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Classified actions per kind
+pub static INSPECTED_ACTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec_with_registry!(
+        "mev_actions_total",
+        "Classified actions per kind",
+        &["action"],
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Blocks or inspections which could not be evaluated, per kind of error
+pub static EVALUATION_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec_with_registry!(
+        "mev_evaluation_errors_total",
+        "Blocks or inspections which could not be evaluated",
+        &["kind"],
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Requests to the node which were retried
+pub static RPC_RETRIES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter_with_registry!(
+        "mev_rpc_retries_total",
+        "Requests to the node which were retried",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// How often requests to the node were paused after too many failed
+pub static RPC_CIRCUIT_OPENED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter_with_registry!(
+        "mev_rpc_circuit_opened_total",
+        "How often requests to the node were paused after too many failed in a row",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Nodes which are used for requests
+pub static RPC_HEALTHY_NODES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge_with_registry!(
+        "mev_rpc_healthy_nodes",
+        "Nodes which are used for requests, as of the last health check",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Historical prices which could not be quoted
+pub static PRICE_QUOTE_FAILURES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter_with_registry!(
+        "mev_price_quote_failures_total",
+        "Historical prices which could not be quoted",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// How long writing evaluations to the sink took
+pub static INSERT_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram_with_registry!(
+        "mev_insert_duration_seconds",
+        "How long writing a batch of evaluations to the sink took",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Evaluations which were written to the sink
+pub static INSERTED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter_with_registry!(
+        "mev_inserted_evaluations_total",
+        "Evaluations which were written to the sink",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Inspections waiting for a free evaluation slot
+pub static WAITING_INSPECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge_with_registry!(
+        "mev_waiting_inspections",
+        "Inspections waiting for a free evaluation slot",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Evaluations waiting to be written to the sink
+pub static INSERTION_QUEUE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge_with_registry!(
+        "mev_insertion_queue",
+        "Evaluations waiting to be written to the sink",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// The chain's latest block, when watching it
+pub static HEAD_BLOCK: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge_with_registry!(
+        "mev_head_block",
+        "The chain's latest block, when watching it",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// The latest block which was processed, when watching the chain
+pub static LAST_PROCESSED_BLOCK: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge_with_registry!(
+        "mev_last_processed_block",
+        "The latest block which was processed, when watching the chain",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// How many blocks the processing is behind the chain's head
+pub static HEAD_LAG: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge_with_registry!(
+        "mev_head_lag_blocks",
+        "How many blocks the processing is behind the chain's head, when watching it",
+        REGISTRY
+    )
+    .unwrap()
+});
+
+/// Counts the inspection's protocols and actions
+pub fn record_inspection(inspection: &Inspection) {
+    for protocol in &inspection.protocols {
+        let protocol = format!("{:?}", protocol).to_lowercase();
+        INSPECTED_PROTOCOLS.with_label_values(&[&protocol]).inc();
+    }
+    for action in inspection.actions.iter().filter_map(|a| a.as_action()) {
+        INSPECTED_ACTIONS.with_label_values(&[action.kind()]).inc();
+    }
+}
+
+/// Counts an error of the evaluation pipeline
+pub fn record_evaluation_error(kind: &str) {
+    EVALUATION_ERRORS.with_label_values(&[kind]).inc();
+}
+
+/// Sets the chain's latest block
+pub fn set_head_block(block: u64) {
+    HEAD_BLOCK.set(block as i64);
+    update_head_lag();
+}
+
+/// Sets the latest processed block
+pub fn set_last_processed_block(block: u64) {
+    LAST_PROCESSED_BLOCK.set(block as i64);
+    update_head_lag();
+}
+
+fn update_head_lag() {
+    let processed = LAST_PROCESSED_BLOCK.get();
+    if processed > 0 {
+        HEAD_LAG.set((HEAD_BLOCK.get() - processed).max(0));
+    }
+}
+
+/// Renders all metrics in the Prometheus text format
+pub fn encode() -> String {
+    // register the metrics which were not touched yet, so that they are
+    // exported with their initial values
+    Lazy::force(&BLOCKS_FETCHED);
+    Lazy::force(&INSPECTED_PROTOCOLS);
+    Lazy::force(&INSPECTED_ACTIONS);
+    Lazy::force(&EVALUATION_ERRORS);
+    Lazy::force(&RPC_RETRIES);
+    Lazy::force(&RPC_CIRCUIT_OPENED);
+    Lazy::force(&RPC_HEALTHY_NODES);
+    Lazy::force(&PRICE_QUOTE_FAILURES);
+    Lazy::force(&INSERT_DURATION);
+    Lazy::force(&INSERTED);
+    Lazy::force(&WAITING_INSPECTIONS);
+    Lazy::force(&INSERTION_QUEUE);
+    Lazy::force(&HEAD_BLOCK);
+    Lazy::force(&LAST_PROCESSED_BLOCK);
+    Lazy::force(&HEAD_LAG);
+
+    let mut buf = Vec::new();
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buf)
+        .expect("metrics are valid");
+    String::from_utf8(buf).expect("metrics are utf-8")
+}
+
+/// The content type of [`encode`]'s output
+pub const CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+/// Returns the route serving the metrics to Prometheus on `GET /metrics`
+#[cfg(feature = "metrics")]
+pub fn router() -> axum::Router {
+    use axum::{http::header, routing::get};
+    axum::Router::new().route(
+        "/metrics",
+        get(|| async { ([(header::CONTENT_TYPE, CONTENT_TYPE)], encode()) }),
+    )
+}
+
+/// Serves the metrics on `addr` until the process stops
+#[cfg(feature = "metrics")]
+pub async fn serve(addr: std::net::SocketAddr) -> Result<(), axum::Error> {
+    log::info!("serving the metrics on http://{}/metrics", addr);
+    axum::Server::bind(&addr)
+        .serve(router().into_make_service())
+        .await
+        .map_err(axum::Error::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_all_metrics() {
+        let text = encode();
+        for metric in [
+            "mev_blocks_fetched_total counter",
+            "mev_price_quote_failures_total counter",
+            "mev_insert_duration_seconds histogram",
+            "mev_inserted_evaluations_total counter",
+            "mev_waiting_inspections gauge",
+            "mev_insertion_queue gauge",
+            "mev_head_lag_blocks gauge",
+        ]
+        .iter()
+        {
+            assert!(text.contains(&format!("# TYPE {}", metric)), "{}", metric);
+        }
+
+        record_evaluation_error("test");
+        assert!(encode().contains(r#"mev_evaluation_errors_total{kind="test"} 1"#));
+    }
+
+    #[test]
+    fn tracks_the_head_lag() {
+        set_head_block(100);
+        set_last_processed_block(90);
+        assert_eq!(HEAD_LAG.get(), 10);
+        set_head_block(101);
+        assert_eq!(HEAD_LAG.get(), 11);
+        set_last_processed_block(101);
+        assert_eq!(HEAD_LAG.get(), 0);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn serves_metrics() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let server =
+            axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(router().into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        BLOCKS_FETCHED.inc();
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.0\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.0 200"));
+        assert!(response.contains("# TYPE mev_blocks_fetched_total counter"));
+        assert!(response.contains("mev_insertion_queue"));
+    }
+}
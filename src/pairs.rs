@@ -0,0 +1,300 @@
+use crate::{
+    addresses::{parse_address, SAKESWAP_FACTORY, SUSHISWAP_FACTORY, UNISWAP_FACTORY},
+    types::Protocol,
+};
+
+use ethers::{
+    types::{Address, Log, H256},
+    utils::keccak256,
+};
+
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+#[derive(Debug, Clone)]
+/// A Uniswap V2 style factory. Its pairs are picked up from the `PairCreated`
+/// events it emits and, if the hash of the pair's init code is known, their
+/// addresses can also be derived from their tokens, since they are deployed
+/// with CREATE2.
+pub struct PairFactory {
+    pub address: Address,
+    /// The keccak256 hash of the pair contract's init code
+    pub init_code_hash: Option<H256>,
+    pub protocol: Protocol,
+}
+
+impl PairFactory {
+    /// Constructor
+    pub fn new(address: Address, init_code_hash: Option<H256>, protocol: Protocol) -> Self {
+        Self {
+            address,
+            init_code_hash,
+            protocol,
+        }
+    }
+
+    /// The factories of Uniswap V2 and its known forks
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new(
+                *UNISWAP_FACTORY,
+                Some(
+                    "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"
+                        .parse()
+                        .expect("invalid init code hash"),
+                ),
+                Protocol::Uniswap,
+            ),
+            // The commonly quoted Sushiswap init code hash (0xe18a34eb...7a3d)
+            // derives none of the pairs of `res/sushipairs.csv`, e.g. not
+            // SUSHI/WETH at 0x795065dc...1fb0, so its pairs are only picked up
+            // from their `PairCreated` events until a hash is checked against
+            // them (see the `default_hashes_derive_onchain_pairs` test).
+            Self::new(*SUSHISWAP_FACTORY, None, Protocol::Sushiswap),
+            Self::new(*SAKESWAP_FACTORY, None, Protocol::SakeSwap),
+        ]
+    }
+
+    /// Returns the CREATE2 address of the pair of the two tokens, in any order
+    pub fn pair_for(&self, token_a: Address, token_b: Address) -> Option<Address> {
+        let init_code_hash = self.init_code_hash?;
+        let (token0, token1) = if token_a < token_b {
+            (token_a, token_b)
+        } else {
+            (token_b, token_a)
+        };
+        let salt = keccak256([token0.as_bytes(), token1.as_bytes()].concat());
+
+        let mut bytes = Vec::with_capacity(85);
+        bytes.push(0xff);
+        bytes.extend_from_slice(self.address.as_bytes());
+        bytes.extend_from_slice(&salt);
+        bytes.extend_from_slice(init_code_hash.as_bytes());
+        Some(Address::from_slice(&keccak256(bytes)[12..]))
+    }
+}
+
+#[derive(Debug)]
+/// Pairs which were discovered while inspecting, either by matching their
+/// address against the factories' CREATE2 addresses or by seeing them get
+/// created. If a path is given, the pairs are persisted there as
+/// `address,protocol` lines and are loaded back on the next run.
+pub struct PairRegistry {
+    factories: Vec<PairFactory>,
+    pairs: RwLock<HashMap<Address, Protocol>>,
+    path: Option<PathBuf>,
+}
+
+impl PairRegistry {
+    /// Instantiates an in-memory registry
+    pub fn new(factories: Vec<PairFactory>) -> Self {
+        Self {
+            factories,
+            pairs: RwLock::new(HashMap::new()),
+            path: None,
+        }
+    }
+
+    /// Instantiates a registry persisted at `path`, loading any pairs which
+    /// were previously discovered
+    pub fn open<P: AsRef<Path>>(path: P, factories: Vec<PairFactory>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut pairs = HashMap::new();
+        match File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    let mut columns = line.split(',');
+                    let (pair, protocol) = match (columns.next(), columns.next()) {
+                        (Some(pair), Some(protocol)) => (pair.trim(), protocol.trim()),
+                        _ => continue,
+                    };
+                    let protocol = protocol
+                        .parse::<Protocol>()
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    pairs.insert(parse_address(pair), protocol);
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            factories,
+            pairs: RwLock::new(pairs),
+            path: Some(path),
+        })
+    }
+
+    /// Returns the protocol of a previously discovered pair
+    pub fn get(&self, pair: &Address) -> Option<Protocol> {
+        self.pairs.read().unwrap().get(pair).cloned()
+    }
+
+    /// Returns the factory deployed at `address`, if any
+    pub fn factory(&self, address: &Address) -> Option<&PairFactory> {
+        self.factories.iter().find(|f| f.address == *address)
+    }
+
+    /// Checks whether `pair` was deployed by any of the factories for the
+    /// two tokens, and registers it if so
+    pub fn discover(&self, pair: Address, token_a: Address, token_b: Address) -> Option<Protocol> {
+        if let Some(protocol) = self.get(&pair) {
+            return Some(protocol);
+        }
+        let protocol = self
+            .factories
+            .iter()
+            .find(|factory| factory.pair_for(token_a, token_b) == Some(pair))?
+            .protocol;
+        self.register(pair, protocol);
+        Some(protocol)
+    }
+
+    /// Registers the pairs created in the `PairCreated` events of the factories
+    pub fn ingest_logs<'a>(&self, logs: impl IntoIterator<Item = &'a Log>) {
+        let topic = H256::from(keccak256("PairCreated(address,address,address,uint256)"));
+        for log in logs {
+            if log.topics.first() != Some(&topic) || log.data.as_ref().len() < 32 {
+                continue;
+            }
+            if let Some(factory) = self.factory(&log.address) {
+                // the pair is the first non-indexed argument
+                let pair = Address::from_slice(&log.data.as_ref()[12..32]);
+                self.register(pair, factory.protocol);
+            }
+        }
+    }
+
+    /// Registers a pair, persisting it if the registry is backed by a file
+    pub fn register(&self, pair: Address, protocol: Protocol) {
+        let mut pairs = self.pairs.write().unwrap();
+        if pairs.insert(pair, protocol) == Some(protocol) {
+            return;
+        }
+
+        if let Some(ref path) = self.path {
+            let res = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{:?},{:?}", pair, protocol));
+            if let Err(err) = res {
+                log::warn!("could not persist pair {:?}: {:?}", pair, err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addresses::WETH;
+
+    fn registry() -> PairRegistry {
+        PairRegistry::new(PairFactory::defaults())
+    }
+
+    #[test]
+    fn derives_pair_addresses() {
+        let usdc = parse_address("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let yfi = parse_address("0x0bc529c00c6401aef6d220be8c6ea1667f6ad93e");
+        let registry = registry();
+
+        let uni = parse_address("0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc");
+        assert_eq!(registry.discover(uni, usdc, *WETH), Some(Protocol::Uniswap));
+        // token order does not matter
+        let yfi_pair = parse_address("0x2fdbadf3c4d5a8666bc06645b8358ab803996e28");
+        assert_eq!(
+            registry.discover(yfi_pair, *WETH, yfi),
+            Some(Protocol::Uniswap)
+        );
+
+        // not a pair of these tokens
+        let unknown = Address::random();
+        assert_eq!(registry.discover(unknown, yfi, *WETH), None);
+        assert_eq!(registry.get(&uni), Some(Protocol::Uniswap));
+        assert_eq!(registry.get(&unknown), None);
+    }
+
+    #[test]
+    fn default_hashes_derive_onchain_pairs() {
+        let usdc = parse_address("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let fixtures = [
+            (*UNISWAP_FACTORY, "res/v2pairs.csv"),
+            (*SUSHISWAP_FACTORY, "res/sushipairs.csv"),
+        ];
+        for (address, path) in fixtures.iter() {
+            let factory = registry().factory(address).cloned().unwrap();
+            let pair = match factory.pair_for(usdc, *WETH) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let pairs = std::fs::read_to_string(path).unwrap();
+            assert!(
+                pairs.lines().any(|line| parse_address(line) == pair),
+                "{:?} is not a {:?} pair",
+                pair,
+                factory.protocol
+            );
+        }
+    }
+
+    #[test]
+    fn ingests_pair_created() {
+        let registry = registry();
+        let pair = Address::random();
+        let mut data = vec![0; 12];
+        data.extend_from_slice(pair.as_bytes());
+        data.extend_from_slice(&[0; 31]);
+        data.push(1);
+
+        let log = Log {
+            address: *SAKESWAP_FACTORY,
+            topics: vec![
+                H256::from(keccak256("PairCreated(address,address,address,uint256)")),
+                H256::from(Address::random()),
+                H256::from(Address::random()),
+            ],
+            data: data.into(),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+        // logs of other contracts are ignored
+        let mut other = log.clone();
+        other.address = Address::random();
+        registry.ingest_logs(&[other]);
+        assert_eq!(registry.get(&pair), None);
+
+        registry.ingest_logs(&[log]);
+        assert_eq!(registry.get(&pair), Some(Protocol::SakeSwap));
+    }
+
+    #[test]
+    fn persists_pairs() {
+        let path = std::env::temp_dir().join(format!("pairs-{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let pair = Address::random();
+
+        let registry = PairRegistry::open(&path, PairFactory::defaults()).unwrap();
+        registry.register(pair, Protocol::SakeSwap);
+        // registering again does not add a duplicate line
+        registry.register(pair, Protocol::SakeSwap);
+
+        let registry = PairRegistry::open(&path, PairFactory::defaults()).unwrap();
+        assert_eq!(registry.get(&pair), Some(Protocol::SakeSwap));
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
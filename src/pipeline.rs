@@ -0,0 +1,509 @@
+//! The inspectors and reducers which classify the traces, and their order
+//!
+//! Inspectors run one after the other on every inspection and each may
+//! rely on what earlier ones classified, e.g. the AMMs turn the transfers
+//! which the `erc20` inspector found into trades. The same goes for the
+//! reducers, which run after all inspectors. Every component declares which
+//! others it needs to run before it, and which it must run ahead of, so that
+//! configs which would silently misclassify are rejected on startup.
+use crate::{
+    inspectors::{
+        Aave, Balancer, Bancor, Compound, CompoundDeployment, Curve, Kyber, Liquity, Maker,
+        OneInch, Paraswap, Synthetix, Uniswap, UniswapV1, ZeroEx, ERC20,
+    },
+    reducers::{ArbitrageReducer, LiquidationReducer, TradeReducer},
+    types::Protocol,
+    BatchInspector, Inspector, PairRegistry, Reducer,
+};
+
+use ethers::providers::Middleware;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{collections::HashMap, path::Path, sync::Arc};
+use thiserror::Error;
+
+/// An inspector or reducer, and how it must be ordered relative to others
+#[derive(Debug, Clone, Copy)]
+pub struct Component {
+    pub name: &'static str,
+    /// Components which must be configured and run before this one
+    pub requires: &'static [&'static str],
+    /// Components which must run after this one, if they are configured
+    pub runs_before: &'static [&'static str],
+}
+
+const AMMS: &[&str] = &["balancer", "uniswap", "curve"];
+
+/// All inspectors, in the order they run by default
+pub const INSPECTORS: &[Component] = &[
+    // Classify Transfers
+    Component {
+        name: "zeroex",
+        requires: &[],
+        runs_before: &["erc20"],
+    },
+    // These must run before the ERC20 inspector, which would otherwise take
+    // their ETH-carrying calls for plain transfers
+    Component {
+        name: "kyber",
+        requires: &[],
+        runs_before: &["erc20"],
+    },
+    Component {
+        name: "bancor",
+        requires: &[],
+        runs_before: &["erc20"],
+    },
+    Component {
+        name: "synthetix",
+        requires: &[],
+        runs_before: &["erc20"],
+    },
+    Component {
+        name: "uniswap_v1",
+        requires: &[],
+        runs_before: &["erc20"],
+    },
+    Component {
+        name: "erc20",
+        requires: &[],
+        runs_before: &[],
+    },
+    // Classify Aggregators, before the AMMs prune the transfers they route
+    Component {
+        name: "oneinch",
+        requires: &["erc20"],
+        runs_before: AMMS,
+    },
+    Component {
+        name: "paraswap",
+        requires: &[],
+        runs_before: AMMS,
+    },
+    // Classify AMMs
+    Component {
+        name: "balancer",
+        requires: &["erc20"],
+        runs_before: &[],
+    },
+    Component {
+        name: "uniswap",
+        requires: &["erc20"],
+        runs_before: &[],
+    },
+    Component {
+        name: "curve",
+        requires: &[],
+        runs_before: &[],
+    },
+    // Classify Liquidations
+    Component {
+        name: "aave",
+        requires: &["erc20"],
+        runs_before: &[],
+    },
+    Component {
+        name: "compound",
+        requires: &[],
+        runs_before: &[],
+    },
+    Component {
+        name: "maker",
+        requires: &[],
+        runs_before: &[],
+    },
+    Component {
+        name: "liquity",
+        requires: &[],
+        runs_before: &[],
+    },
+];
+
+/// All reducers, in the order they run by default
+pub const REDUCERS: &[Component] = &[
+    // Classify liquidations first, before their transfers become trades
+    Component {
+        name: "liquidation",
+        requires: &["erc20"],
+        runs_before: &["trade"],
+    },
+    Component {
+        name: "trade",
+        requires: &["erc20"],
+        runs_before: &[],
+    },
+    Component {
+        name: "arbitrage",
+        requires: &["trade"],
+        runs_before: &[],
+    },
+];
+
+/// Which inspectors and reducers to run, in order
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    #[serde(default)]
+    pub inspectors: Vec<ComponentConfig>,
+    #[serde(default)]
+    pub reducers: Vec<ComponentConfig>,
+}
+
+/// A component to run and its options
+#[derive(Debug, Clone, Deserialize)]
+pub struct ComponentConfig {
+    pub name: String,
+    /// Any other keys are the component's options
+    #[serde(flatten)]
+    pub options: toml::value::Table,
+}
+
+impl ComponentConfig {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            options: toml::value::Table::new(),
+        }
+    }
+
+    fn options<T: DeserializeOwned>(&self) -> Result<T, PipelineError> {
+        toml::Value::Table(self.options.clone())
+            .try_into()
+            .map_err(|err| PipelineError::InvalidOptions(self.name.clone(), err.to_string()))
+    }
+}
+
+/// For the components which do not take any options
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoOptions {}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct CompoundOptions {
+    /// Which of Compound and its forks to inspect, all by default
+    deployments: Option<Vec<Protocol>>,
+}
+
+impl CompoundOptions {
+    fn deployments(&self) -> Result<Vec<CompoundDeployment>, PipelineError> {
+        let defaults = CompoundDeployment::defaults();
+        let protocols = match self.deployments {
+            Some(ref protocols) => protocols,
+            None => return Ok(defaults),
+        };
+        protocols
+            .iter()
+            .map(|protocol| {
+                defaults
+                    .iter()
+                    .find(|deployment| deployment.protocol == *protocol)
+                    .cloned()
+                    .ok_or_else(|| {
+                        PipelineError::InvalidOptions(
+                            "compound".to_owned(),
+                            format!("{:?} is not a known Compound deployment", protocol),
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+impl Default for PipelineConfig {
+    /// Runs all inspectors and reducers in their default order
+    fn default() -> Self {
+        Self {
+            inspectors: INSPECTORS
+                .iter()
+                .map(|c| ComponentConfig::new(c.name))
+                .collect(),
+            reducers: REDUCERS
+                .iter()
+                .map(|c| ComponentConfig::new(c.name))
+                .collect(),
+        }
+    }
+}
+
+impl PipelineConfig {
+    /// Reads and validates the config from a TOML file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PipelineError> {
+        let contents = std::fs::read_to_string(path)?;
+        let config: Self = toml::from_str(&contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that all components exist, are configured once, have valid
+    /// options and are ordered as their dependencies require
+    pub fn validate(&self) -> Result<(), PipelineError> {
+        let inspectors = self.inspectors.iter().map(|c| {
+            find(INSPECTORS, &c.name).ok_or_else(|| PipelineError::UnknownInspector(c.name.clone()))
+        });
+        let reducers = self.reducers.iter().map(|c| {
+            find(REDUCERS, &c.name).ok_or_else(|| PipelineError::UnknownReducer(c.name.clone()))
+        });
+        let components = inspectors.chain(reducers).collect::<Result<Vec<_>, _>>()?;
+
+        // inspectors and reducers share the namespace
+        let mut positions = HashMap::new();
+        for (i, component) in components.iter().enumerate() {
+            if positions.insert(component.name, i).is_some() {
+                return Err(PipelineError::Duplicate(component.name.to_owned()));
+            }
+        }
+
+        for (i, component) in components.iter().enumerate() {
+            for dependency in component.requires {
+                match positions.get(dependency) {
+                    None => {
+                        return Err(PipelineError::MissingDependency(
+                            component.name.to_owned(),
+                            (*dependency).to_owned(),
+                        ))
+                    }
+                    Some(j) if *j > i => {
+                        return Err(PipelineError::MustRunAfter(
+                            component.name.to_owned(),
+                            (*dependency).to_owned(),
+                        ))
+                    }
+                    Some(_) => {}
+                }
+            }
+            for later in component.runs_before {
+                if matches!(positions.get(later), Some(j) if *j < i) {
+                    return Err(PipelineError::MustRunAfter(
+                        (*later).to_owned(),
+                        component.name.to_owned(),
+                    ));
+                }
+            }
+        }
+
+        for config in self.inspectors.iter().chain(&self.reducers) {
+            match config.name.as_str() {
+                "compound" => config
+                    .options::<CompoundOptions>()?
+                    .deployments()
+                    .map(drop)?,
+                _ => config.options::<NoOptions>().map(drop)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Instantiates the inspectors and reducers. Some inspectors query the
+    /// node for the contracts they inspect.
+    pub async fn build<M: Middleware + 'static>(
+        &self,
+        provider: Arc<M>,
+        pairs: Arc<PairRegistry>,
+    ) -> Result<BatchInspector, PipelineError> {
+        self.validate()?;
+
+        let mut inspectors: Vec<Box<dyn Inspector + Send + Sync>> = Vec::new();
+        for config in &self.inspectors {
+            inspectors.push(match config.name.as_str() {
+                "zeroex" => Box::new(ZeroEx::new()),
+                "kyber" => Box::new(Kyber::new()),
+                "bancor" => Box::new(Bancor::new()),
+                "synthetix" => Box::new(Synthetix::new()),
+                "uniswap_v1" => Box::new(UniswapV1::new()),
+                "erc20" => Box::new(ERC20::new()),
+                "oneinch" => Box::new(OneInch::new()),
+                "paraswap" => Box::new(Paraswap::new()),
+                "balancer" => Box::new(Balancer::new()),
+                "uniswap" => Box::new(Uniswap::new().with_pairs(pairs.clone())),
+                "curve" => Box::new(
+                    Curve::create(provider.clone())
+                        .await
+                        .map_err(|err| PipelineError::Create("curve", Box::new(err)))?,
+                ),
+                "aave" => Box::new(Aave::new()),
+                "compound" => {
+                    let deployments = config.options::<CompoundOptions>()?.deployments()?;
+                    Box::new(
+                        Compound::create(provider.clone(), &deployments)
+                            .await
+                            .map_err(|err| PipelineError::Create("compound", Box::new(err)))?,
+                    )
+                }
+                "maker" => Box::new(Maker::new()),
+                "liquity" => Box::new(Liquity::new()),
+                name => return Err(PipelineError::UnknownInspector(name.to_owned())),
+            });
+        }
+
+        let mut reducers: Vec<Box<dyn Reducer + Send + Sync>> = Vec::new();
+        for config in &self.reducers {
+            reducers.push(match config.name.as_str() {
+                "liquidation" => Box::new(LiquidationReducer::new()),
+                "trade" => Box::new(TradeReducer::new()),
+                "arbitrage" => Box::new(ArbitrageReducer::new()),
+                name => return Err(PipelineError::UnknownReducer(name.to_owned())),
+            });
+        }
+
+        Ok(BatchInspector::new(inspectors, reducers))
+    }
+}
+
+fn find(components: &[Component], name: &str) -> Option<Component> {
+    components.iter().find(|c| c.name == name).copied()
+}
+
+#[derive(Error, Debug)]
+pub enum PipelineError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    #[error("unknown inspector `{0}`")]
+    UnknownInspector(String),
+
+    #[error("unknown reducer `{0}`")]
+    UnknownReducer(String),
+
+    #[error("`{0}` is configured more than once")]
+    Duplicate(String),
+
+    #[error("`{0}` requires `{1}`, which is not configured")]
+    MissingDependency(String, String),
+
+    #[error("`{0}` must run after `{1}`")]
+    MustRunAfter(String, String),
+
+    #[error("invalid options for `{0}`: {1}")]
+    InvalidOptions(String, String),
+
+    #[error("could not create the `{0}` inspector: {1}")]
+    Create(&'static str, Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Result<PipelineConfig, PipelineError> {
+        let config: PipelineConfig = toml::from_str(toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn default_pipeline_is_valid() {
+        PipelineConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn parses_configs() {
+        let config = config(
+            r#"
+            [[inspectors]]
+            name = "erc20"
+
+            [[inspectors]]
+            name = "uniswap"
+
+            [[inspectors]]
+            name = "compound"
+            deployments = ["Cream"]
+
+            [[reducers]]
+            name = "trade"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config
+                .inspectors
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["erc20", "uniswap", "compound"]
+        );
+        let deployments = config.inspectors[2]
+            .options::<CompoundOptions>()
+            .unwrap()
+            .deployments()
+            .unwrap();
+        assert_eq!(deployments.len(), 1);
+        assert_eq!(deployments[0].protocol, Protocol::Cream);
+    }
+
+    #[test]
+    fn rejects_invalid_orderings() {
+        let err = config(
+            r#"
+            [[inspectors]]
+            name = "uniswap"
+            [[inspectors]]
+            name = "erc20"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "`uniswap` must run after `erc20`");
+
+        let err = config(
+            r#"
+            [[inspectors]]
+            name = "erc20"
+            [[inspectors]]
+            name = "kyber"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "`erc20` must run after `kyber`");
+
+        let err = config(
+            r#"
+            [[inspectors]]
+            name = "erc20"
+            [[reducers]]
+            name = "arbitrage"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`arbitrage` requires `trade`, which is not configured"
+        );
+
+        let err = config(
+            r#"
+            [[inspectors]]
+            name = "erc20"
+            [[reducers]]
+            name = "trade"
+            [[reducers]]
+            name = "liquidation"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "`trade` must run after `liquidation`");
+    }
+
+    #[test]
+    fn rejects_invalid_components() {
+        let err = config("[[inspectors]]\nname = \"sushi\"").unwrap_err();
+        assert!(matches!(err, PipelineError::UnknownInspector(_)));
+
+        // inspectors can't be used as reducers
+        let err = config("[[reducers]]\nname = \"erc20\"").unwrap_err();
+        assert!(matches!(err, PipelineError::UnknownReducer(_)));
+
+        let err =
+            config("[[inspectors]]\nname = \"aave\"\n[[inspectors]]\nname = \"aave\"").unwrap_err();
+        assert!(matches!(err, PipelineError::Duplicate(_)));
+
+        let err = config("[[inspectors]]\nname = \"maker\"\nfast = true").unwrap_err();
+        assert!(matches!(err, PipelineError::InvalidOptions(_, _)));
+
+        let err =
+            config("[[inspectors]]\nname = \"compound\"\ndeployments = [\"Aave\"]").unwrap_err();
+        assert!(matches!(err, PipelineError::InvalidOptions(_, _)));
+    }
+}
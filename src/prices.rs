@@ -1,5 +1,8 @@
-#![allow(clippy::clippy::too_many_arguments)]
-use crate::addresses::{parse_address, ETH, WETH};
+#![allow(clippy::too_many_arguments)]
+use crate::{
+    addresses::{parse_address, ETH, WETH},
+    metrics,
+};
 use ethers::{
     contract::{abigen, ContractError},
     providers::Middleware,
@@ -66,7 +69,8 @@ impl<M: Middleware> HistoricalPrice<M> {
             .get_amounts_out(one, vec![token, *WETH])
             .block(block)
             .call()
-            .await?;
+            .await
+            .inspect_err(|_| metrics::PRICE_QUOTE_FAILURES.inc())?;
 
         debug_assert_eq!(one, amounts[0]);
         debug_assert_eq!(amounts.len(), 2);
@@ -89,8 +93,7 @@ mod tests {
 
     static PROVIDER: Lazy<Provider<Http>> = Lazy::new(|| {
         let url: String = std::env::var("ARCHIVE").expect("Archive node URL should be set");
-        let provider = Provider::<Http>::try_from(url).unwrap();
-        provider
+        Provider::<Http>::try_from(url).unwrap()
     });
 
     #[tokio::test]
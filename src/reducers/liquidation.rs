@@ -30,10 +30,7 @@ impl Reducer for LiquidationReducer {
             .iter_mut()
             .enumerate()
             .for_each(|(i, ref mut action)| {
-                let opt = action
-                    .as_action_mut()
-                    .map(|x| x.liquidation_mut())
-                    .flatten();
+                let opt = action.as_action_mut().and_then(|x| x.liquidation_mut());
                 let liquidation = if let Some(liquidation) = opt {
                     liquidation
                 } else {
@@ -23,12 +23,12 @@ impl Reducer for TradeReducer {
             .enumerate()
             .for_each(|(i, action)| {
                 // check if we got a transfer
-                let transfer =
-                    if let Some(transfer) = action.as_action().map(|x| x.transfer()).flatten() {
-                        transfer
-                    } else {
-                        return;
-                    };
+                let transfer = if let Some(transfer) = action.as_action().and_then(|x| x.transfer())
+                {
+                    transfer
+                } else {
+                    return;
+                };
 
                 // find the first transfer after it
                 let res = find_matching(
@@ -0,0 +1,275 @@
+use crate::{
+    addresses::{ADDRESSBOOK, FILTER, PROTOCOLS, SHARED},
+    types::Protocol,
+};
+
+use ethers::types::Address;
+
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+static REGISTRY: OnceCell<Registry> = OnceCell::new();
+
+/// Returns the registry which was installed with `install`, or the embedded
+/// defaults if none was
+pub fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(Registry::defaults)
+}
+
+/// Installs the registry which all lookups go through. This must be done
+/// before any inspection happens, since the key contracts' addresses are
+/// read once on first use.
+pub fn install(registry: Registry) -> Result<(), RegistryError> {
+    REGISTRY
+        .set(registry)
+        .map_err(|_| RegistryError::AlreadyInstalled)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Labels, protocol mappings, filters, shared implementations and key
+/// contract addresses
+pub struct Registry {
+    /// Human readable names of addresses
+    pub labels: HashMap<Address, String>,
+    /// The protocol each address belongs to
+    pub protocols: HashMap<Address, Protocol>,
+    /// Txs sent to these addresses may be skipped
+    pub filter: HashSet<Address>,
+    /// Implementations shared by unrelated contracts, e.g. the Gnosis Safe
+    /// master copies, which searchers are not clustered by
+    pub shared: HashSet<Address>,
+    /// Overrides of the key contracts in `addresses`, by the name of their static
+    pub contracts: HashMap<String, Address>,
+}
+
+/// The TOML / JSON representation of a registry
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RegistryFile {
+    labels: HashMap<String, String>,
+    protocols: HashMap<String, String>,
+    filter: Vec<String>,
+    shared: Vec<String>,
+    contracts: HashMap<String, String>,
+}
+
+impl Registry {
+    /// The registry which is compiled into the binary
+    pub fn defaults() -> Self {
+        Self {
+            labels: ADDRESSBOOK.clone(),
+            protocols: PROTOCOLS.clone(),
+            filter: FILTER.clone(),
+            shared: SHARED.clone(),
+            contracts: HashMap::new(),
+        }
+    }
+
+    /// Reads a registry from a `.toml`, `.json` or `.csv` file.
+    ///
+    /// TOML and JSON files may have `labels` and `protocols` tables keyed by
+    /// address, `filter` and `shared` lists of addresses and a `contracts`
+    /// table which maps the names of the statics in `addresses` to their
+    /// address.
+    ///
+    /// CSV files have an `address,label[,protocol]` row per address.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RegistryError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        match extension {
+            Some("toml") => Self::from_file(toml::from_str(&contents)?),
+            Some("json") => Self::from_file(serde_json::from_str(&contents)?),
+            Some("csv") => Self::from_csv(&contents),
+            _ => Err(RegistryError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+
+    /// Loads the files in order on top of the embedded defaults, with later
+    /// files taking precedence
+    pub fn with_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self, RegistryError> {
+        let mut registry = Self::defaults();
+        for path in paths {
+            registry.merge(Self::load(path)?);
+        }
+        Ok(registry)
+    }
+
+    /// Merges `other` into this registry, overriding any existing entries
+    pub fn merge(&mut self, other: Registry) {
+        self.labels.extend(other.labels);
+        self.protocols.extend(other.protocols);
+        self.filter.extend(other.filter);
+        self.shared.extend(other.shared);
+        self.contracts.extend(other.contracts);
+    }
+
+    pub fn label(&self, address: &Address) -> Option<&String> {
+        self.labels.get(address)
+    }
+
+    pub fn protocol(&self, address: &Address) -> Option<Protocol> {
+        self.protocols.get(address).cloned()
+    }
+
+    pub fn contract(&self, name: &str) -> Option<Address> {
+        self.contracts.get(name).cloned()
+    }
+
+    fn from_file(file: RegistryFile) -> Result<Self, RegistryError> {
+        let mut registry = Self::default();
+        for (address, label) in file.labels {
+            registry.labels.insert(address_from_str(&address)?, label);
+        }
+        for (address, protocol) in file.protocols {
+            registry
+                .protocols
+                .insert(address_from_str(&address)?, protocol_from_str(&protocol)?);
+        }
+        for address in file.filter {
+            registry.filter.insert(address_from_str(&address)?);
+        }
+        for address in file.shared {
+            registry.shared.insert(address_from_str(&address)?);
+        }
+        for (name, address) in file.contracts {
+            registry.contracts.insert(name, address_from_str(&address)?);
+        }
+        Ok(registry)
+    }
+
+    fn from_csv(contents: &str) -> Result<Self, RegistryError> {
+        let mut registry = Self::default();
+        for line in contents.lines() {
+            let mut columns = line.split(',').map(str::trim);
+            let address = match columns.next() {
+                // skip empty lines and the header
+                Some("") | Some("address") | None => continue,
+                Some(address) => address_from_str(address)?,
+            };
+            if let Some(label) = columns.next().filter(|label| !label.is_empty()) {
+                registry.labels.insert(address, label.to_owned());
+            }
+            if let Some(protocol) = columns.next().filter(|protocol| !protocol.is_empty()) {
+                registry
+                    .protocols
+                    .insert(address, protocol_from_str(protocol)?);
+            }
+        }
+        Ok(registry)
+    }
+}
+
+fn address_from_str(address: &str) -> Result<Address, RegistryError> {
+    address
+        .strip_prefix("0x")
+        .unwrap_or(address)
+        .parse()
+        .map_err(|_| RegistryError::InvalidAddress(address.to_owned()))
+}
+
+fn protocol_from_str(protocol: &str) -> Result<Protocol, RegistryError> {
+    protocol.parse().map_err(RegistryError::UnknownProtocol)
+}
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("{0}")]
+    UnknownProtocol(String),
+    #[error("unsupported registry file (expected .toml, .json or .csv): {0:?}")]
+    UnknownFormat(PathBuf),
+    #[error("the registry was already installed or used")]
+    AlreadyInstalled,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addresses::parse_address;
+
+    fn write(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn loads_all_formats() {
+        let bot = parse_address("0x00000000000000000000000000000000000000b0");
+        let pair = parse_address("0x00000000000000000000000000000000000000a1");
+        let toml = write(
+            "registry.toml",
+            r#"
+            filter = ["0x00000000000000000000000000000000000000b0"]
+            shared = ["0x00000000000000000000000000000000000000a1"]
+
+            [labels]
+            "0x00000000000000000000000000000000000000b0" = "My Bot"
+
+            [protocols]
+            "0x00000000000000000000000000000000000000a1" = "SakeSwap"
+
+            [contracts]
+            KYBER_PROXY = "0x00000000000000000000000000000000000000a1"
+            "#,
+        );
+        let json = write(
+            "registry.json",
+            r#"{"labels": {"0x00000000000000000000000000000000000000b0": "Other Bot"}}"#,
+        );
+        let csv = write(
+            "registry.csv",
+            "address,label,protocol\n0x00000000000000000000000000000000000000a1,Sake Pair,SakeSwap\n",
+        );
+
+        let registry = Registry::with_files(&[&toml, &json, &csv]).unwrap();
+        // later files take precedence
+        assert_eq!(registry.label(&bot).unwrap(), "Other Bot");
+        assert_eq!(registry.label(&pair).unwrap(), "Sake Pair");
+        assert_eq!(registry.protocol(&pair), Some(Protocol::SakeSwap));
+        assert!(registry.filter.contains(&bot));
+        assert!(registry.shared.contains(&pair));
+        assert_eq!(registry.contract("KYBER_PROXY"), Some(pair));
+        // the defaults are still there
+        assert_eq!(registry.label(&crate::addresses::WETH).unwrap(), "WETH");
+        assert!(registry.filter.is_superset(&FILTER));
+        assert!(registry.shared.is_superset(&SHARED));
+
+        for path in &[toml, json, csv] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        let csv = write(
+            "invalid.csv",
+            "0x00000000000000000000000000000000000000a1,Pair,Foo\n",
+        );
+        assert!(matches!(
+            Registry::load(&csv),
+            Err(RegistryError::UnknownProtocol(_))
+        ));
+        let txt = write("registry.txt", "");
+        assert!(matches!(
+            Registry::load(&txt),
+            Err(RegistryError::UnknownFormat(_))
+        ));
+        for path in &[csv, txt] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
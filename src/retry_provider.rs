@@ -0,0 +1,422 @@
+use async_trait::async_trait;
+use ethers::{
+    providers::{FromErr, Middleware},
+    types::{
+        Block, BlockId, BlockNumber, Bytes, Filter, Log, NameOrAddress, Trace, Transaction,
+        TransactionReceipt, TransactionRequest, TxHash, H256, U64,
+    },
+};
+use std::{
+    error::Error,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+use crate::metrics;
+
+/// Messages of errors which are worth retrying, e.g. dropped connections,
+/// timeouts, overloaded nodes and rate limits. Anything else, such as reverted
+/// calls, fails right away.
+const RETRYABLE: &[&str] = &[
+    "error sending request",
+    "connection",
+    "timed out",
+    "timeout",
+    "server error",
+    "too many requests",
+    "rate limit",
+    "limit exceeded",
+    "header not found",
+    // the node's proxy answered with something which is not JSON-RPC
+    "deserialization error",
+];
+
+/// Retries the requests which failed transiently with exponential backoff,
+/// limits how many requests are sent per second, and stops sending any for a
+/// while once too many failed in a row.
+///
+/// Clones share the rate limit and the circuit breaker.
+#[derive(Clone, Debug)]
+pub struct RetryProvider<M> {
+    inner: M,
+    /// How often a request is retried before giving up
+    max_retries: u32,
+    /// How long to wait before the first retry, doubled for every other one
+    initial_backoff: Duration,
+    /// The longest to wait between two retries
+    max_backoff: Duration,
+    /// The least time between two requests
+    min_interval: Option<Duration>,
+    /// When the next request may be sent
+    next_request: Arc<Mutex<Instant>>,
+    /// How many failures in a row open the circuit
+    failure_threshold: u32,
+    /// How long the circuit stays open
+    cooldown: Duration,
+    circuit: Arc<Mutex<Circuit>>,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    /// Retryable failures since the last success
+    failures: u32,
+    /// Requests fail right away until then
+    open_until: Option<Instant>,
+}
+
+impl<M: Middleware> RetryProvider<M>
+where
+    M::Error: 'static,
+{
+    /// Retries up to 5 times, starting to back off at 500ms, and opens the
+    /// circuit for 30s after 20 failures in a row. Requests are not rate
+    /// limited.
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            min_interval: None,
+            next_request: Arc::new(Mutex::new(Instant::now())),
+            failure_threshold: 20,
+            cooldown: Duration::from_secs(30),
+            circuit: Arc::new(Mutex::new(Circuit::default())),
+        }
+    }
+
+    /// Sets how often a request is retried before giving up
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets how long to wait before the first retry, which doubles for every
+    /// other retry up to `max`
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Sends at most this many requests per second
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.min_interval =
+            Some(Duration::from_secs(1) / requests_per_second.max(1)).filter(|i| !i.is_zero());
+        self
+    }
+
+    /// Fails requests right away for `cooldown` once `threshold` requests
+    /// failed in a row
+    pub fn with_circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.failure_threshold = threshold.max(1);
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Waits until the rate limit allows sending another request
+    async fn throttle(&self) {
+        let interval = match self.min_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let at = {
+            let mut next_request = self.next_request.lock().unwrap();
+            let at = (*next_request).max(Instant::now());
+            *next_request = at + interval;
+            at
+        };
+        tokio::time::sleep_until(at.into()).await;
+    }
+
+    fn check_circuit(&self) -> Result<(), RetryProviderError<M>> {
+        let mut circuit = self.circuit.lock().unwrap();
+        match circuit.open_until {
+            Some(until) if until > Instant::now() => Err(RetryProviderError::CircuitOpen(
+                until.saturating_duration_since(Instant::now()),
+            )),
+            Some(_) => {
+                // let requests through again, a single failure re-opens it
+                circuit.open_until = None;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn record_success(&self) {
+        self.circuit.lock().unwrap().failures = 0;
+    }
+
+    fn record_failure(&self) {
+        let mut circuit = self.circuit.lock().unwrap();
+        circuit.failures += 1;
+        if circuit.failures >= self.failure_threshold && circuit.open_until.is_none() {
+            log::warn!(
+                "{} requests failed in a row, pausing requests for {:?}",
+                circuit.failures,
+                self.cooldown
+            );
+            metrics::RPC_CIRCUIT_OPENED.inc();
+            circuit.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    /// Sends the request until it succeeds, fails with an error which is not
+    /// worth retrying, or ran out of retries
+    async fn retry<T, F, Fut>(&self, mut request: F) -> Result<T, RetryProviderError<M>>
+    where
+        F: FnMut() -> Fut + Send,
+        Fut: Future<Output = Result<T, M::Error>> + Send,
+    {
+        let mut backoff = self.initial_backoff;
+        let mut retries = 0;
+        loop {
+            self.check_circuit()?;
+            self.throttle().await;
+            let err = match request().await {
+                Ok(res) => {
+                    self.record_success();
+                    return Ok(res);
+                }
+                Err(err) => err,
+            };
+            if !is_retryable(&err) {
+                return Err(RetryProviderError::MiddlewareError(err));
+            }
+            self.record_failure();
+            if retries >= self.max_retries {
+                return Err(RetryProviderError::MiddlewareError(err));
+            }
+            retries += 1;
+            metrics::RPC_RETRIES.inc();
+            log::debug!("retrying request in {:?} after: {}", backoff, err);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.max_backoff);
+        }
+    }
+}
+
+/// Whether the error or any of its sources looks transient
+pub(crate) fn is_retryable(err: &(dyn Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        let msg = err.to_string().to_lowercase();
+        if RETRYABLE.iter().any(|retryable| msg.contains(retryable)) {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+#[async_trait]
+impl<M: Middleware> Middleware for RetryProvider<M>
+where
+    M::Error: 'static,
+{
+    type Error = RetryProviderError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &Self::Inner {
+        &self.inner
+    }
+
+    async fn get_block_number(&self) -> Result<U64, Self::Error> {
+        self.retry(|| self.inner.get_block_number()).await
+    }
+
+    async fn get_block<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block_hash_or_number: T,
+    ) -> Result<Option<Block<TxHash>>, Self::Error> {
+        let block = block_hash_or_number.into();
+        self.retry(|| self.inner.get_block(block)).await
+    }
+
+    async fn get_block_with_txs<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block_hash_or_number: T,
+    ) -> Result<Option<Block<Transaction>>, Self::Error> {
+        let block = block_hash_or_number.into();
+        self.retry(|| self.inner.get_block_with_txs(block)).await
+    }
+
+    async fn call(
+        &self,
+        tx: &TransactionRequest,
+        block: Option<BlockNumber>,
+    ) -> Result<Bytes, Self::Error> {
+        self.retry(|| self.inner.call(tx, block)).await
+    }
+
+    async fn get_transaction<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<Transaction>, Self::Error> {
+        let hash = transaction_hash.into();
+        self.retry(|| self.inner.get_transaction(hash)).await
+    }
+
+    async fn get_transaction_receipt<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<TransactionReceipt>, Self::Error> {
+        let hash = transaction_hash.into();
+        self.retry(|| self.inner.get_transaction_receipt(hash))
+            .await
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, Self::Error> {
+        self.retry(|| self.inner.get_logs(filter)).await
+    }
+
+    async fn get_code<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        at: T,
+        block: Option<BlockNumber>,
+    ) -> Result<Bytes, Self::Error> {
+        let at = at.into();
+        self.retry(|| self.inner.get_code(at.clone(), block)).await
+    }
+
+    async fn trace_block(&self, block: BlockNumber) -> Result<Vec<Trace>, Self::Error> {
+        self.retry(|| self.inner.trace_block(block)).await
+    }
+
+    async fn trace_transaction(&self, hash: H256) -> Result<Vec<Trace>, Self::Error> {
+        self.retry(|| self.inner.trace_transaction(hash)).await
+    }
+
+    async fn parity_block_receipts<T: Into<BlockNumber> + Send + Sync>(
+        &self,
+        block: T,
+    ) -> Result<Vec<TransactionReceipt>, Self::Error> {
+        let block = block.into();
+        self.retry(|| self.inner.parity_block_receipts(block)).await
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RetryProviderError<M: Middleware> {
+    /// Thrown when the internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+    /// Thrown while too many requests failed in a row
+    #[error("too many requests failed in a row, pausing requests for another {0:?}")]
+    CircuitOpen(Duration),
+}
+
+impl<M: Middleware> FromErr<M::Error> for RetryProviderError<M> {
+    fn from(src: M::Error) -> Self {
+        RetryProviderError::MiddlewareError(src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::{MockProvider, Provider, ProviderError};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn provider() -> RetryProvider<Provider<MockProvider>> {
+        let (provider, _) = Provider::mocked();
+        RetryProvider::new(provider)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(4))
+    }
+
+    /// Fails `failures` times with `msg`, then succeeds
+    async fn flaky(calls: &AtomicU32, failures: u32, msg: &str) -> Result<u32, ProviderError> {
+        let call = calls.fetch_add(1, Ordering::SeqCst);
+        if call < failures {
+            Err(ProviderError::CustomError(msg.to_owned()))
+        } else {
+            Ok(call)
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let provider = provider();
+        let calls = AtomicU32::new(0);
+        let res = provider
+            .retry(|| flaky(&calls, 2, "error sending request for url"))
+            .await
+            .unwrap();
+        assert_eq!(res, 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn fails_on_other_errors() {
+        let provider = provider();
+        let calls = AtomicU32::new(0);
+        provider
+            .retry(|| flaky(&calls, 1, "execution reverted"))
+            .await
+            .unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let provider = provider().with_max_retries(3);
+        let calls = AtomicU32::new(0);
+        provider
+            .retry(|| flaky(&calls, 10, "429 Too Many Requests"))
+            .await
+            .unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn opens_the_circuit() {
+        let provider = provider()
+            .with_max_retries(0)
+            .with_circuit_breaker(2, Duration::from_millis(50));
+        let calls = AtomicU32::new(0);
+        for _ in 0..2 {
+            provider
+                .retry(|| flaky(&calls, 2, "operation timed out"))
+                .await
+                .unwrap_err();
+        }
+
+        // requests are not sent while the circuit is open
+        let err = provider
+            .retry(|| flaky(&calls, 2, "operation timed out"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RetryProviderError::CircuitOpen(_)));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // and are again once it cooled down
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        provider
+            .retry(|| flaky(&calls, 2, "operation timed out"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn limits_the_request_rate() {
+        let provider = provider().with_rate_limit(100);
+        let calls = AtomicU32::new(0);
+        let start = Instant::now();
+        for _ in 0..5 {
+            provider.retry(|| flaky(&calls, 0, "")).await.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn forwards_requests() {
+        let (provider, mock) = Provider::mocked();
+        let provider = RetryProvider::new(provider);
+        mock.push(U64::from(12)).unwrap();
+        assert_eq!(provider.get_block_number().await.unwrap(), 12.into());
+    }
+}
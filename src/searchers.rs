@@ -0,0 +1,400 @@
+use crate::{
+    registry::registry,
+    types::{evaluation::ActionType, Evaluation},
+};
+
+use ethers::types::{Address, TxHash};
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// An entity which extracts MEV, identified by all the EOAs, contracts and
+/// proxy implementations it was seen using together
+pub struct Searcher {
+    /// The id is kept when the searcher gets merged with a newer one
+    pub id: u64,
+    pub addresses: BTreeSet<Address>,
+    /// The number of arbitrage txs
+    pub arbitrages: u64,
+    /// The number of liquidation txs
+    pub liquidations: u64,
+}
+
+impl fmt::Display for Searcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "searcher {} ({} arbs, {} liquidations)",
+            self.id, self.arbitrages, self.liquidations
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+/// Clusters the evaluations' senders and contracts into searchers. If a path
+/// is given, the searchers are persisted there as JSON so that their ids are
+/// stable across runs.
+///
+/// Txs are only counted once: the blocks which were fully processed are
+/// kept as ranges, and only the txs of the blocks which were not are kept
+/// by hash, so that the file does not grow with every recorded tx.
+pub struct Searchers {
+    searchers: BTreeMap<u64, Searcher>,
+    by_address: HashMap<Address, u64>,
+    /// Ids of merged searchers are never handed out again
+    next_id: u64,
+    /// Sorted and non-overlapping ranges of the processed blocks
+    processed: Vec<Range<u64>>,
+    /// The recorded txs of the blocks which are not processed yet
+    pending: BTreeMap<u64, BTreeSet<TxHash>>,
+    path: Option<PathBuf>,
+}
+
+/// The JSON representation of the searchers
+#[derive(Default, Serialize, Deserialize)]
+struct SearchersFile {
+    next_id: u64,
+    searchers: Vec<Searcher>,
+    #[serde(default)]
+    processed: Vec<Range<u64>>,
+    #[serde(default)]
+    pending: BTreeMap<u64, BTreeSet<TxHash>>,
+}
+
+impl Searchers {
+    /// Instantiates an in-memory set of searchers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the searchers persisted at `path`, if any
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file: SearchersFile = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => SearchersFile::default(),
+            Err(err) => return Err(err),
+        };
+
+        let mut res = Self {
+            next_id: file.next_id,
+            processed: file.processed,
+            pending: file.pending,
+            path: Some(path),
+            ..Self::default()
+        };
+        for searcher in file.searchers {
+            for address in &searcher.addresses {
+                res.by_address.insert(*address, searcher.id);
+            }
+            res.searchers.insert(searcher.id, searcher);
+        }
+        Ok(res)
+    }
+
+    /// Writes the searchers to the path they were opened from. They are
+    /// written to a temporary file first, so that the previous ones are not
+    /// lost if writing fails midway.
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(ref path) = self.path {
+            let file = SearchersFile {
+                next_id: self.next_id,
+                searchers: self.searchers.values().cloned().collect(),
+                processed: self.processed.clone(),
+                pending: self.pending.clone(),
+            };
+            let mut tmp = path.clone().into_os_string();
+            tmp.push(".tmp");
+            std::fs::write(&tmp, serde_json::to_string_pretty(&file)?)?;
+            std::fs::rename(&tmp, path)?;
+        }
+        Ok(())
+    }
+
+    /// Marks the blocks as processed, i.e. all their txs were recorded
+    pub fn mark_processed(&mut self, blocks: Range<u64>) {
+        if blocks.is_empty() {
+            return;
+        }
+        let pending = self.pending.split_off(&blocks.start);
+        self.pending.extend(
+            pending
+                .into_iter()
+                .filter(|(block, _)| !blocks.contains(block)),
+        );
+
+        // merge the ranges which overlap or touch the new one
+        let (mut start, mut end) = (blocks.start, blocks.end);
+        self.processed.retain(|range| {
+            let disjoint = range.end < start || range.start > end;
+            if !disjoint {
+                start = start.min(range.start);
+                end = end.max(range.end);
+            }
+            disjoint
+        });
+        let idx = self.processed.partition_point(|range| range.start < start);
+        self.processed.insert(idx, start..end);
+    }
+
+    fn is_recorded(&self, block: u64, hash: &TxHash) -> bool {
+        self.processed.iter().any(|range| range.contains(&block))
+            || self
+                .pending
+                .get(&block)
+                .is_some_and(|txs| txs.contains(hash))
+    }
+
+    /// Returns the searcher which `address` belongs to
+    pub fn get(&self, address: &Address) -> Option<&Searcher> {
+        self.by_address
+            .get(address)
+            .and_then(|id| self.searchers.get(id))
+    }
+
+    /// Iterates over all the searchers, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &Searcher> {
+        self.searchers.values()
+    }
+
+    /// Attributes an arbitrage or liquidation to the searcher of its sender,
+    /// contract or proxy implementation. Searchers which turn out to share
+    /// any of these get merged into the oldest one. Txs which were already
+    /// recorded, or whose block was already processed, are not counted again.
+    pub fn record(&mut self, evaluation: &Evaluation) -> Option<&Searcher> {
+        let arbitrage = evaluation.actions.contains(&ActionType::Arbitrage);
+        let liquidation = evaluation.actions.contains(&ActionType::Liquidation);
+        if !arbitrage && !liquidation {
+            return None;
+        }
+
+        let inspection = &evaluation.inspection;
+        // the sender is always part of the searcher which recorded the tx
+        if self.is_recorded(inspection.block_number, &inspection.hash) {
+            return self.get(&inspection.from);
+        }
+        self.pending
+            .entry(inspection.block_number)
+            .or_default()
+            .insert(inspection.hash);
+
+        let mut addresses = vec![inspection.from];
+        // txs sent straight to a protocol, or through an implementation which
+        // many unrelated contracts delegate to, do not tell us anything about
+        // who sent them
+        addresses.extend(
+            std::iter::once(inspection.contract)
+                .chain(inspection.proxy_impl)
+                .filter(|address| {
+                    registry().protocol(address).is_none() && !registry().shared.contains(address)
+                }),
+        );
+
+        let ids = addresses
+            .iter()
+            .filter_map(|address| self.by_address.get(address))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let id = match ids.iter().next() {
+            Some(id) => *id,
+            None => {
+                self.next_id += 1;
+                self.next_id
+            }
+        };
+
+        let mut searcher = self.searchers.remove(&id).unwrap_or(Searcher {
+            id,
+            addresses: BTreeSet::new(),
+            arbitrages: 0,
+            liquidations: 0,
+        });
+        for other in ids.iter().skip(1) {
+            if let Some(other) = self.searchers.remove(other) {
+                searcher.addresses.extend(other.addresses);
+                searcher.arbitrages += other.arbitrages;
+                searcher.liquidations += other.liquidations;
+            }
+        }
+        searcher.addresses.extend(addresses);
+        searcher.arbitrages += arbitrage as u64;
+        searcher.liquidations += liquidation as u64;
+
+        for address in &searcher.addresses {
+            self.by_address.insert(*address, id);
+        }
+        self.searchers.insert(id, searcher);
+        self.searchers.get(&id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{addresses::parse_address, test_helpers::*};
+    use ethers::types::U256;
+    use std::collections::HashSet;
+
+    fn evaluation(from: Address, contract: Address, action: ActionType) -> Evaluation {
+        let mut inspection = mk_inspection(vec![]);
+        inspection.from = from;
+        inspection.contract = contract;
+        inspection.hash = TxHash::random();
+        let mut actions = HashSet::new();
+        actions.insert(action);
+        Evaluation {
+            inspection,
+            gas_used: U256::zero(),
+            gas_price: U256::zero(),
+            actions,
+            profit: U256::zero(),
+        }
+    }
+
+    #[test]
+    fn clusters_by_shared_addresses() {
+        let addrs = addrs();
+        let (eoa1, eoa2, bot1, bot2) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let mut searchers = Searchers::new();
+
+        let first = searchers
+            .record(&evaluation(eoa1, bot1, ActionType::Arbitrage))
+            .unwrap()
+            .id;
+        let second = searchers
+            .record(&evaluation(eoa2, bot2, ActionType::Liquidation))
+            .unwrap()
+            .id;
+        assert_ne!(first, second);
+
+        // the first EOA starts using the second bot, so they are the same
+        let searcher = searchers
+            .record(&evaluation(eoa1, bot2, ActionType::Arbitrage))
+            .unwrap()
+            .clone();
+        assert_eq!(searcher.id, first);
+        assert_eq!(searcher.to_string(), "searcher 1 (2 arbs, 1 liquidations)");
+        assert_eq!(searchers.get(&eoa2), Some(&searcher));
+        assert_eq!(searchers.iter().count(), 1);
+
+        // merged ids are not reused
+        let searcher = searchers
+            .record(&evaluation(addrs[6], addrs[7], ActionType::Arbitrage))
+            .unwrap();
+        assert_eq!(searcher.id, 3);
+
+        // plain trades are not attributed to anyone
+        assert!(searchers
+            .record(&evaluation(addrs[4], addrs[5], ActionType::Trade))
+            .is_none());
+        assert!(searchers.get(&addrs[4]).is_none());
+    }
+
+    #[test]
+    fn ignores_protocol_contracts() {
+        let addrs = addrs();
+        let router = parse_address("0x7a250d5630b4cf539739df2c5dacb4c659f2488d");
+        let mut searchers = Searchers::new();
+
+        let first = searchers
+            .record(&evaluation(addrs[0], router, ActionType::Arbitrage))
+            .unwrap()
+            .id;
+        let second = searchers
+            .record(&evaluation(addrs[1], router, ActionType::Arbitrage))
+            .unwrap()
+            .id;
+        assert_ne!(first, second);
+        assert!(searchers.get(&router).is_none());
+    }
+
+    #[test]
+    fn ignores_shared_implementations() {
+        let addrs = addrs();
+        let (eoa1, eoa2, safe1, safe2) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        // Gnosis Safe 1.3.0
+        let master_copy = parse_address("0xd9db270c1b5e3bd161e8c8503c55ceabee709552");
+        let mut searchers = Searchers::new();
+
+        let mut first = evaluation(eoa1, safe1, ActionType::Arbitrage);
+        first.inspection.proxy_impl = Some(master_copy);
+        let mut second = evaluation(eoa2, safe2, ActionType::Arbitrage);
+        second.inspection.proxy_impl = Some(master_copy);
+        let first = searchers.record(&first).unwrap().id;
+        let second = searchers.record(&second).unwrap().id;
+        assert_ne!(first, second);
+        assert!(searchers.get(&master_copy).is_none());
+        assert_eq!(searchers.get(&safe2).unwrap().id, second);
+    }
+
+    #[test]
+    fn counts_txs_once() {
+        let addrs = addrs();
+        let mut searchers = Searchers::new();
+        let arb = evaluation(addrs[0], addrs[1], ActionType::Arbitrage);
+        let liquidation = evaluation(addrs[0], addrs[1], ActionType::Liquidation);
+
+        searchers.record(&arb);
+        searchers.record(&liquidation);
+        let searcher = searchers.record(&arb).unwrap();
+        assert_eq!((searcher.arbitrages, searcher.liquidations), (1, 1));
+        let searcher = searchers.record(&liquidation).unwrap();
+        assert_eq!((searcher.arbitrages, searcher.liquidations), (1, 1));
+    }
+
+    #[test]
+    fn persists_ids() {
+        let path = std::env::temp_dir().join(format!("searchers-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let addrs = addrs();
+
+        let arb = evaluation(addrs[0], addrs[1], ActionType::Arbitrage);
+        let mut pending = evaluation(addrs[0], addrs[1], ActionType::Arbitrage);
+        pending.inspection.block_number = 1;
+        let mut searchers = Searchers::open(&path).unwrap();
+        searchers.record(&arb);
+        searchers.record(&pending);
+        searchers.record(&evaluation(addrs[2], addrs[3], ActionType::Arbitrage));
+        searchers.mark_processed(0..1);
+        searchers.save().unwrap();
+
+        let mut searchers = Searchers::open(&path).unwrap();
+        assert_eq!(searchers.get(&addrs[3]).unwrap().id, 2);
+        // re-processing a tx does not count it again, whether its block was
+        // processed or not
+        assert_eq!(searchers.record(&arb).unwrap().arbitrages, 2);
+        assert_eq!(searchers.record(&pending).unwrap().arbitrages, 2);
+        let mut liquidation = evaluation(addrs[4], addrs[1], ActionType::Liquidation);
+        liquidation.inspection.block_number = 1;
+        let searcher = searchers.record(&liquidation).unwrap();
+        assert_eq!(searcher.id, 1);
+        assert_eq!(searcher.liquidations, 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn merges_processed_blocks() {
+        let mut searchers = Searchers::new();
+        let mut arb = evaluation(addrs()[0], addrs()[1], ActionType::Arbitrage);
+        arb.inspection.block_number = 5;
+        searchers.record(&arb);
+
+        searchers.mark_processed(10..20);
+        searchers.mark_processed(0..3);
+        searchers.mark_processed(3..6);
+        assert_eq!(searchers.processed, vec![0..6, 10..20]);
+        // the txs of processed blocks are not kept around
+        assert!(searchers.pending.is_empty());
+
+        searchers.mark_processed(6..10);
+        assert_eq!(searchers.processed, vec![0..20]);
+        searchers.mark_processed(15..30);
+        assert_eq!(searchers.processed, vec![0..30]);
+    }
+}
@@ -0,0 +1,318 @@
+//! A read-only HTTP API over the stored evaluations
+//!
+//! - `GET /tx/:hash`: the evaluation of a transaction
+//! - `GET /evaluations?from=&to=&limit=`: the evaluations of the blocks in
+//!   `[from, to)`, ordered by block
+//! - `GET /searchers/top?limit=`: the EOAs which made the most profit
+//! - `GET /protocols`: the transactions and profit per protocol
+//!
+//! Evaluations are serialized the same way as by `--json` and the `.jsonl`
+//! sink.
+//!
+//! The pipeline's metrics are served separately on `GET /metrics` while
+//! inspecting, see [`crate::metrics::serve`].
+use crate::storage::{DbError, Storage};
+
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use ethers::types::TxHash;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// The number of results returned if no `limit` is given
+const DEFAULT_LIMIT: usize = 100;
+
+/// The most results a single request may return
+const MAX_LIMIT: usize = 1000;
+
+/// Returns the API's routes, which read from `db`. Each request queries a
+/// clone of it, so requests only wait on each other if its clones do, e.g.
+/// for a free connection of the pool.
+pub fn router<S: Storage + Clone + Sync + 'static>(db: S) -> Router {
+    Router::new()
+        .route("/tx/:hash", get(tx::<S>))
+        .route("/evaluations", get(evaluations::<S>))
+        .route("/searchers/top", get(top_searchers::<S>))
+        .route("/protocols", get(protocols::<S>))
+        .with_state(db)
+}
+
+/// Serves the API on `addr` until the process stops
+pub async fn serve<S: Storage + Clone + Sync + 'static>(
+    db: S,
+    addr: SocketAddr,
+) -> Result<(), axum::Error> {
+    log::info!("serving the inspections on http://{}", addr);
+    axum::Server::bind(&addr)
+        .serve(router(db).into_make_service())
+        .await
+        .map_err(axum::Error::new)
+}
+
+async fn tx<S: Storage>(
+    State(mut db): State<S>,
+    Path(hash): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let hash = hash
+        .parse::<TxHash>()
+        .map_err(|_| ApiError::BadRequest(format!("invalid transaction hash: {}", hash)))?;
+    match db.evaluation(hash).await? {
+        Some(evaluation) => Ok(Json(evaluation)),
+        // rows inserted before the evaluations were stored only have columns
+        None if db.exists(hash).await? => Err(ApiError::NotFound(format!(
+            "{:?} was stored without an evaluation, re-inspect it to store one",
+            hash
+        ))),
+        None => Err(ApiError::NotFound(format!("{:?} was not inspected", hash))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockRange {
+    from: u64,
+    to: u64,
+    limit: Option<usize>,
+}
+
+async fn evaluations<S: Storage>(
+    State(mut db): State<S>,
+    range: Result<Query<BlockRange>, QueryRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Query(range) = range?;
+    let limit = limit(range.limit)?;
+    let evaluations = db.evaluations(range.from, range.to, limit).await?;
+    Ok(Json(evaluations))
+}
+
+#[derive(Debug, Deserialize)]
+struct Limit {
+    limit: Option<usize>,
+}
+
+async fn top_searchers<S: Storage>(
+    State(mut db): State<S>,
+    query: Result<Query<Limit>, QueryRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = limit(query?.limit)?;
+    Ok(Json(db.top_searchers(limit).await?))
+}
+
+async fn protocols<S: Storage>(State(mut db): State<S>) -> Result<impl IntoResponse, ApiError> {
+    Ok(Json(db.protocol_stats().await?))
+}
+
+fn limit(limit: Option<usize>) -> Result<usize, ApiError> {
+    match limit {
+        None => Ok(DEFAULT_LIMIT),
+        Some(limit) if limit <= MAX_LIMIT => Ok(limit),
+        Some(limit) => Err(ApiError::BadRequest(format!(
+            "limit {} is above the maximum of {}",
+            limit, MAX_LIMIT
+        ))),
+    }
+}
+
+#[derive(Debug)]
+enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    Db(DbError),
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<DbError> for ApiError {
+    fn from(err: DbError) -> Self {
+        ApiError::Db(err)
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            ApiError::Db(err) => {
+                log::error!("query failed: {:?}", err);
+                // don't leak the database's internals
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "could not query the database".to_owned(),
+                )
+            }
+        };
+        (status, Json(ErrorBody { error })).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::tests::{evaluation, table},
+        types::{Evaluation, Protocol},
+        SqliteDB,
+    };
+    use ethers::types::{Address, U256};
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    /// Serves a SQLite database holding `evaluations` on a free port
+    async fn spawn(evaluations: &[Evaluation]) -> SocketAddr {
+        let mut db = SqliteDB::in_memory(table("mev_inspections")).unwrap();
+        db.create().await.unwrap();
+        db.insert_batch(evaluations).await.unwrap();
+        spawn_db(db)
+    }
+
+    fn spawn_db(db: SqliteDB) -> SocketAddr {
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(router(db).into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    /// Sends a GET request and returns the status and JSON body
+    async fn get(addr: SocketAddr, path: &str) -> (u16, Value) {
+        let (status, body) = get_text(addr, path).await;
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    /// Sends a GET request and returns the status and body
+    async fn get_text(addr: SocketAddr, path: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.0\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, body.to_owned())
+    }
+
+    fn evaluations() -> Vec<Evaluation> {
+        (1..=3u8)
+            .map(|i| {
+                let mut evaluation = evaluation(TxHash::repeat_byte(i), i as u64);
+                evaluation.inspection.from = Address::repeat_byte(i % 2);
+                evaluation.inspection.protocols = vec![Protocol::Uniswap].into_iter().collect();
+                evaluation.profit = U256::from(i) * U256::exp10(18);
+                evaluation
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn serves_transactions() {
+        let evaluations = evaluations();
+        let addr = spawn(&evaluations).await;
+
+        let (status, body) = get(addr, &format!("/tx/{:?}", TxHash::repeat_byte(2))).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, serde_json::to_value(&evaluations[1]).unwrap());
+
+        let (status, body) = get(addr, &format!("/tx/{:?}", TxHash::repeat_byte(9))).await;
+        assert_eq!(status, 404);
+        assert!(body["error"].as_str().unwrap().contains("not inspected"));
+
+        let (status, _) = get(addr, "/tx/0x1234").await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn tells_rows_without_evaluations_apart() {
+        let path = std::env::temp_dir().join(format!("serve-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut db = SqliteDB::open(&path, table("mev_inspections")).unwrap();
+        db.create().await.unwrap();
+        db.insert_batch(&evaluations()).await.unwrap();
+        // as if it was inserted before the evaluations were stored
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute(
+                "UPDATE mev_inspections SET evaluation = NULL WHERE hash = ?1",
+                [format!("{:?}", TxHash::repeat_byte(1))],
+            )
+            .unwrap();
+        let addr = spawn_db(db);
+
+        let (status, body) = get(addr, &format!("/tx/{:?}", TxHash::repeat_byte(1))).await;
+        assert_eq!(status, 404);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("stored without an evaluation"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn serves_block_ranges() {
+        let evaluations = evaluations();
+        let addr = spawn(&evaluations).await;
+
+        let (status, body) = get(addr, "/evaluations?from=2&to=10").await;
+        assert_eq!(status, 200);
+        assert_eq!(body, serde_json::to_value(&evaluations[1..]).unwrap());
+
+        let (_, body) = get(addr, "/evaluations?from=0&to=10&limit=1").await;
+        assert_eq!(body, serde_json::to_value(&evaluations[..1]).unwrap());
+
+        let (status, _) = get(addr, "/evaluations?from=0&to=10&limit=100000").await;
+        assert_eq!(status, 400);
+        let (status, body) = get(addr, "/evaluations?from=0").await;
+        assert_eq!(status, 400);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("missing field `to`"));
+    }
+
+    #[tokio::test]
+    async fn serves_aggregates() {
+        let addr = spawn(&evaluations()).await;
+
+        // txs 1 and 3 were sent by the same EOA
+        let (status, body) = get(addr, "/searchers/top?limit=1").await;
+        assert_eq!(status, 200);
+        assert_eq!(
+            body,
+            json!([{
+                "address": format!("{:?}", Address::repeat_byte(1)),
+                "transactions": 2,
+                "profit": "4000000000000000000",
+            }])
+        );
+
+        let (status, body) = get(addr, "/protocols").await;
+        assert_eq!(status, 200);
+        assert_eq!(
+            body,
+            json!([{
+                "protocol": "uniswap",
+                "transactions": 3,
+                "profit": "6000000000000000000",
+            }])
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use super::{Sink, SinkError};
+use crate::types::{actions::SpecificAction, Evaluation};
+
+use async_trait::async_trait;
+use ethers::types::U256;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::{fs::File, io::Write, path::Path};
+
+/// Writes a row per evaluation, with the same columns as the CSVs of the
+/// original TypeScript inspector (see `res/11017338.csv`)
+pub struct CsvSink<W: Write> {
+    writer: ::csv::Writer<W>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Row {
+    hash: String,
+    /// In gwei
+    gas_price: String,
+    /// In thousands of gas
+    gas_used: String,
+    calls: usize,
+    unknown_calls: usize,
+    classified_calls: usize,
+    /// The classified calls which are more than plain transfers
+    classified_actions: usize,
+    compacted_providers: String,
+    #[serde(rename = "type")]
+    kind: String,
+    status: String,
+    /// The revenue minus the tx fees, in ETH
+    profit: String,
+}
+
+impl CsvSink<File> {
+    /// Creates (or truncates) the file at `path`
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, SinkError> {
+        Ok(Self::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + Send> CsvSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: ::csv::Writer::from_writer(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> Result<W, SinkError> {
+        self.writer
+            .into_inner()
+            .map_err(|err| SinkError::Io(err.into_error()))
+    }
+}
+
+#[async_trait]
+impl<W: Write + Send> Sink for CsvSink<W> {
+    async fn write(&mut self, evaluation: &Evaluation) -> Result<(), SinkError> {
+        let inspection = &evaluation.inspection;
+        let known = inspection.known();
+        let unknown_calls = inspection.unknown().len();
+        let classified_actions = known
+            .iter()
+            .filter(|action| {
+                !matches!(
+                    action.action,
+                    SpecificAction::Transfer(_)
+                        | SpecificAction::WethDeposit(_)
+                        | SpecificAction::WethWithdrawal(_)
+                )
+            })
+            .count();
+
+        let mut protocols = inspection.protocols.iter().collect::<Vec<_>>();
+        protocols.sort();
+        let mut actions = evaluation.actions.iter().collect::<Vec<_>>();
+        actions.sort();
+
+        let cost = evaluation.gas_used * evaluation.gas_price;
+        let profit = decimal(evaluation.profit)? - decimal(cost)?;
+
+        self.writer.serialize(Row {
+            hash: format!("{:?}", inspection.hash),
+            gas_price: scaled(decimal(evaluation.gas_price)?, 9),
+            gas_used: scaled(decimal(evaluation.gas_used)?, 3)
+                .parse::<Decimal>()
+                .map(|gas| gas.round().to_string())
+                .unwrap_or_default(),
+            calls: known.len() + unknown_calls,
+            unknown_calls,
+            classified_calls: known.len(),
+            classified_actions,
+            compacted_providers: uppercase(protocols),
+            kind: uppercase(actions),
+            status: format!("{:?}", inspection.status).to_uppercase(),
+            profit: scaled(profit, 18),
+        })?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), SinkError> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+fn decimal(num: U256) -> Result<Decimal, SinkError> {
+    Decimal::from_str(&num.to_string()).map_err(|_| SinkError::Overflow(num))
+}
+
+/// Divides by 10^decimals, keeping 4 decimal places like the TS inspector
+fn scaled(num: Decimal, decimals: u32) -> String {
+    (num / Decimal::from(10u64.pow(decimals)))
+        .round_dp(4)
+        .normalize()
+        .to_string()
+}
+
+fn uppercase<T: std::fmt::Debug>(items: Vec<T>) -> String {
+    items
+        .iter()
+        .map(|item| format!("{:?}", item).to_uppercase())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sinks::tests::evaluation,
+        types::{Protocol, Status},
+    };
+
+    #[tokio::test]
+    async fn matches_ts_columns() {
+        let mut sink = CsvSink::new(Vec::new());
+        let mut eval = evaluation(1);
+        eval.inspection.status = Status::Checked;
+        eval.inspection.protocols.insert(Protocol::Uniswap);
+        eval.inspection.protocols.insert(Protocol::Balancer);
+        // 21k gas at 100.5 gwei
+        eval.gas_price = U256::from(100_500_000_000u64);
+        sink.write(&eval).await.unwrap();
+        sink.finish().await.unwrap();
+
+        let out = String::from_utf8(sink.into_inner().unwrap()).unwrap();
+        let mut lines = out.lines();
+        let expected = include_str!("../../res/11017338.csv")
+            .lines()
+            .next()
+            .unwrap();
+        assert_eq!(lines.next().unwrap(), expected);
+        // 1 ETH of revenue minus 0.0021105 ETH of fees
+        assert_eq!(
+            lines.next().unwrap(),
+            format!(
+                "{:?},100.5,21,0,0,0,0,\"UNISWAP,BALANCER\",ARBITRAGE,CHECKED,0.9979",
+                eval.inspection.hash
+            )
+        );
+    }
+}
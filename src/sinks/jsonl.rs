@@ -0,0 +1,65 @@
+use super::{Sink, SinkError};
+use crate::types::Evaluation;
+
+use async_trait::async_trait;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Writes each evaluation as a JSON object on its own line
+pub struct JsonLinesSink<W> {
+    writer: W,
+}
+
+impl JsonLinesSink<BufWriter<File>> {
+    /// Creates (or truncates) the file at `path`
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, SinkError> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[async_trait]
+impl<W: Write + Send> Sink for JsonLinesSink<W> {
+    async fn write(&mut self, evaluation: &Evaluation) -> Result<(), SinkError> {
+        serde_json::to_writer(&mut self.writer, evaluation)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), SinkError> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::tests::evaluation;
+
+    #[tokio::test]
+    async fn writes_lines() {
+        let mut sink = JsonLinesSink::new(Vec::new());
+        sink.write(&evaluation(1)).await.unwrap();
+        sink.write(&evaluation(2)).await.unwrap();
+        sink.finish().await.unwrap();
+
+        let out = String::from_utf8(sink.into_inner()).unwrap();
+        let evaluations = out
+            .lines()
+            .map(|line| serde_json::from_str::<Evaluation>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(evaluations, vec![evaluation(1), evaluation(2)]);
+    }
+}
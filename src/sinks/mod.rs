@@ -0,0 +1,560 @@
+//! Destinations which evaluations get written to
+use crate::{inspectors::BatchEvaluationError, metrics, storage::DbError, types::Evaluation};
+
+use async_trait::async_trait;
+use ethers::prelude::Middleware;
+use futures::{stream::FuturesUnordered, Future, FutureExt, Stream, StreamExt};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::time::Sleep;
+
+mod jsonl;
+pub use jsonl::JsonLinesSink;
+
+mod csv;
+pub use self::csv::CsvSink;
+
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "parquet")]
+pub use self::parquet::ParquetSink;
+
+#[async_trait]
+/// Somewhere evaluations can be written to, e.g. a database or a file
+pub trait Sink: Send {
+    /// Writes a single evaluation
+    async fn write(&mut self, evaluation: &Evaluation) -> Result<(), SinkError>;
+
+    /// Writes several evaluations at once. Databases override this to insert
+    /// them in a single transaction.
+    async fn write_batch(&mut self, evaluations: &[Evaluation]) -> Result<(), SinkError> {
+        for evaluation in evaluations {
+            self.write(evaluation).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns another handle to the same destination which can write
+    /// concurrently with this one, if the sink supports that
+    fn try_clone(&self) -> Option<Box<dyn Sink>> {
+        None
+    }
+
+    /// Flushes anything buffered
+    async fn flush(&mut self) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    /// Flushes and finalizes the output. Must be called once all evaluations
+    /// were written, since some formats only become readable once finished.
+    async fn finish(&mut self) -> Result<(), SinkError> {
+        self.flush().await
+    }
+}
+
+#[async_trait]
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    async fn write(&mut self, evaluation: &Evaluation) -> Result<(), SinkError> {
+        (**self).write(evaluation).await
+    }
+
+    async fn write_batch(&mut self, evaluations: &[Evaluation]) -> Result<(), SinkError> {
+        (**self).write_batch(evaluations).await
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn Sink>> {
+        (**self).try_clone()
+    }
+
+    async fn flush(&mut self) -> Result<(), SinkError> {
+        (**self).flush().await
+    }
+
+    async fn finish(&mut self) -> Result<(), SinkError> {
+        (**self).finish().await
+    }
+}
+
+/// Creates the file sink for `path`, picking the format from its extension
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Box<dyn Sink>, SinkError> {
+    let path = path.as_ref();
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    Ok(match extension {
+        Some("jsonl") | Some("json") => Box::new(JsonLinesSink::create(path)?),
+        Some("csv") => Box::new(CsvSink::create(path)?),
+        #[cfg(feature = "parquet")]
+        Some("parquet") => Box::new(ParquetSink::create(path)?),
+        _ => return Err(SinkError::UnknownFormat(path.to_path_buf())),
+    })
+}
+
+#[derive(Error, Debug)]
+pub enum SinkError {
+    #[error(transparent)]
+    Db(#[from] DbError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Csv(#[from] ::csv::Error),
+
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    Parquet(#[from] ::parquet::errors::ParquetError),
+
+    #[error("amount does not fit in a decimal: {0}")]
+    Overflow(ethers::types::U256),
+
+    #[error("unsupported output file (expected .jsonl, .csv or, with the `parquet` feature, .parquet): {0:?}")]
+    UnknownFormat(PathBuf),
+}
+
+/// The evaluations which were written, and those which failed along with why
+type BatchInsertion =
+    Pin<Box<dyn Future<Output = (Vec<Evaluation>, Vec<(Evaluation, SinkError)>, Box<dyn Sink>)>>>;
+
+type EvaluationStream<'a, M> =
+    Pin<Box<dyn Stream<Item = Result<Evaluation, BatchEvaluationError<M>>> + 'a>>;
+
+/// Takes a stream of `Evaluation`s and writes it to a sink in batches
+///
+/// A batch is written once `batch_size` evaluations are queued, or once the
+/// oldest queued evaluation waited for `flush_interval`. If the sink supports
+/// it, up to `connections` batches are written concurrently.
+pub struct BatchInserts<'a, M: Middleware + Unpin + 'static> {
+    /// The sinks which are not busy
+    sinks: Vec<Box<dyn Sink>>,
+    /// The currently running insert jobs
+    insertions: FuturesUnordered<BatchInsertion>,
+    /// `Evaluation`s ready to insert
+    insertion_queue: VecDeque<Evaluation>,
+    /// `Evaluation`s which got inserted but were not yielded yet
+    inserted: VecDeque<Evaluation>,
+    /// `Evaluation`s which could not be inserted and were not yielded yet
+    failed: VecDeque<(Evaluation, SinkError)>,
+    /// All the evaluations to insert
+    pending_evaluations: EvaluationStream<'a, M>,
+    /// Whether no more evaluations are coming
+    evals_done: bool,
+    /// How many evaluations to write at once
+    batch_size: usize,
+    /// How long queued evaluations may wait for the batch to fill up
+    flush_interval: Duration,
+    /// Fires once the queued evaluations waited long enough
+    flush_timer: Option<Pin<Box<Sleep>>>,
+}
+
+impl<'a, M: Middleware + Unpin + 'static> BatchInserts<'a, M> {
+    pub fn new<T, S>(sink: T, evals: S) -> Self
+    where
+        T: Sink + 'static,
+        S: Stream<Item = Result<Evaluation, BatchEvaluationError<M>>> + 'a,
+    {
+        Self {
+            sinks: vec![Box::new(sink)],
+            insertions: FuturesUnordered::new(),
+            insertion_queue: VecDeque::new(),
+            inserted: VecDeque::new(),
+            failed: VecDeque::new(),
+            pending_evaluations: Box::pin(evals),
+            evals_done: false,
+            batch_size: 100,
+            flush_interval: Duration::from_secs(1),
+            flush_timer: None,
+        }
+    }
+
+    /// Sets how many evaluations get written at once
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets how long evaluations may wait before a partial batch is written
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Writes up to `connections` batches concurrently, if the sink can be
+    /// cloned
+    pub fn with_connections(mut self, connections: usize) -> Self {
+        while self.sinks.len() < connections {
+            match self.sinks[0].try_clone() {
+                Some(sink) => self.sinks.push(sink),
+                None => break,
+            }
+        }
+        self
+    }
+
+    /// Returns the sink again
+    ///
+    /// If the sink is currently busy, this waits until the running jobs are
+    /// completed
+    pub async fn into_sink(mut self) -> Box<dyn Sink> {
+        while let Some((_, _, sink)) = self.insertions.next().await {
+            self.sinks.push(sink);
+        }
+        self.sinks.swap_remove(0)
+    }
+
+    /// Whether the queued evaluations should be written now
+    fn should_flush(&mut self, cx: &mut Context<'_>) -> bool {
+        if self.insertion_queue.is_empty() {
+            self.flush_timer = None;
+            return false;
+        }
+        if self.evals_done || self.insertion_queue.len() >= self.batch_size {
+            return true;
+        }
+        let flush_interval = self.flush_interval;
+        let timer = self
+            .flush_timer
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(flush_interval)));
+        timer.poll_unpin(cx).is_ready()
+    }
+}
+
+impl<'a, M: Middleware + Unpin> Stream for BatchInserts<'a, M> {
+    type Item = Result<Evaluation, InsertEvaluationError<M>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if !this.evals_done {
+            // queue in all evaluations that are coming in
+            loop {
+                match this.pending_evaluations.poll_next_unpin(cx) {
+                    Poll::Ready(Some(Ok(eval))) => {
+                        log::trace!(
+                            "received new evaluation of block {} with tx {}; waiting evaluations: {}",
+                            eval.inspection.block_number,
+                            eval.inspection.hash,
+                            this.insertion_queue.len() + 1
+                        );
+                        this.insertion_queue.push_back(eval);
+                        metrics::INSERTION_QUEUE.inc();
+                    }
+                    Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
+                    Poll::Ready(None) => {
+                        log::trace!("evaluations done");
+                        this.evals_done = true;
+                        break;
+                    }
+                    Poll::Pending => break,
+                }
+            }
+        }
+
+        loop {
+            // start new inserts while there are idle sinks
+            while !this.sinks.is_empty() && this.should_flush(cx) {
+                let len = this.insertion_queue.len().min(this.batch_size);
+                let batch = this.insertion_queue.drain(..len).collect::<Vec<_>>();
+                metrics::INSERTION_QUEUE.sub(batch.len() as i64);
+                log::trace!(
+                    "start next batch insert of {} evaluations, {} pending",
+                    batch.len(),
+                    this.insertion_queue.len()
+                );
+                let sink = this.sinks.pop().expect("sink is idle");
+                this.insertions
+                    .push(Box::pin(insert_evaluations(batch, sink)));
+                this.flush_timer = None;
+            }
+
+            if let Some(eval) = this.inserted.pop_front() {
+                return Poll::Ready(Some(Ok(eval)));
+            }
+            if let Some((eval, err)) = this.failed.pop_front() {
+                return Poll::Ready(Some(Err(InsertEvaluationError::Insert(
+                    Box::new(eval),
+                    err,
+                ))));
+            }
+
+            // complete the insertion tasks
+            match this.insertions.poll_next_unpin(cx) {
+                Poll::Ready(Some((inserted, failed, sink))) => {
+                    this.sinks.push(sink);
+                    this.inserted.extend(inserted);
+                    this.failed.extend(failed);
+                }
+                Poll::Ready(None) | Poll::Pending => break,
+            }
+        }
+
+        // If more evaluations and insertions are processed we're not done yet
+        if this.evals_done && this.insertion_queue.is_empty() && this.insertions.is_empty() {
+            log::trace!("batch insert done");
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let insertions = self.insertion_queue.len() + self.inserted.len() + self.failed.len();
+        let (evals, _) = self.pending_evaluations.size_hint();
+        (insertions + evals, None)
+    }
+}
+
+/// Writes the evaluations in a single batch. If that fails they are written
+/// one by one, so that only the evaluations which cannot be written are lost.
+async fn insert_evaluations(
+    evals: Vec<Evaluation>,
+    mut sink: Box<dyn Sink>,
+) -> (Vec<Evaluation>, Vec<(Evaluation, SinkError)>, Box<dyn Sink>) {
+    let timer = metrics::INSERT_DURATION.start_timer();
+    let res = sink.write_batch(&evals).await;
+    timer.observe_duration();
+    let (inserted, failed) = match res {
+        Ok(()) => (evals, Vec::new()),
+        Err(err) if evals.len() == 1 => {
+            (Vec::new(), vec![(evals.into_iter().next().unwrap(), err)])
+        }
+        Err(err) => {
+            log::warn!(
+                "insert of {} evaluations failed, inserting them one by one: {:?}",
+                evals.len(),
+                err
+            );
+            let mut inserted = Vec::new();
+            let mut failed = Vec::new();
+            for eval in evals {
+                match sink.write(&eval).await {
+                    Ok(()) => inserted.push(eval),
+                    Err(err) => failed.push((eval, err)),
+                }
+            }
+            (inserted, failed)
+        }
+    };
+
+    for (eval, err) in &failed {
+        log::error!(
+            "insert of tx 0x{} in block {} failed: {:?}",
+            eval.inspection.hash,
+            eval.inspection.block_number,
+            err
+        );
+    }
+    if !inserted.is_empty() {
+        log::debug!(
+            "inserted {} evaluations of blocks {:?}",
+            inserted.len(),
+            inserted
+                .iter()
+                .map(|eval| eval.inspection.block_number)
+                .collect::<BTreeSet<_>>()
+        );
+        metrics::INSERTED.inc_by(inserted.len() as u64);
+    }
+    (inserted, failed, sink)
+}
+
+impl<'a, M: Middleware + Unpin + 'static> Drop for BatchInserts<'a, M> {
+    fn drop(&mut self) {
+        // the evaluations which are dropped won't be inserted anymore
+        metrics::INSERTION_QUEUE.sub(self.insertion_queue.len() as i64);
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum InsertEvaluationError<M: Middleware + 'static> {
+    #[error(transparent)]
+    SinkError(#[from] SinkError),
+
+    /// The evaluation which could not be written
+    #[error("could not insert tx 0x{} of block {}: {}", .0.inspection.hash, .0.inspection.block_number, .1)]
+    Insert(Box<Evaluation>, #[source] SinkError),
+
+    #[error(transparent)]
+    BatchEvaluationError(#[from] BatchEvaluationError<M>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helpers::*, types::evaluation::ActionType};
+    use ethers::{
+        providers::{Http, Provider},
+        types::U256,
+    };
+    use std::sync::{Arc, Mutex};
+
+    /// Collects the evaluations in memory, along with the size of each batch
+    #[derive(Default, Clone)]
+    struct VecSink(Arc<Mutex<Vec<Evaluation>>>, Arc<Mutex<Vec<usize>>>);
+
+    #[async_trait]
+    impl Sink for VecSink {
+        async fn write(&mut self, evaluation: &Evaluation) -> Result<(), SinkError> {
+            self.0.lock().unwrap().push(evaluation.clone());
+            Ok(())
+        }
+
+        async fn write_batch(&mut self, evaluations: &[Evaluation]) -> Result<(), SinkError> {
+            self.1.lock().unwrap().push(evaluations.len());
+            self.0.lock().unwrap().extend(evaluations.iter().cloned());
+            Ok(())
+        }
+
+        fn try_clone(&self) -> Option<Box<dyn Sink>> {
+            Some(Box::new(self.clone()))
+        }
+    }
+
+    /// Fails to write the evaluations of one block, and any batch with them
+    #[derive(Clone)]
+    struct FailingSink(VecSink, u64);
+
+    #[async_trait]
+    impl Sink for FailingSink {
+        async fn write(&mut self, evaluation: &Evaluation) -> Result<(), SinkError> {
+            if evaluation.inspection.block_number == self.1 {
+                return Err(SinkError::Overflow(U256::MAX));
+            }
+            self.0.write(evaluation).await
+        }
+
+        async fn write_batch(&mut self, evaluations: &[Evaluation]) -> Result<(), SinkError> {
+            if evaluations
+                .iter()
+                .any(|eval| eval.inspection.block_number == self.1)
+            {
+                return Err(SinkError::Overflow(U256::MAX));
+            }
+            self.0.write_batch(evaluations).await
+        }
+    }
+
+    pub(crate) fn evaluation(block_number: u64) -> Evaluation {
+        let mut inspection = mk_inspection(vec![]);
+        inspection.block_number = block_number;
+        Evaluation {
+            inspection,
+            gas_used: 21000.into(),
+            gas_price: U256::exp10(11),
+            actions: vec![ActionType::Arbitrage].into_iter().collect(),
+            profit: U256::exp10(18),
+        }
+    }
+
+    #[tokio::test]
+    async fn inserts_all_evaluations() {
+        let sink = VecSink::default();
+        let evals = (0..5).map(|i| Ok(evaluation(i))).collect::<Vec<_>>();
+        let inserts =
+            BatchInserts::<Provider<Http>>::new(sink.clone(), futures::stream::iter(evals));
+
+        let inserted = inserts.collect::<Vec<_>>().await;
+        assert_eq!(inserted.len(), 5);
+        let written = sink.0.lock().unwrap();
+        assert_eq!(
+            written
+                .iter()
+                .map(|eval| eval.inspection.block_number)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4]
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_in_batches() {
+        let sink = VecSink::default();
+        let evals = (0..5).map(|i| Ok(evaluation(i))).collect::<Vec<_>>();
+        let inserts =
+            BatchInserts::<Provider<Http>>::new(sink.clone(), futures::stream::iter(evals))
+                .with_batch_size(2);
+
+        assert_eq!(inserts.collect::<Vec<_>>().await.len(), 5);
+        assert_eq!(*sink.1.lock().unwrap(), vec![2, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn flushes_partial_batches_after_interval() {
+        let sink = VecSink::default();
+        let (mut tx, rx) = futures::channel::mpsc::unbounded();
+        let mut inserts = BatchInserts::<Provider<Http>>::new(sink.clone(), rx)
+            .with_flush_interval(Duration::from_millis(10));
+
+        tx.start_send(Ok(evaluation(1))).unwrap();
+        // the batch is not full and the stream still open
+        let eval = tokio::time::timeout(Duration::from_secs(5), inserts.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(eval.inspection.block_number, 1);
+        assert_eq!(*sink.1.lock().unwrap(), vec![1]);
+
+        drop(tx);
+        assert!(inserts.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn writes_concurrently() {
+        let sink = VecSink::default();
+        let evals = (0..10).map(|i| Ok(evaluation(i))).collect::<Vec<_>>();
+        let inserts =
+            BatchInserts::<Provider<Http>>::new(sink.clone(), futures::stream::iter(evals))
+                .with_batch_size(3)
+                .with_connections(4);
+        assert_eq!(inserts.sinks.len(), 4);
+
+        let mut inserted = inserts
+            .map(|res| res.unwrap().inspection.block_number)
+            .collect::<Vec<_>>()
+            .await;
+        inserted.sort_unstable();
+        assert_eq!(inserted, (0..10).collect::<Vec<_>>());
+        assert_eq!(sink.0.lock().unwrap().len(), 10);
+    }
+
+    #[tokio::test]
+    async fn inserts_failed_batches_one_by_one() {
+        let sink = VecSink::default();
+        let evals = (0..5).map(|i| Ok(evaluation(i))).collect::<Vec<_>>();
+        let inserts = BatchInserts::<Provider<Http>>::new(
+            FailingSink(sink.clone(), 2),
+            futures::stream::iter(evals),
+        )
+        .with_batch_size(5);
+
+        let (inserted, failed): (Vec<_>, Vec<_>) = inserts
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .partition(|res| res.is_ok());
+        assert_eq!(inserted.len(), 4);
+        assert_eq!(failed.len(), 1);
+        match &failed[0] {
+            Err(InsertEvaluationError::Insert(eval, _)) => {
+                assert_eq!(eval.inspection.block_number, 2)
+            }
+            _ => panic!("expected the failed evaluation"),
+        }
+        assert_eq!(sink.0.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        assert!(matches!(
+            open(std::env::temp_dir().join("evaluations.txt")),
+            Err(SinkError::UnknownFormat(_))
+        ));
+    }
+}
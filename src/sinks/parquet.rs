@@ -0,0 +1,214 @@
+use super::{Sink, SinkError};
+use crate::{storage::vec_str, types::Evaluation};
+
+use ::parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use async_trait::async_trait;
+use std::{fs::File, io::Write, path::Path, sync::Arc};
+
+/// The same columns as the Postgres table
+const SCHEMA: &str = "
+message evaluation {
+    required binary hash (UTF8);
+    required binary status (UTF8);
+    required int64 block_number;
+    required binary gas_price (UTF8);
+    required binary gas_used (UTF8);
+    required binary revenue (UTF8);
+    repeated binary protocols (UTF8);
+    repeated binary actions (UTF8);
+    required binary eoa (UTF8);
+    required binary contract (UTF8);
+    optional binary proxy_impl (UTF8);
+}
+";
+
+/// How many evaluations are buffered before being written as a row group
+const ROW_GROUP_SIZE: usize = 10_000;
+
+/// Writes the evaluations to a Parquet file. Amounts are decimal strings since
+/// they do not fit in any of Parquet's integer types.
+pub struct ParquetSink<W: Write + Send> {
+    writer: Option<SerializedFileWriter<W>>,
+    rows: Vec<Evaluation>,
+}
+
+impl ParquetSink<File> {
+    /// Creates (or truncates) the file at `path`
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, SinkError> {
+        Self::new(File::create(path)?)
+    }
+}
+
+impl<W: Write + Send> ParquetSink<W> {
+    pub fn new(writer: W) -> Result<Self, SinkError> {
+        let schema = Arc::new(parse_message_type(SCHEMA)?);
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        Ok(Self {
+            writer: Some(SerializedFileWriter::new(writer, schema, Arc::new(props))?),
+            rows: Vec::new(),
+        })
+    }
+
+    fn write_row_group(&mut self) -> Result<(), SinkError> {
+        let writer = match self.writer {
+            Some(ref mut writer) if !self.rows.is_empty() => writer,
+            _ => return Ok(()),
+        };
+        let rows = std::mem::take(&mut self.rows);
+
+        let mut row_group = writer.next_row_group()?;
+        let mut idx = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            if idx == 2 {
+                let values = rows
+                    .iter()
+                    .map(|eval| eval.inspection.block_number as i64)
+                    .collect::<Vec<_>>();
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, None, None)?;
+            } else {
+                let (values, def, rep) = strings(&rows, idx);
+                column.typed::<ByteArrayType>().write_batch(
+                    &values,
+                    def.as_deref(),
+                    rep.as_deref(),
+                )?;
+            }
+            column.close()?;
+            idx += 1;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+}
+
+/// Returns the values of the `idx`th string column along with its definition
+/// and repetition levels
+#[allow(clippy::type_complexity)]
+fn strings(
+    rows: &[Evaluation],
+    idx: usize,
+) -> (Vec<ByteArray>, Option<Vec<i16>>, Option<Vec<i16>>) {
+    let required = |f: &dyn Fn(&Evaluation) -> String| {
+        let values = rows.iter().map(|eval| f(eval).as_str().into()).collect();
+        (values, None, None)
+    };
+    let repeated = |f: &dyn Fn(&Evaluation) -> Vec<String>| {
+        let (mut values, mut def, mut rep) = (Vec::new(), Vec::new(), Vec::new());
+        for eval in rows {
+            let items = f(eval);
+            if items.is_empty() {
+                def.push(0);
+                rep.push(0);
+            }
+            for (i, item) in items.into_iter().enumerate() {
+                values.push(item.as_str().into());
+                def.push(1);
+                rep.push((i > 0) as i16);
+            }
+        }
+        (values, Some(def), Some(rep))
+    };
+
+    match idx {
+        0 => required(&|eval| format!("{:?}", eval.inspection.hash)),
+        1 => required(&|eval| format!("{:?}", eval.inspection.status)),
+        3 => required(&|eval| eval.gas_price.to_string()),
+        4 => required(&|eval| eval.gas_used.to_string()),
+        5 => required(&|eval| eval.profit.to_string()),
+        6 => repeated(&|eval| vec_str(&eval.inspection.protocols)),
+        7 => repeated(&|eval| vec_str(&eval.actions)),
+        8 => required(&|eval| format!("{:?}", eval.inspection.from)),
+        9 => required(&|eval| format!("{:?}", eval.inspection.contract)),
+        10 => {
+            let values = rows
+                .iter()
+                .filter_map(|eval| eval.inspection.proxy_impl)
+                .map(|proxy| format!("{:?}", proxy).as_str().into())
+                .collect();
+            let def = rows
+                .iter()
+                .map(|eval| eval.inspection.proxy_impl.is_some() as i16)
+                .collect();
+            (values, Some(def), None)
+        }
+        _ => unreachable!("unknown column {}", idx),
+    }
+}
+
+#[async_trait]
+impl<W: Write + Send> Sink for ParquetSink<W> {
+    async fn write(&mut self, evaluation: &Evaluation) -> Result<(), SinkError> {
+        self.rows.push(evaluation.clone());
+        if self.rows.len() >= ROW_GROUP_SIZE {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered evaluations as a row group. Note that the file is
+    /// only readable once finished.
+    async fn flush(&mut self) -> Result<(), SinkError> {
+        self.write_row_group()
+    }
+
+    async fn finish(&mut self) -> Result<(), SinkError> {
+        self.write_row_group()?;
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sinks::tests::evaluation, types::Protocol};
+    use ::parquet::{
+        file::{reader::FileReader, serialized_reader::SerializedFileReader},
+        record::{ListAccessor, RowAccessor},
+    };
+    use ethers::types::Address;
+
+    #[tokio::test]
+    async fn writes_readable_file() {
+        let path = std::env::temp_dir().join(format!("evaluations-{}.parquet", std::process::id()));
+        let mut sink = ParquetSink::create(&path).unwrap();
+        let mut first = evaluation(1);
+        first.inspection.protocols.insert(Protocol::Uniswap);
+        first.inspection.protocols.insert(Protocol::Curve);
+        let mut second = evaluation(2);
+        second.inspection.proxy_impl = Some(Address::repeat_byte(1));
+        sink.write(&first).await.unwrap();
+        sink.write(&second).await.unwrap();
+        sink.finish().await.unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let rows = reader
+            .get_row_iter(None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_long(2).unwrap(), 1);
+        assert_eq!(rows[0].get_string(5).unwrap(), "1000000000000000000");
+        assert_eq!(rows[1].get_long(2).unwrap(), 2);
+        assert_eq!(
+            rows[1].get_string(10).unwrap(),
+            &format!("{:?}", Address::repeat_byte(1))
+        );
+        let protocols = rows[0].get_list(6).unwrap();
+        assert_eq!(protocols.len(), 2);
+        assert_eq!(protocols.get_string(0).unwrap(), "curve");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
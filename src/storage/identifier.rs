@@ -0,0 +1,155 @@
+//! Names of tables, which are always quoted when interpolated into SQL so
+//! that they can't be used to inject statements
+use super::DbError;
+
+use std::{fmt, str::FromStr};
+
+/// Postgres truncates longer identifiers
+const MAX_LEN: usize = 63;
+
+/// A validated SQL identifier, which is displayed quoted and escaped
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Identifier(String);
+
+impl Identifier {
+    /// Validates the identifier, which must be non-empty, at most 63 bytes
+    /// and without NUL characters. Any other character is fine since the
+    /// identifier always gets quoted.
+    pub fn new(name: impl Into<String>) -> Result<Self, DbError> {
+        let name = name.into();
+        if name.is_empty() || name.len() > MAX_LEN || name.contains('\0') {
+            return Err(DbError::InvalidIdentifier(name));
+        }
+        Ok(Self(name))
+    }
+
+    /// The identifier as it was given, unquoted
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `{self}_{suffix}`, e.g. for naming an index after its table.
+    /// Fails if that gets longer than 63 bytes, since Postgres would truncate
+    /// it to a name which may clash with another one.
+    pub fn suffixed(&self, suffix: &str) -> Result<Self, DbError> {
+        Self::new(format!("{}_{}", self.0, suffix))
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.0.replace('"', "\"\""))
+    }
+}
+
+impl FromStr for Identifier {
+    type Err = DbError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+/// A table, optionally in a schema, which is displayed quoted and escaped
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Table {
+    schema: Option<Identifier>,
+    name: Identifier,
+}
+
+impl Table {
+    pub fn new(name: Identifier) -> Self {
+        Self { schema: None, name }
+    }
+
+    /// Places the table in the schema
+    pub fn with_schema(mut self, schema: Identifier) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    pub fn schema(&self) -> Option<&Identifier> {
+        self.schema.as_ref()
+    }
+
+    pub fn name(&self) -> &Identifier {
+        &self.name
+    }
+
+    /// Returns the table named `{name}_{suffix}` in the same schema
+    pub fn suffixed(&self, suffix: &str) -> Result<Self, DbError> {
+        Ok(Self {
+            schema: self.schema.clone(),
+            name: self.name.suffixed(suffix)?,
+        })
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref schema) = self.schema {
+            write!(f, "{}.", schema)?;
+        }
+        write!(f, "{}", self.name)
+    }
+}
+
+impl From<Identifier> for Table {
+    fn from(name: Identifier) -> Self {
+        Self::new(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_identifiers() {
+        let ident = Identifier::new("mev_inspections").unwrap();
+        assert_eq!(ident.to_string(), r#""mev_inspections""#);
+        assert_eq!(
+            ident.suffixed("block_number").unwrap().to_string(),
+            r#""mev_inspections_block_number""#
+        );
+
+        let ident = Identifier::new(r#"x"; DROP TABLE users; --"#).unwrap();
+        assert_eq!(ident.to_string(), r#""x""; DROP TABLE users; --""#);
+        assert_eq!(ident.as_str(), r#"x"; DROP TABLE users; --"#);
+    }
+
+    #[test]
+    fn rejects_invalid_identifiers() {
+        assert!(Identifier::new("").is_err());
+        assert!(Identifier::new("a\0b").is_err());
+        assert!(Identifier::new("a".repeat(64)).is_err());
+        assert!(Identifier::new("a".repeat(63)).is_ok());
+        assert!("".parse::<Identifier>().is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_suffixes() {
+        // `_schema_versions` is 16 bytes long
+        let ident = Identifier::new("a".repeat(47)).unwrap();
+        assert_eq!(
+            ident.suffixed("schema_versions").unwrap().as_str().len(),
+            63
+        );
+        let ident = Identifier::new("a".repeat(48)).unwrap();
+        assert!(ident.suffixed("schema_versions").is_err());
+        assert!(Table::new(ident).suffixed("schema_versions").is_err());
+    }
+
+    #[test]
+    fn qualifies_tables() {
+        let table = Table::new("mev_inspections".parse().unwrap());
+        assert_eq!(table.to_string(), r#""mev_inspections""#);
+
+        let table = table.with_schema("Team A".parse().unwrap());
+        assert_eq!(table.to_string(), r#""Team A"."mev_inspections""#);
+        assert_eq!(
+            table.suffixed("schema_versions").unwrap().to_string(),
+            r#""Team A"."mev_inspections_schema_versions""#
+        );
+    }
+}
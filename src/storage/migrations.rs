@@ -0,0 +1,143 @@
+//! The schema of the inspections table, as an ordered list of migrations.
+//!
+//! Migrations are only ever appended to this list: once released, a migration
+//! must not be edited, since the deployments which already applied it will not
+//! run it again.
+use super::{DbError, Table};
+
+/// A single, versioned change to the schema
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    postgres: fn(&Table) -> Result<String, DbError>,
+    sqlite: fn(&Table) -> Result<String, DbError>,
+}
+
+impl Migration {
+    /// The Postgres statements of this migration for `table`
+    pub fn postgres(&self, table: &Table) -> Result<String, DbError> {
+        (self.postgres)(table)
+    }
+
+    /// The SQLite statements of this migration for `table`
+    pub fn sqlite(&self, table: &Table) -> Result<String, DbError> {
+        (self.sqlite)(table)
+    }
+}
+
+/// All the migrations, in the order they are applied
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create the inspections table",
+        // `IF NOT EXISTS` so that tables created before migrations existed
+        // are picked up as they are
+        postgres: |table| {
+            Ok(format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    hash text PRIMARY KEY,
+                    status text,
+
+                    block_number NUMERIC,
+                    gas_price NUMERIC,
+                    gas_used NUMERIC,
+                    revenue NUMERIC,
+
+                    protocols text[],
+                    actions text[],
+
+                    eoa text,
+                    contract text,
+                    proxy_impl text,
+
+                    inserted_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+                )",
+                table
+            ))
+        },
+        sqlite: |table| {
+            Ok(format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    hash TEXT PRIMARY KEY,
+                    status TEXT,
+
+                    block_number INTEGER,
+                    gas_price TEXT,
+                    gas_used TEXT,
+                    revenue TEXT,
+
+                    protocols TEXT,
+                    actions TEXT,
+
+                    eoa TEXT,
+                    contract TEXT,
+                    proxy_impl TEXT,
+
+                    inserted_at TEXT DEFAULT CURRENT_TIMESTAMP
+                )",
+                table
+            ))
+        },
+    },
+    Migration {
+        version: 2,
+        description: "index the block numbers",
+        // indexes are always created in the schema of their table
+        postgres: |table| {
+            Ok(format!(
+                "CREATE INDEX IF NOT EXISTS {} ON {} (block_number)",
+                table.name().suffixed("block_number")?,
+                table
+            ))
+        },
+        sqlite: |table| {
+            Ok(format!(
+                "CREATE INDEX IF NOT EXISTS {} ON {} (block_number)",
+                table.name().suffixed("block_number")?,
+                table
+            ))
+        },
+    },
+    Migration {
+        version: 3,
+        description: "store the serialized evaluations",
+        postgres: |table| {
+            Ok(format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS evaluation jsonb",
+                table
+            ))
+        },
+        sqlite: |table| Ok(format!("ALTER TABLE {} ADD COLUMN evaluation TEXT", table)),
+    },
+];
+
+/// The table which records the applied migrations of `table`. Its name is
+/// the longest one derived from `table`, so this fails first for tables whose
+/// names are too long.
+pub fn versions_table(table: &Table) -> Result<Table, DbError> {
+    table.suffixed("schema_versions")
+}
+
+/// The migrations which are not in `applied`, in order
+pub fn pending(applied: &[u32]) -> impl Iterator<Item = &'static Migration> + '_ {
+    MIGRATIONS
+        .iter()
+        .filter(move |migration| !applied.contains(&migration.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_ordered() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, i + 1);
+        }
+        assert_eq!(
+            pending(&[1]).map(|m| m.version).collect::<Vec<_>>(),
+            (2..=MIGRATIONS.len() as u32).collect::<Vec<_>>()
+        );
+    }
+}
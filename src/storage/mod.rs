@@ -0,0 +1,334 @@
+use crate::types::{serialization::decimal, Evaluation};
+
+use async_trait::async_trait;
+use ethers::types::{Address, TxHash, U256};
+use serde::Serialize;
+use thiserror::Error;
+
+mod identifier;
+pub use identifier::{Identifier, Table};
+
+pub mod migrations;
+
+mod postgres;
+pub use postgres::MevDB;
+
+mod sqlite;
+pub use sqlite::SqliteDB;
+
+#[async_trait]
+/// A database which the evaluations are stored in
+pub trait Storage: Send {
+    /// Creates the table for the MEV data or brings its schema up to date
+    async fn create(&mut self) -> Result<(), DbError> {
+        let applied = self.migrate().await?;
+        if !applied.is_empty() {
+            log::info!("applied migrations {:?}", applied);
+        }
+        Ok(())
+    }
+
+    /// Applies the pending migrations in order, each in its own transaction,
+    /// and returns their versions
+    async fn migrate(&mut self) -> Result<Vec<u32>, DbError>;
+
+    /// Returns the versions of the migrations which were applied
+    async fn applied_migrations(&mut self) -> Result<Vec<u32>, DbError>;
+
+    /// Inserts the evaluation, ignoring it if its tx was already inserted
+    async fn insert(&mut self, evaluation: &Evaluation) -> Result<(), DbError>;
+
+    /// Inserts all the evaluations at once, ignoring the txs which were
+    /// already inserted. Either all or none of them get inserted.
+    async fn insert_batch(&mut self, evaluations: &[Evaluation]) -> Result<(), DbError>;
+
+    /// Checks if the transaction hash is already inspected
+    async fn exists(&mut self, hash: TxHash) -> Result<bool, DbError>;
+
+    /// Checks if the provided block has been inspected
+    async fn block_exists(&mut self, block: u64) -> Result<bool, DbError>;
+
+    /// Drops the table along with all of its data and schema versions
+    async fn clear(&mut self) -> Result<(), DbError>;
+
+    /// Returns the evaluation of the transaction, if it was inserted
+    async fn evaluation(&mut self, hash: TxHash) -> Result<Option<Evaluation>, DbError>;
+
+    /// Returns up to `limit` evaluations of the blocks in `[from, to)`, ordered
+    /// by block
+    async fn evaluations(
+        &mut self,
+        from: u64,
+        to: u64,
+        limit: usize,
+    ) -> Result<Vec<Evaluation>, DbError>;
+
+    /// Returns the `limit` EOAs which made the most profit
+    async fn top_searchers(&mut self, limit: usize) -> Result<Vec<SearcherProfit>, DbError>;
+
+    /// Returns the transactions and profit of each protocol, most profitable
+    /// first. A transaction counts towards all the protocols it used.
+    async fn protocol_stats(&mut self) -> Result<Vec<ProtocolStats>, DbError>;
+}
+
+/// The transactions sent by a searcher's EOA and the profit they made
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearcherProfit {
+    pub address: Address,
+    pub transactions: u64,
+    #[serde(with = "decimal")]
+    pub profit: U256,
+}
+
+/// The transactions which used a protocol and the profit they made
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProtocolStats {
+    /// The protocol's lowercase name, as stored in the database
+    pub protocol: String,
+    pub transactions: u64,
+    #[serde(with = "decimal")]
+    pub profit: U256,
+}
+
+#[derive(Error, Debug)]
+pub enum DbError {
+    #[error(transparent)]
+    Decimal(#[from] rust_decimal::Error),
+
+    #[error("could not convert u64 to decimal")]
+    InvalidDecimal,
+
+    #[error(transparent)]
+    TokioPostGres(#[from] tokio_postgres::Error),
+
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error(transparent)]
+    Pool(#[from] deadpool_postgres::PoolError),
+
+    #[error(transparent)]
+    BuildPool(#[from] deadpool_postgres::BuildError),
+
+    #[cfg(feature = "tls")]
+    #[error(transparent)]
+    Tls(#[from] native_tls::Error),
+
+    #[error("the database requires TLS, which needs building with `--features tls`")]
+    TlsDisabled,
+
+    #[error("invalid identifier (expected 1 to 63 bytes without NUL): {0:?}")]
+    InvalidIdentifier(String),
+
+    #[error("could not convert the stored amount {0:?} to u256")]
+    InvalidAmount(String),
+
+    #[error("could not parse the stored address {0:?}")]
+    InvalidAddress(String),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid {0}: {1}")]
+    InvalidEnv(&'static str, String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+// helpers
+pub(crate) fn parse_amount(amount: &str) -> Result<U256, DbError> {
+    U256::from_dec_str(amount).map_err(|_| DbError::InvalidAmount(amount.to_owned()))
+}
+
+pub(crate) fn parse_address(address: &str) -> Result<Address, DbError> {
+    address
+        .parse()
+        .map_err(|_| DbError::InvalidAddress(address.to_owned()))
+}
+
+/// Orders by profit, highest first, and then by `key`
+pub(crate) fn by_profit<T, K: Ord>(
+    items: &mut [T],
+    profit: impl Fn(&T) -> U256,
+    key: impl Fn(&T) -> K,
+) {
+    items.sort_by(|a, b| profit(b).cmp(&profit(a)).then_with(|| key(a).cmp(&key(b))));
+}
+
+pub(crate) fn vec_str<T: std::fmt::Debug, I: IntoIterator<Item = T>>(t: I) -> Vec<String> {
+    let mut items = t
+        .into_iter()
+        .map(|i| format!("{:?}", i).to_lowercase())
+        .collect::<Vec<_>>();
+    items.sort();
+    items
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::types::{evaluation::ActionType, Inspection, Protocol, Status};
+    use ethers::types::Address;
+    use std::collections::HashSet;
+
+    pub fn table(name: &str) -> Table {
+        Table::new(name.parse().unwrap())
+    }
+
+    pub fn evaluation(hash: TxHash, block_number: u64) -> Evaluation {
+        let inspection = Inspection {
+            status: Status::Checked,
+            actions: Vec::new(),
+            protocols: HashSet::new(),
+            from: Address::zero(),
+            contract: Address::zero(),
+            proxy_impl: None,
+            hash,
+            block_number,
+        };
+        let actions = [ActionType::Liquidation, ActionType::Arbitrage]
+            .iter()
+            .cloned()
+            .collect::<HashSet<_>>();
+        Evaluation {
+            inspection,
+            gas_used: 21000.into(),
+            gas_price: (100e9 as u64).into(),
+            actions,
+            profit: (1e18 as u64).into(),
+        }
+    }
+
+    /// The behavior every storage backend must have
+    pub async fn check_storage<S: Storage>(db: &mut S) {
+        let _ = db.clear().await;
+        db.create().await.unwrap();
+        // creating twice is fine
+        db.create().await.unwrap();
+
+        let evaluation = evaluation(TxHash::repeat_byte(1), 9);
+        assert!(!db.exists(evaluation.as_ref().hash).await.unwrap());
+        assert!(!db.block_exists(9).await.unwrap());
+
+        db.insert(&evaluation).await.unwrap();
+        assert!(db.exists(evaluation.as_ref().hash).await.unwrap());
+        assert!(!db.exists(TxHash::repeat_byte(2)).await.unwrap());
+        assert!(db.block_exists(9).await.unwrap());
+        assert!(!db.block_exists(10).await.unwrap());
+
+        // conflicts get ignored
+        db.insert(&evaluation).await.unwrap();
+
+        // batches may contain already inserted txs
+        let batch = (10..13)
+            .map(|i| self::evaluation(TxHash::repeat_byte(i), i as u64))
+            .chain(std::iter::once(evaluation.clone()))
+            .collect::<Vec<_>>();
+        db.insert_batch(&batch).await.unwrap();
+        db.insert_batch(&[]).await.unwrap();
+        for i in 10..13 {
+            assert!(db.exists(TxHash::repeat_byte(i)).await.unwrap());
+            assert!(db.block_exists(i as u64).await.unwrap());
+        }
+
+        db.clear().await.unwrap();
+        db.create().await.unwrap();
+        assert!(!db.exists(evaluation.as_ref().hash).await.unwrap());
+    }
+
+    /// Reading back evaluations and aggregating them
+    pub async fn check_queries<S: Storage>(db: &mut S) {
+        let _ = db.clear().await;
+        db.create().await.unwrap();
+
+        let alice = Address::repeat_byte(0xa);
+        let bob = Address::repeat_byte(0xb);
+        let txs = [
+            (1, alice, vec![Protocol::Uniswap], 5u64),
+            (2, alice, vec![Protocol::Uniswap, Protocol::Aave], 7),
+            (3, bob, vec![Protocol::Sushiswap], 20),
+            (3, bob, vec![], 1),
+        ];
+        let evaluations = txs
+            .iter()
+            .enumerate()
+            .map(|(i, (block, from, protocols, profit))| {
+                let mut evaluation = evaluation(TxHash::repeat_byte(i as u8 + 1), *block);
+                evaluation.inspection.from = *from;
+                evaluation.inspection.protocols = protocols.iter().cloned().collect();
+                evaluation.profit = U256::from(*profit) * U256::exp10(18);
+                evaluation
+            })
+            .collect::<Vec<_>>();
+        db.insert_batch(&evaluations).await.unwrap();
+
+        assert_eq!(
+            db.evaluation(TxHash::repeat_byte(2)).await.unwrap(),
+            Some(evaluations[1].clone())
+        );
+        assert_eq!(db.evaluation(TxHash::repeat_byte(9)).await.unwrap(), None);
+
+        assert_eq!(
+            db.evaluations(2, 4, 10).await.unwrap(),
+            evaluations[1..].to_vec()
+        );
+        assert_eq!(
+            db.evaluations(1, 4, 2).await.unwrap(),
+            evaluations[..2].to_vec()
+        );
+        assert!(db.evaluations(4, 10, 10).await.unwrap().is_empty());
+
+        let searchers = db.top_searchers(10).await.unwrap();
+        assert_eq!(
+            searchers,
+            vec![
+                SearcherProfit {
+                    address: bob,
+                    transactions: 2,
+                    profit: U256::from(21) * U256::exp10(18),
+                },
+                SearcherProfit {
+                    address: alice,
+                    transactions: 2,
+                    profit: U256::from(12) * U256::exp10(18),
+                },
+            ]
+        );
+        assert_eq!(db.top_searchers(1).await.unwrap(), searchers[..1].to_vec());
+
+        let protocols = db
+            .protocol_stats()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|stats| {
+                let eth = stats.profit / U256::exp10(18);
+                (stats.protocol, stats.transactions, eth.as_u64())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            protocols,
+            vec![
+                ("sushiswap".to_owned(), 1, 20),
+                ("uniswap".to_owned(), 2, 12),
+                ("aave".to_owned(), 1, 7),
+            ]
+        );
+        db.clear().await.unwrap();
+    }
+
+    /// Migrations are applied once, in order
+    pub async fn check_migrations<S: Storage>(db: &mut S) {
+        let _ = db.clear().await;
+        let all = migrations::MIGRATIONS
+            .iter()
+            .map(|migration| migration.version)
+            .collect::<Vec<_>>();
+        assert!(db.applied_migrations().await.unwrap().is_empty());
+        assert_eq!(db.migrate().await.unwrap(), all);
+        assert!(db.migrate().await.unwrap().is_empty());
+        assert_eq!(db.applied_migrations().await.unwrap(), all);
+        db.clear().await.unwrap();
+    }
+}
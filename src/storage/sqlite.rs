@@ -0,0 +1,418 @@
+use super::{
+    by_profit, migrations, parse_address, parse_amount, vec_str, DbError, ProtocolStats,
+    SearcherProfit, Storage, Table,
+};
+use crate::{
+    sinks::{Sink, SinkError},
+    types::Evaluation,
+};
+
+use async_trait::async_trait;
+use ethers::types::{TxHash, U256};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// An embedded SQLite database with the same table as the Postgres one. The
+/// amounts are stored as decimal strings since they do not fit in an
+/// INTEGER, and the protocols / actions as JSON arrays. For the same reason,
+/// profits are summed up outside of SQLite.
+///
+/// Clones share the same connection, so their queries run one at a time.
+#[derive(Clone)]
+pub struct SqliteDB {
+    conn: Arc<Mutex<Connection>>,
+    table: Table,
+}
+
+impl SqliteDB {
+    /// Opens (or creates) the database file at `path`
+    pub fn open<P: AsRef<Path>>(path: P, table: Table) -> Result<Self, DbError> {
+        Ok(Self {
+            conn: Arc::new(Mutex::new(Connection::open(path)?)),
+            table,
+        })
+    }
+
+    /// Opens a database which only lives in memory
+    pub fn in_memory(table: Table) -> Result<Self, DbError> {
+        Ok(Self {
+            conn: Arc::new(Mutex::new(Connection::open_in_memory()?)),
+            table,
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // a panic while querying does not leave the connection in a bad state
+        self.conn.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteDB {
+    async fn migrate(&mut self) -> Result<Vec<u32>, DbError> {
+        let applied = self.applied_migrations().await?;
+        let versions = migrations::versions_table(&self.table)?;
+
+        let mut res = Vec::new();
+        for migration in migrations::pending(&applied) {
+            log::debug!(
+                "applying migration {}: {}",
+                migration.version,
+                migration.description
+            );
+            let mut conn = self.conn();
+            let tx = conn.transaction()?;
+            tx.execute_batch(&migration.sqlite(&self.table)?)?;
+            tx.execute(
+                &format!(
+                    "INSERT INTO {} (version, description) VALUES (?1, ?2)",
+                    versions
+                ),
+                params![migration.version, migration.description],
+            )?;
+            tx.commit()?;
+            res.push(migration.version);
+        }
+        Ok(res)
+    }
+
+    async fn applied_migrations(&mut self) -> Result<Vec<u32>, DbError> {
+        let versions = migrations::versions_table(&self.table)?;
+        let conn = self.conn();
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                version INTEGER PRIMARY KEY,
+                description TEXT,
+                applied_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            versions
+        ))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT version FROM {} ORDER BY version",
+            versions
+        ))?;
+        let rows = stmt.query_map(params![], |row| row.get::<_, u32>(0))?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    async fn insert(&mut self, evaluation: &Evaluation) -> Result<(), DbError> {
+        insert_with(&self.conn(), &self.table, evaluation)
+    }
+
+    async fn insert_batch(&mut self, evaluations: &[Evaluation]) -> Result<(), DbError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        for evaluation in evaluations {
+            insert_with(&tx, &self.table, evaluation)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn exists(&mut self, hash: TxHash) -> Result<bool, DbError> {
+        let row = self
+            .conn()
+            .query_row(
+                &format!("SELECT hash FROM {} WHERE hash = ?1", self.table),
+                params![format!("{:?}", hash)],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(row.is_some())
+    }
+
+    async fn block_exists(&mut self, block: u64) -> Result<bool, DbError> {
+        let row = self
+            .conn()
+            .query_row(
+                &format!(
+                    "SELECT block_number FROM {} WHERE block_number = ?1 LIMIT 1",
+                    self.table
+                ),
+                params![block as i64],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        Ok(row.is_some())
+    }
+
+    async fn clear(&mut self) -> Result<(), DbError> {
+        self.conn().execute_batch(&format!(
+            "DROP TABLE {}; DROP TABLE IF EXISTS {}",
+            self.table,
+            migrations::versions_table(&self.table)?
+        ))?;
+        Ok(())
+    }
+
+    async fn evaluation(&mut self, hash: TxHash) -> Result<Option<Evaluation>, DbError> {
+        let evaluation = self
+            .conn()
+            .query_row(
+                &format!(
+                    "SELECT evaluation FROM {} WHERE hash = ?1 AND evaluation IS NOT NULL",
+                    self.table
+                ),
+                params![format!("{:?}", hash)],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(evaluation
+            .map(|evaluation| serde_json::from_str(&evaluation))
+            .transpose()?)
+    }
+
+    async fn evaluations(
+        &mut self,
+        from: u64,
+        to: u64,
+        limit: usize,
+    ) -> Result<Vec<Evaluation>, DbError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT evaluation FROM {}
+            WHERE block_number >= ?1 AND block_number < ?2 AND evaluation IS NOT NULL
+            ORDER BY block_number, hash
+            LIMIT ?3",
+            self.table
+        ))?;
+        let rows = stmt.query_map(params![from as i64, to as i64, limit as i64], |row| {
+            row.get::<_, String>(0)
+        })?;
+        rows.map(|evaluation| Ok(serde_json::from_str(&evaluation?)?))
+            .collect()
+    }
+
+    /// Scans the whole table, since the profits are summed up outside of
+    /// SQLite, so this gets slow as the table grows
+    async fn top_searchers(&mut self, limit: usize) -> Result<Vec<SearcherProfit>, DbError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!("SELECT eoa, revenue FROM {}", self.table))?;
+        let rows = stmt.query_map(params![], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut searchers = HashMap::new();
+        for row in rows {
+            let (eoa, revenue) = row?;
+            let searcher = searchers.entry(eoa).or_insert((0, U256::zero()));
+            searcher.0 += 1;
+            searcher.1 = searcher.1.saturating_add(parse_amount(&revenue)?);
+        }
+
+        let mut searchers = searchers
+            .into_iter()
+            .map(|(eoa, (transactions, profit))| {
+                Ok(SearcherProfit {
+                    address: parse_address(&eoa)?,
+                    transactions,
+                    profit,
+                })
+            })
+            .collect::<Result<Vec<_>, DbError>>()?;
+        by_profit(&mut searchers, |s| s.profit, |s| s.address);
+        searchers.truncate(limit);
+        Ok(searchers)
+    }
+
+    /// Scans the whole table, like `top_searchers`
+    async fn protocol_stats(&mut self) -> Result<Vec<ProtocolStats>, DbError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!("SELECT protocols, revenue FROM {}", self.table))?;
+        let rows = stmt.query_map(params![], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut protocols = HashMap::new();
+        for row in rows {
+            let (names, revenue) = row?;
+            let revenue = parse_amount(&revenue)?;
+            for name in serde_json::from_str::<Vec<String>>(&names)? {
+                let protocol = protocols.entry(name).or_insert((0, U256::zero()));
+                protocol.0 += 1;
+                protocol.1 = protocol.1.saturating_add(revenue);
+            }
+        }
+
+        let mut protocols = protocols
+            .into_iter()
+            .map(|(protocol, (transactions, profit))| ProtocolStats {
+                protocol,
+                transactions,
+                profit,
+            })
+            .collect::<Vec<_>>();
+        by_profit(&mut protocols, |p| p.profit, |p| p.protocol.clone());
+        Ok(protocols)
+    }
+}
+
+#[async_trait]
+impl Sink for SqliteDB {
+    async fn write(&mut self, evaluation: &Evaluation) -> Result<(), SinkError> {
+        Ok(self.insert(evaluation).await?)
+    }
+
+    async fn write_batch(&mut self, evaluations: &[Evaluation]) -> Result<(), SinkError> {
+        Ok(self.insert_batch(evaluations).await?)
+    }
+}
+
+/// Inserts the evaluation through `conn`, which may be a transaction
+fn insert_with(conn: &Connection, table: &Table, evaluation: &Evaluation) -> Result<(), DbError> {
+    let inspection = &evaluation.inspection;
+    let mut stmt = conn.prepare_cached(&format!(
+        "INSERT OR IGNORE INTO {} (
+            hash,
+            status,
+            block_number,
+            gas_price,
+            gas_used,
+            revenue,
+            protocols,
+            actions,
+            eoa,
+            contract,
+            proxy_impl,
+            evaluation
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        table
+    ))?;
+    stmt.execute(params![
+        format!("{:?}", inspection.hash),
+        format!("{:?}", inspection.status),
+        inspection.block_number as i64,
+        evaluation.gas_price.to_string(),
+        evaluation.gas_used.to_string(),
+        evaluation.profit.to_string(),
+        serde_json::to_string(&vec_str(&inspection.protocols)).unwrap_or_default(),
+        serde_json::to_string(&vec_str(&evaluation.actions)).unwrap_or_default(),
+        format!("{:?}", inspection.from),
+        format!("{:?}", inspection.contract),
+        inspection
+            .proxy_impl
+            .map(|x| format!("{:?}", x))
+            .unwrap_or_else(|| "".to_owned()),
+        serde_json::to_string(evaluation)?,
+    ])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::{
+        check_migrations, check_queries, check_storage, evaluation, table,
+    };
+
+    #[tokio::test]
+    async fn storage() {
+        check_storage(&mut SqliteDB::in_memory(table("mev_inspections")).unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn queries() {
+        check_queries(&mut SqliteDB::in_memory(table("mev_inspections")).unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn migrations() {
+        check_migrations(&mut SqliteDB::in_memory(table("mev_inspections")).unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn clones_share_the_connection() {
+        let mut db = SqliteDB::in_memory(table("mev_inspections")).unwrap();
+        db.create().await.unwrap();
+        let mut clone = db.clone();
+        clone
+            .insert(&evaluation(TxHash::repeat_byte(1), 1))
+            .await
+            .unwrap();
+        assert!(db.exists(TxHash::repeat_byte(1)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn adopts_tables_from_before_migrations() {
+        let mut db = SqliteDB::in_memory(table("mev_inspections")).unwrap();
+        db.conn()
+            .execute_batch(
+                &migrations::MIGRATIONS[0]
+                    .sqlite(&table("mev_inspections"))
+                    .unwrap(),
+            )
+            .unwrap();
+        db.conn()
+            .execute(
+                "INSERT INTO mev_inspections (hash, block_number, revenue, eoa, protocols)
+                VALUES (?1, 1, '5', ?2, '[]')",
+                params![
+                    format!("{:?}", TxHash::repeat_byte(1)),
+                    format!("{:?}", ethers::types::Address::zero())
+                ],
+            )
+            .unwrap();
+
+        assert!(db.applied_migrations().await.unwrap().is_empty());
+        db.create().await.unwrap();
+        assert_eq!(
+            db.applied_migrations().await.unwrap().len(),
+            migrations::MIGRATIONS.len()
+        );
+        // the data survived, but can only be aggregated since the evaluation
+        // was not stored back then
+        assert!(db.exists(TxHash::repeat_byte(1)).await.unwrap());
+        assert_eq!(db.evaluation(TxHash::repeat_byte(1)).await.unwrap(), None);
+        assert_eq!(db.top_searchers(1).await.unwrap()[0].profit, 5.into());
+    }
+
+    #[tokio::test]
+    async fn quotes_the_table_name() {
+        let name = r#"mev"; DROP TABLE "other"; --"#;
+        let mut db = SqliteDB::in_memory(table(name)).unwrap();
+        db.conn().execute_batch("CREATE TABLE other (x)").unwrap();
+        check_storage(&mut db).await;
+
+        let tables = db
+            .conn()
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap()
+            .query_map(params![], |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(tables.contains(&"other".to_owned()));
+        db.create().await.unwrap();
+        assert!(db.exists(TxHash::repeat_byte(1)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn persists_to_file() {
+        let path = std::env::temp_dir().join(format!("mev-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let hash = TxHash::repeat_byte(3);
+
+        let mut db = SqliteDB::open(&path, table("mev_inspections")).unwrap();
+        db.create().await.unwrap();
+        db.insert(&evaluation(hash, 1)).await.unwrap();
+        drop(db);
+
+        let mut db = SqliteDB::open(&path, table("mev_inspections")).unwrap();
+        assert!(db.exists(hash).await.unwrap());
+        let (revenue, actions): (String, String) = db
+            .conn()
+            .query_row(
+                "SELECT revenue, actions FROM mev_inspections",
+                params![],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(revenue, "1000000000000000000");
+        assert_eq!(actions, r#"["arbitrage","liquidation"]"#);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,5 +1,11 @@
-use crate::types::{inspection::TraceWrapper, Classification, Inspection, Status};
-use ethers::types::{Address, Trace, TxHash};
+use crate::types::{
+    classification::CallTrace, inspection::TraceWrapper, Classification, Inspection, Status,
+};
+use ethers::{
+    abi::parse_abi,
+    contract::BaseContract,
+    types::{Address, Bytes, Call, CallType, Trace, TxHash, U256},
+};
 use once_cell::sync::Lazy;
 use std::{collections::HashSet, convert::TryInto};
 
@@ -9,7 +15,6 @@ pub static TRACES: Lazy<Vec<Trace>> = Lazy::new(|| serde_json::from_str(TRACE).u
 pub fn addrs() -> Vec<Address> {
     use ethers::core::rand::thread_rng;
     (0..10)
-        .into_iter()
         .map(|_| ethers::signers::LocalWallet::new(&mut thread_rng()).address())
         .collect()
 }
@@ -27,6 +32,40 @@ pub fn mk_inspection(actions: Vec<Classification>) -> Inspection {
     }
 }
 
+/// Builds an unclassified call, for hand-crafted inspections of txs we have no
+/// trace fixture for
+pub fn mk_call<T: Into<U256>>(
+    from: Address,
+    to: Address,
+    value: T,
+    input: Bytes,
+    trace_address: Vec<usize>,
+) -> Classification {
+    CallTrace {
+        call: Call {
+            from,
+            to,
+            value: value.into(),
+            gas: 100_000.into(),
+            input,
+            call_type: CallType::Call,
+        },
+        trace_address,
+    }
+    .into()
+}
+
+/// ERC20 abi for encoding the token transfers of hand-crafted inspections
+pub fn erc20() -> BaseContract {
+    BaseContract::from(
+        parse_abi(&[
+            "function transferFrom(address, address, uint256)",
+            "function transfer(address, uint256)",
+        ])
+        .unwrap(),
+    )
+}
+
 pub fn read_trace(path: &str) -> Inspection {
     let input = std::fs::read_to_string(format!("res/{}", path)).unwrap();
     let traces: Vec<Trace> = serde_json::from_str(&input).unwrap();
@@ -34,11 +73,7 @@ pub fn read_trace(path: &str) -> Inspection {
 }
 
 pub fn get_trace(hash: &str) -> Inspection {
-    let hash = if hash.starts_with("0x") {
-        &hash[2..]
-    } else {
-        hash
-    };
+    let hash = hash.strip_prefix("0x").unwrap_or(hash);
 
     TraceWrapper(
         TRACES
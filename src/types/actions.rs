@@ -1,11 +1,15 @@
-use crate::addresses::lookup;
+use crate::{
+    addresses::lookup,
+    types::serialization::{decimal, decimal_vec},
+};
 
 use ethers::types::{Address, Bytes, U256};
 
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 // https://github.com/flashbots/mev-inspect/blob/master/src/types.ts#L65-L87
-#[derive(Debug, Clone, PartialOrd, PartialEq)]
+#[derive(Debug, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 /// The types of actions
 pub enum SpecificAction {
     WethDeposit(Deposit),
@@ -14,9 +18,13 @@ pub enum SpecificAction {
     Transfer(Transfer),
     Trade(Trade),
     Liquidation(Liquidation),
+    FlashLoan(FlashLoan),
+    Redemption(Redemption),
 
     AddLiquidity(AddLiquidity),
 
+    AggregatorSwap(AggregatorSwap),
+
     Arbitrage(Arbitrage),
     ProfitableLiquidation(ProfitableLiquidation),
 
@@ -25,9 +33,10 @@ pub enum SpecificAction {
     LiquidationCheck,
 }
 
-#[derive(Debug, Clone, PartialOrd, PartialEq)]
+#[derive(Debug, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct AddLiquidity {
     pub tokens: Vec<Address>,
+    #[serde(with = "decimal_vec")]
     pub amounts: Vec<U256>,
 }
 
@@ -38,6 +47,25 @@ impl From<AddLiquidity> for SpecificAction {
 }
 
 impl SpecificAction {
+    /// The name of this kind of action
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SpecificAction::WethDeposit(_) => "WethDeposit",
+            SpecificAction::WethWithdrawal(_) => "WethWithdrawal",
+            SpecificAction::Transfer(_) => "Transfer",
+            SpecificAction::Trade(_) => "Trade",
+            SpecificAction::Liquidation(_) => "Liquidation",
+            SpecificAction::FlashLoan(_) => "FlashLoan",
+            SpecificAction::Redemption(_) => "Redemption",
+            SpecificAction::AddLiquidity(_) => "AddLiquidity",
+            SpecificAction::AggregatorSwap(_) => "AggregatorSwap",
+            SpecificAction::Arbitrage(_) => "Arbitrage",
+            SpecificAction::ProfitableLiquidation(_) => "ProfitableLiquidation",
+            SpecificAction::Unclassified(_) => "Unclassified",
+            SpecificAction::LiquidationCheck => "LiquidationCheck",
+        }
+    }
+
     pub fn deposit(&self) -> Option<&Deposit> {
         match self {
             SpecificAction::WethDeposit(inner) => Some(inner),
@@ -88,6 +116,20 @@ impl SpecificAction {
         }
     }
 
+    pub fn flash_loan(&self) -> Option<&FlashLoan> {
+        match self {
+            SpecificAction::FlashLoan(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    pub fn redemption(&self) -> Option<&Redemption> {
+        match self {
+            SpecificAction::Redemption(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
     pub fn profitable_liquidation(&self) -> Option<&ProfitableLiquidation> {
         match self {
             SpecificAction::ProfitableLiquidation(inner) => Some(inner),
@@ -101,13 +143,21 @@ impl SpecificAction {
             _ => None,
         }
     }
+
+    pub fn aggregator_swap(&self) -> Option<&AggregatorSwap> {
+        match self {
+            SpecificAction::AggregatorSwap(inner) => Some(inner),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Clone, PartialOrd, PartialEq)]
+#[derive(Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 /// A token transfer
 pub struct Transfer {
     pub from: Address,
     pub to: Address,
+    #[serde(with = "decimal")]
     pub amount: U256,
     pub token: Address,
 }
@@ -132,9 +182,10 @@ impl fmt::Debug for Transfer {
     }
 }
 
-#[derive(Clone, PartialOrd, PartialEq)]
+#[derive(Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct Deposit {
     pub from: Address,
+    #[serde(with = "decimal")]
     pub amount: U256,
 }
 
@@ -153,9 +204,10 @@ impl fmt::Debug for Deposit {
     }
 }
 
-#[derive(Clone, PartialOrd, PartialEq)]
+#[derive(Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct Withdrawal {
     pub to: Address,
+    #[serde(with = "decimal")]
     pub amount: U256,
 }
 
@@ -174,7 +226,7 @@ impl fmt::Debug for Withdrawal {
     }
 }
 
-#[derive(Debug, Clone, PartialOrd, PartialEq)]
+#[derive(Debug, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct Trade {
     pub t1: Transfer,
     pub t2: Transfer,
@@ -197,8 +249,45 @@ impl Trade {
     }
 }
 
-#[derive(Clone, PartialOrd, PartialEq)]
+#[derive(Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
+/// The swap intent of a call to an aggregator router, as given in its calldata.
+/// The actual trades are the router's subtraces.
+pub struct AggregatorSwap {
+    pub token_in: Address,
+    #[serde(with = "decimal")]
+    pub amount_in: U256,
+
+    pub token_out: Address,
+    /// The minimum amount the caller accepts, i.e. their slippage tolerance
+    #[serde(with = "decimal")]
+    pub min_amount_out: U256,
+
+    pub from: Address,
+    pub recipient: Address,
+}
+
+impl From<AggregatorSwap> for SpecificAction {
+    fn from(src: AggregatorSwap) -> Self {
+        SpecificAction::AggregatorSwap(src)
+    }
+}
+
+impl fmt::Debug for AggregatorSwap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AggregatorSwap")
+            .field("token_in", &lookup(self.token_in))
+            .field("amount_in", &self.amount_in)
+            .field("token_out", &lookup(self.token_out))
+            .field("min_amount_out", &self.min_amount_out)
+            .field("from", &lookup(self.from))
+            .field("recipient", &lookup(self.recipient))
+            .finish()
+    }
+}
+
+#[derive(Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct Arbitrage {
+    #[serde(with = "decimal")]
     pub profit: U256,
     pub token: Address,
     pub to: Address,
@@ -220,12 +309,14 @@ impl fmt::Debug for Arbitrage {
     }
 }
 
-#[derive(Default, Clone, PartialOrd, PartialEq)]
+#[derive(Default, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct Liquidation {
     pub sent_token: Address,
+    #[serde(with = "decimal")]
     pub sent_amount: U256,
 
     pub received_token: Address,
+    #[serde(with = "decimal")]
     pub received_amount: U256,
 
     pub from: Address,
@@ -251,9 +342,72 @@ impl fmt::Debug for Liquidation {
     }
 }
 
-#[derive(Clone, PartialOrd, PartialEq)]
+#[derive(Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
+/// A flash loan of one or more assets, which must be repaid within the call
+pub struct FlashLoan {
+    pub assets: Vec<Address>,
+    #[serde(with = "decimal_vec")]
+    pub amounts: Vec<U256>,
+
+    /// The contract which receives the assets and is called back
+    pub receiver: Address,
+    pub from: Address,
+}
+
+impl From<FlashLoan> for SpecificAction {
+    fn from(src: FlashLoan) -> Self {
+        SpecificAction::FlashLoan(src)
+    }
+}
+
+impl fmt::Debug for FlashLoan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let assets = self.assets.iter().cloned().map(lookup).collect::<Vec<_>>();
+        f.debug_struct("FlashLoan")
+            .field("assets", &assets)
+            .field("amounts", &self.amounts)
+            .field("receiver", &lookup(self.receiver))
+            .field("from", &lookup(self.from))
+            .finish()
+    }
+}
+
+#[derive(Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
+/// A redemption of a stablecoin for collateral at face value
+pub struct Redemption {
+    pub sent_token: Address,
+    #[serde(with = "decimal")]
+    pub sent_amount: U256,
+
+    pub received_token: Address,
+    #[serde(with = "decimal")]
+    pub received_amount: U256,
+
+    pub from: Address,
+}
+
+impl From<Redemption> for SpecificAction {
+    fn from(src: Redemption) -> Self {
+        SpecificAction::Redemption(src)
+    }
+}
+
+impl fmt::Debug for Redemption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redemption")
+            .field("sent_token", &lookup(self.sent_token))
+            .field("sent_amount", &self.sent_amount)
+            .field("received_token", &lookup(self.received_token))
+            .field("received_amount", &self.received_amount)
+            .field("from", &lookup(self.from))
+            .finish()
+    }
+}
+
+#[derive(Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct ProfitableLiquidation {
     pub liquidation: Liquidation,
+    #[serde(with = "decimal")]
     pub profit: U256,
     pub token: Address,
 }
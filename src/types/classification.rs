@@ -1,15 +1,20 @@
-use crate::{addresses::lookup, is_subtrace, types::actions::SpecificAction};
+use crate::{
+    addresses::lookup,
+    is_subtrace,
+    types::{actions::SpecificAction, serialization::CallDef},
+};
 use ethers::types::Call;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum Classification {
     Known(ActionTrace),
     Unknown(CallTrace),
     Prune,
 }
 
-#[derive(Debug, Clone, PartialOrd, PartialEq)]
+#[derive(Debug, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct ActionTrace {
     pub action: SpecificAction,
     pub trace_address: Vec<usize>,
@@ -27,8 +32,9 @@ impl From<ActionTrace> for Classification {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CallTrace {
+    #[serde(with = "CallDef")]
     pub call: Call,
     pub trace_address: Vec<usize>,
 }
@@ -107,7 +113,7 @@ impl Classification {
 
     pub fn as_call(&self) -> Option<&CallTrace> {
         match self {
-            Classification::Unknown(ref inner) => Some(&inner),
+            Classification::Unknown(ref inner) => Some(inner),
             _ => None,
         }
     }
@@ -1,5 +1,9 @@
 use crate::{
-    types::{actions::SpecificAction, Inspection, Status},
+    types::{
+        actions::SpecificAction,
+        serialization::{decimal, sorted},
+        Inspection, Status,
+    },
     HistoricalPrice,
 };
 
@@ -8,28 +12,34 @@ use ethers::{
     providers::Middleware,
     types::{TxHash, U256},
 };
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
 use thiserror::Error;
 
-#[derive(Debug, Clone, PartialOrd, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialOrd, PartialEq, Eq, Ord, Hash, Serialize, Deserialize)]
 pub enum ActionType {
     Liquidation,
     Arbitrage,
     Trade,
+    Redemption,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Evaluation {
     /// The internal inspection which produced this evaluation
     pub inspection: Inspection,
     /// The gas used in total by this transaction
+    #[serde(with = "decimal")]
     pub gas_used: U256,
     /// The gas price used in this transaction
+    #[serde(with = "decimal")]
     pub gas_price: U256,
     /// The actions involved
+    #[serde(serialize_with = "sorted")]
     pub actions: HashSet<ActionType>,
     /// The money made by this transfer
+    #[serde(with = "decimal")]
     pub profit: U256,
 }
 
@@ -42,15 +52,12 @@ impl AsRef<Inspection> for Evaluation {
 impl Evaluation {
     /// Takes an inspection and reduces it to the data format which will be pushed
     /// to the database.
-    pub async fn new<T: Middleware>(
+    pub async fn new<T: Middleware + 'static>(
         inspection: Inspection,
         prices: &HistoricalPrice<T>,
         gas_used: U256,
         gas_price: U256,
-    ) -> Result<Self, EvalError<T>>
-    where
-        T: 'static,
-    {
+    ) -> Result<Self, EvalError<T>> {
         // TODO: Figure out how to sum up liquidations & arbs while pruning
         // aggressively
         // TODO: If an Inspection is CHECKED and contains >1 trading protocol,
@@ -74,9 +81,12 @@ impl Evaluation {
                 Liquidation(_) | ProfitableLiquidation(_) | LiquidationCheck => {
                     actions.insert(ActionType::Liquidation);
                 }
-                Trade(_) => {
+                Trade(_) | AggregatorSwap(_) => {
                     actions.insert(ActionType::Trade);
                 }
+                Redemption(_) => {
+                    actions.insert(ActionType::Redemption);
+                }
                 _ => {}
             };
 
@@ -86,13 +96,11 @@ impl Evaluation {
             }
 
             match action {
-                SpecificAction::Arbitrage(arb) => {
-                    if arb.profit > 0.into() {
-                        profit += prices
-                            .quote(arb.token, arb.profit, inspection.block_number)
-                            .await
-                            .map_err(EvalError::Contract)?;
-                    }
+                SpecificAction::Arbitrage(arb) if arb.profit > 0.into() => {
+                    profit += prices
+                        .quote(arb.token, arb.profit, inspection.block_number)
+                        .await
+                        .map_err(EvalError::Contract)?;
                 }
                 SpecificAction::Liquidation(liq) => {
                     if liq.sent_amount == U256::MAX {
@@ -130,6 +138,28 @@ impl Evaluation {
                         );
                     }
                 }
+                SpecificAction::Redemption(redemption) => {
+                    let res = futures::future::join(
+                        prices.quote(
+                            redemption.sent_token,
+                            redemption.sent_amount,
+                            inspection.block_number,
+                        ),
+                        prices.quote(
+                            redemption.received_token,
+                            redemption.received_amount,
+                            inspection.block_number,
+                        ),
+                    )
+                    .await;
+
+                    match res {
+                        (Ok(amount_in), Ok(amount_out)) => {
+                            profit += amount_out.saturating_sub(amount_in);
+                        }
+                        _ => println!("Could not fetch prices from Uniswap"),
+                    };
+                }
                 SpecificAction::ProfitableLiquidation(liq) => {
                     profit += prices
                         .quote(liq.token, liq.profit, inspection.block_number)
@@ -163,3 +193,14 @@ where
     #[error(transparent)]
     Contract(ContractError<M>),
 }
+
+impl<M: Middleware> EvalError<M> {
+    /// The name of this kind of error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EvalError::Provider(_) => "provider",
+            EvalError::TxNotFound(_) => "tx_not_found",
+            EvalError::Contract(_) => "contract",
+        }
+    }
+}
@@ -1,14 +1,16 @@
 use crate::{
-    addresses::{DYDX, FILTER, ZEROX},
+    addresses::{DYDX, ZEROX},
     types::{
         classification::{ActionTrace, CallTrace},
-        Classification, Protocol, Status,
+        serialization::sorted,
+        Classification, Protocol, Status, Summary,
     },
 };
 use ethers::types::{Action, Address, CallType, Trace, TxHash};
+use serde::{Deserialize, Serialize};
 use std::{collections::HashSet, convert::TryFrom};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// The result of an inspection of a trace along with its inspected subtraces
 pub struct Inspection {
     /// Success / failure
@@ -20,6 +22,7 @@ pub struct Inspection {
 
     ///// Where
     /// All the involved protocols
+    #[serde(serialize_with = "sorted")]
     pub protocols: HashSet<Protocol>,
 
     // Who
@@ -60,10 +63,13 @@ impl Inspection {
             .collect();
     }
 
-    /// Returns: types of protocols, types of actions (arb, liq), bot addresses and profit
+    /// Returns: types of protocols, types of actions (arb, liq), the involved
+    /// addresses and the tokens that flowed in and out of the sender and its contracts
     /// Bots that perform liq/arbs maybe for a profit that are not in the addressbook should be
     /// added
-    pub fn summary(&self) {}
+    pub fn summary(&self) -> Summary {
+        Summary::new(self)
+    }
 
     /// Returns all the successfully classified calls in this Inspection
     pub fn known(&self) -> Vec<ActionTrace> {
@@ -110,11 +116,6 @@ impl<T: IntoIterator<Item = Trace>> TryFrom<TraceWrapper<T>> for Inspection {
             _ => return Err(()),
         };
 
-        // Filter out unwanted calls
-        if FILTER.get(&call.to).is_some() {
-            return Err(());
-        }
-
         let mut inspection = Inspection {
             status: Status::Success,
             // all unclassified calls
@@ -129,7 +130,6 @@ impl<T: IntoIterator<Item = Trace>> TryFrom<TraceWrapper<T>> for Inspection {
         };
 
         inspection.actions = traces
-            .into_iter()
             .filter_map(|trace| {
                 // Revert if all subtraces revert? There are counterexamples
                 // e.g. when a low-level trace's revert is handled
@@ -10,7 +10,14 @@ pub use classification::Classification;
 pub(crate) mod inspection;
 pub use inspection::Inspection;
 
-#[derive(Debug, Clone, PartialOrd, PartialEq)]
+pub(crate) mod serialization;
+
+pub mod summary;
+pub use summary::Summary;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub enum Status {
     /// When a transaction reverts without touching any DeFi protocol
     Reverted,
@@ -20,7 +27,7 @@ pub enum Status {
     Success,
 }
 
-#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq, Ord, Hash, Serialize, Deserialize)]
 /// The supported protocols
 pub enum Protocol {
     // Uniswap & Forks
@@ -33,15 +40,59 @@ pub enum Protocol {
     // Other AMMs
     Curve,
     Balancer,
+    Kyber,
+    Bancor,
+    Synthetix,
 
     // Lending / Liquidations
     Aave,
+    AaveV2,
     Compound,
+    Cream,
+    IronBank,
+    Maker,
+    Liquity,
 
     // Aggregators
     ZeroEx,
+    OneInch,
+    Paraswap,
 
     // Misc.
     Flashloan,
     DyDx,
 }
+
+impl std::str::FromStr for Protocol {
+    type Err = String;
+
+    /// Parses a protocol from its name, as it is printed with `Debug`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Protocol::*;
+        Ok(match s {
+            "UniswapV1" => UniswapV1,
+            "Uniswap" => Uniswap,
+            "Uniswappy" => Uniswappy,
+            "Sushiswap" => Sushiswap,
+            "SakeSwap" => SakeSwap,
+            "Curve" => Curve,
+            "Balancer" => Balancer,
+            "Kyber" => Kyber,
+            "Bancor" => Bancor,
+            "Synthetix" => Synthetix,
+            "Aave" => Aave,
+            "AaveV2" => AaveV2,
+            "Compound" => Compound,
+            "Cream" => Cream,
+            "IronBank" => IronBank,
+            "Maker" => Maker,
+            "Liquity" => Liquity,
+            "ZeroEx" => ZeroEx,
+            "OneInch" => OneInch,
+            "Paraswap" => Paraswap,
+            "Flashloan" => Flashloan,
+            "DyDx" => DyDx,
+            _ => return Err(format!("unknown protocol: {}", s)),
+        })
+    }
+}
@@ -0,0 +1,199 @@
+//! Serde helpers so that amounts are written as decimal strings, which
+//! unlike JSON numbers do not lose precision, and unordered collections are
+//! written in a stable order
+use ethers::types::{Address, Bytes, Call, CallType, U256};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::HashSet, fmt::Display, hash::Hash};
+
+/// (De)serializes a `U256` as a decimal string
+pub mod decimal {
+    use super::*;
+
+    pub fn serialize<T: Display, S: Serializer>(num: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(num)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        U256::from_dec_str(&s).map_err(|_| de::Error::custom(format!("invalid amount: {}", s)))
+    }
+}
+
+/// (De)serializes a `Vec<U256>` as a list of decimal strings
+pub mod decimal_vec {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Decimal(#[serde(with = "decimal")] U256);
+
+    pub fn serialize<S: Serializer>(nums: &[U256], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(nums.iter().map(|num| Decimal(*num)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<U256>, D::Error> {
+        let nums = Vec::<Decimal>::deserialize(deserializer)?;
+        Ok(nums.into_iter().map(|num| num.0).collect())
+    }
+}
+
+/// Serializes a `HashSet` in ascending order
+pub fn sorted<T: Ord + Hash + Serialize, S: Serializer>(
+    set: &HashSet<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut items = set.iter().collect::<Vec<_>>();
+    items.sort();
+    serializer.collect_seq(items)
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Call")]
+/// A trace's call with its value and gas as decimal strings
+pub struct CallDef {
+    pub from: Address,
+    pub to: Address,
+    #[serde(with = "decimal")]
+    pub value: U256,
+    #[serde(with = "decimal")]
+    pub gas: U256,
+    pub input: Bytes,
+    pub call_type: CallType,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inspectors::{Uniswap, ERC20},
+        reducers::{ArbitrageReducer, TradeReducer},
+        test_helpers::*,
+        types::{
+            actions::{AddLiquidity, Liquidation, ProfitableLiquidation, SpecificAction, Transfer},
+            evaluation::ActionType,
+            Classification, Evaluation, Inspection, Status,
+        },
+        Inspector, Reducer,
+    };
+    use ethers::types::U256;
+    use std::collections::HashSet;
+
+    fn round_trip<T>(value: &T) -> serde_json::Value
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let json = serde_json::to_string(value).unwrap();
+        let decoded: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(&decoded, value);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn inspection_round_trip() {
+        let mut inspection = read_trace("triangular_arb.json");
+        ERC20::new().inspect(&mut inspection);
+        Uniswap::new().inspect(&mut inspection);
+        TradeReducer::new().reduce(&mut inspection);
+        ArbitrageReducer::new().reduce(&mut inspection);
+        inspection.prune();
+
+        let json = round_trip(&inspection);
+        assert_eq!(json["status"], "Success");
+        assert_eq!(json["protocols"][0], "Uniswap");
+        assert_eq!(
+            json["from"],
+            format!("0x{}", hex::encode(inspection.from.as_bytes()))
+        );
+        assert_eq!(
+            json["actions"][0]["Known"]["action"]["Arbitrage"]["profit"],
+            "9196963592118237"
+        );
+        // unknown calls keep their trace address and amounts
+        let unknown = &json["actions"][1]["Unknown"];
+        assert!(unknown["trace_address"].is_array());
+        assert!(unknown["call"]["gas"]
+            .as_str()
+            .unwrap()
+            .parse::<u64>()
+            .is_ok());
+    }
+
+    #[test]
+    fn actions_round_trip() {
+        let addrs = addrs();
+        let transfer = Transfer {
+            from: addrs[0],
+            to: addrs[1],
+            amount: U256::MAX,
+            token: addrs[2],
+        };
+        let liquidation = Liquidation {
+            sent_token: addrs[0],
+            sent_amount: 1.into(),
+            received_token: addrs[1],
+            received_amount: 2.into(),
+            from: addrs[2],
+            liquidated_user: addrs[3],
+        };
+        let actions = vec![
+            Classification::new(transfer, vec![0, 1]),
+            Classification::new(
+                ProfitableLiquidation {
+                    liquidation,
+                    profit: 1.into(),
+                    token: addrs[1],
+                },
+                vec![2],
+            ),
+            Classification::new(
+                AddLiquidity {
+                    tokens: vec![addrs[0], addrs[1]],
+                    amounts: vec![1.into(), 2.into()],
+                },
+                vec![3],
+            ),
+            Classification::new(SpecificAction::Unclassified(vec![1, 2].into()), vec![4]),
+            Classification::new(SpecificAction::LiquidationCheck, vec![5]),
+            mk_call(addrs[0], addrs[1], 5, vec![].into(), vec![6]),
+            Classification::Prune,
+        ];
+
+        let json = round_trip(&actions);
+        let transfer = &json[0]["Known"];
+        assert_eq!(transfer["trace_address"], serde_json::json!([0, 1]));
+        assert_eq!(
+            transfer["action"]["Transfer"]["amount"],
+            U256::MAX.to_string()
+        );
+        assert_eq!(
+            json[2]["Known"]["action"]["AddLiquidity"]["amounts"],
+            serde_json::json!(["1", "2"])
+        );
+        assert_eq!(json[3]["Known"]["action"]["Unclassified"], "0x0102");
+        assert_eq!(json[4]["Known"]["action"], "LiquidationCheck");
+        assert_eq!(json[5]["Unknown"]["call"]["value"], "5");
+        assert_eq!(json[6], "Prune");
+    }
+
+    #[test]
+    fn evaluation_round_trip() {
+        let mut inspection: Inspection = mk_inspection(vec![]);
+        inspection.status = Status::Checked;
+        let mut actions = HashSet::new();
+        actions.insert(ActionType::Trade);
+        actions.insert(ActionType::Arbitrage);
+        let evaluation = Evaluation {
+            inspection,
+            gas_used: 21000.into(),
+            gas_price: U256::exp10(11),
+            actions,
+            profit: U256::exp10(20),
+        };
+
+        let json = round_trip(&evaluation);
+        assert_eq!(json["gas_price"], "100000000000");
+        assert_eq!(json["profit"], "100000000000000000000");
+        // sets are written in a stable order
+        assert_eq!(json["actions"], serde_json::json!(["Arbitrage", "Trade"]));
+        assert_eq!(json["inspection"]["status"], "Checked");
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    addresses::{ETH, WETH},
+    registry::registry,
+    types::{actions::SpecificAction, serialization::decimal, Inspection, Protocol, Status},
+};
+
+use ethers::types::{Address, TxHash, I256, U256};
+
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// A condensed, serializable report of an inspection
+pub struct Summary {
+    pub hash: TxHash,
+    pub block_number: u64,
+    pub status: Status,
+    /// All the involved protocols
+    pub protocols: BTreeSet<Protocol>,
+    /// How many times each kind of action was found
+    pub actions: BTreeMap<&'static str, usize>,
+    /// The sender, the contract and all the counterparties of the actions
+    pub addresses: Vec<Participant>,
+    /// The tokens which the sender and its contracts received or sent
+    pub flows: Vec<TokenFlow>,
+    /// How many calls could not be classified
+    pub unknown_calls: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// An address along with its name in the address book, if any
+pub struct Participant {
+    pub address: Address,
+    pub name: Option<String>,
+}
+
+impl fmt::Display for Participant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(ref name) => write!(f, "{} ({:?})", name, self.address),
+            None => write!(f, "{:?}", self.address),
+        }
+    }
+}
+
+impl From<Address> for Participant {
+    fn from(address: Address) -> Self {
+        Self {
+            address,
+            name: registry().label(&address).cloned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The amounts of a token that flowed in and out of the sender and its contracts
+pub struct TokenFlow {
+    pub token: Participant,
+    #[serde(serialize_with = "decimal::serialize")]
+    pub received: U256,
+    #[serde(serialize_with = "decimal::serialize")]
+    pub sent: U256,
+    /// The received minus the sent amount
+    #[serde(serialize_with = "decimal::serialize")]
+    pub net: I256,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Found: {:?} in block {}", self.hash, self.block_number)?;
+        writeln!(f, "Status: {:?}", self.status)?;
+        writeln!(f, "Protocols: {:?}", self.protocols)?;
+        writeln!(f, "Actions: {:?}", self.actions)?;
+        writeln!(f, "Addresses:")?;
+        for address in &self.addresses {
+            writeln!(f, "  {}", address)?;
+        }
+        writeln!(f, "Flows:")?;
+        for flow in &self.flows {
+            writeln!(
+                f,
+                "  {}: {} (received {}, sent {})",
+                flow.token, flow.net, flow.received, flow.sent
+            )?;
+        }
+        write!(f, "Unknown calls: {}", self.unknown_calls)
+    }
+}
+
+impl Summary {
+    pub(crate) fn new(inspection: &Inspection) -> Self {
+        let searcher = std::iter::once(inspection.from)
+            .chain(std::iter::once(inspection.contract))
+            .chain(inspection.proxy_impl)
+            .collect::<BTreeSet<_>>();
+
+        let mut actions = BTreeMap::new();
+        let mut addresses = vec![inspection.from, inspection.contract];
+        addresses.extend(inspection.proxy_impl);
+        // (token, received, sent)
+        let mut flows: BTreeMap<Address, (U256, U256)> = BTreeMap::new();
+        let mut flow = |from: Address, to: Address, token: Address, amount: U256| {
+            let entry = flows.entry(token).or_default();
+            if searcher.contains(&to) {
+                entry.0 += amount;
+            }
+            if searcher.contains(&from) {
+                entry.1 += amount;
+            }
+        };
+
+        for action in inspection.known() {
+            *actions.entry(action.action.kind()).or_insert(0) += 1;
+            match action.action {
+                SpecificAction::Transfer(t) => {
+                    flow(t.from, t.to, t.token, t.amount);
+                    addresses.extend(&[t.from, t.to]);
+                }
+                SpecificAction::Trade(t) => {
+                    flow(t.t1.from, t.t1.to, t.t1.token, t.t1.amount);
+                    flow(t.t2.from, t.t2.to, t.t2.token, t.t2.amount);
+                    addresses.extend(&[t.t1.from, t.t1.to, t.t2.from, t.t2.to]);
+                }
+                SpecificAction::WethDeposit(d) => {
+                    flow(d.from, *WETH, *ETH, d.amount);
+                    flow(*WETH, d.from, *WETH, d.amount);
+                    addresses.push(d.from);
+                }
+                SpecificAction::WethWithdrawal(w) => {
+                    flow(w.to, *WETH, *WETH, w.amount);
+                    flow(*WETH, w.to, *ETH, w.amount);
+                    addresses.push(w.to);
+                }
+                SpecificAction::Arbitrage(a) => {
+                    flow(Address::zero(), a.to, a.token, a.profit);
+                    addresses.push(a.to);
+                }
+                SpecificAction::Liquidation(l) => {
+                    flow(l.from, l.liquidated_user, l.sent_token, l.sent_amount);
+                    flow(
+                        l.liquidated_user,
+                        l.from,
+                        l.received_token,
+                        l.received_amount,
+                    );
+                    addresses.extend(&[l.from, l.liquidated_user]);
+                }
+                SpecificAction::ProfitableLiquidation(p) => {
+                    let l = p.liquidation;
+                    flow(l.from, l.liquidated_user, l.sent_token, l.sent_amount);
+                    flow(
+                        l.liquidated_user,
+                        l.from,
+                        l.received_token,
+                        l.received_amount,
+                    );
+                    addresses.extend(&[l.from, l.liquidated_user]);
+                }
+                _ => {}
+            }
+        }
+
+        // keep the first occurrence of each address
+        let mut seen = BTreeSet::new();
+        addresses.retain(|address| seen.insert(*address));
+
+        Self {
+            hash: inspection.hash,
+            block_number: inspection.block_number,
+            status: inspection.status.clone(),
+            protocols: inspection.protocols.iter().cloned().collect(),
+            actions,
+            addresses: addresses.into_iter().map(Participant::from).collect(),
+            flows: flows
+                .into_iter()
+                .filter(|(_, (received, sent))| !received.is_zero() || !sent.is_zero())
+                .map(|(token, (received, sent))| TokenFlow {
+                    token: token.into(),
+                    received,
+                    sent,
+                    net: I256::from_raw(received).saturating_sub(I256::from_raw(sent)),
+                })
+                .collect(),
+            unknown_calls: inspection.unknown().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inspectors::{Uniswap, ERC20},
+        reducers::{ArbitrageReducer, TradeReducer},
+        test_helpers::*,
+        types::{
+            actions::{Trade, Transfer},
+            Classification, Protocol,
+        },
+        Inspector, Reducer,
+    };
+    use ethers::types::U256;
+
+    #[test]
+    // https://etherscan.io/tx/0xddbf97f758bd0958487e18d9e307cd1256b1ad6763cd34090f4c9720ba1b4acc
+    fn summarizes_arbitrage() {
+        let mut inspection = read_trace("triangular_arb.json");
+        ERC20::new().inspect(&mut inspection);
+        Uniswap::new().inspect(&mut inspection);
+        TradeReducer::new().reduce(&mut inspection);
+        ArbitrageReducer::new().reduce(&mut inspection);
+        inspection.prune();
+
+        let summary = inspection.summary();
+        assert_eq!(summary.actions.get("Arbitrage"), Some(&1));
+        assert_eq!(summary.actions.len(), 1);
+        assert_eq!(summary.unknown_calls, 2);
+        assert!(summary.protocols.contains(&Protocol::Uniswap));
+        assert_eq!(summary.addresses[0].address, inspection.from);
+
+        let profit = U256::from_dec_str("9196963592118237").unwrap();
+        assert_eq!(summary.flows.len(), 1);
+        let flow = &summary.flows[0];
+        assert_eq!(flow.token.name.as_deref(), Some("WETH"));
+        assert_eq!(flow.received, profit);
+        assert_eq!(flow.sent, U256::zero());
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["status"], "Success");
+        assert_eq!(json["actions"]["Arbitrage"], 1);
+        assert_eq!(json["flows"][0]["net"], "9196963592118237");
+    }
+
+    #[test]
+    fn lists_both_sides_of_trades() {
+        let addrs = addrs();
+        let (trader, pool, recipient, usdc, weth) =
+            (addrs[0], addrs[1], addrs[2], addrs[3], addrs[4]);
+        // the trade pays out to someone else than the trader
+        let trade = Trade {
+            t1: Transfer {
+                from: trader,
+                to: pool,
+                amount: 2000.into(),
+                token: usdc,
+            },
+            t2: Transfer {
+                from: pool,
+                to: recipient,
+                amount: 1.into(),
+                token: weth,
+            },
+        };
+        let mut inspection = mk_inspection(vec![Classification::new(trade, vec![])]);
+        inspection.from = trader;
+        inspection.contract = pool;
+
+        let addresses = inspection
+            .summary()
+            .addresses
+            .iter()
+            .map(|participant| participant.address)
+            .collect::<Vec<_>>();
+        assert_eq!(addresses, vec![trader, pool, recipient]);
+    }
+}